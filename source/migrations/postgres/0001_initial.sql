@@ -0,0 +1,57 @@
+-- Resources saved from discord messages. Older deployments may have the
+-- table created by the old `_startup` (resource_id, integer user ids, no
+-- shash), bring it in line with `Resource`.
+CREATE TABLE IF NOT EXISTS resources
+(
+  id BIGSERIAL PRIMARY KEY,
+  user_id TEXT NOT NULL,
+  channel_id TEXT NOT NULL,
+  url TEXT NOT NULL,
+  description TEXT NOT NULL,
+  type_id INTEGER NOT NULL DEFAULT 10,
+  shash TEXT NOT NULL DEFAULT ''
+);
+
+DO $$
+BEGIN
+  IF EXISTS (SELECT 1 FROM information_schema.columns
+             WHERE table_name = 'resources' AND column_name = 'resource_id') THEN
+    ALTER TABLE resources RENAME COLUMN resource_id TO id;
+  END IF;
+END $$;
+
+ALTER TABLE resources ADD COLUMN IF NOT EXISTS shash TEXT NOT NULL DEFAULT '';
+ALTER TABLE resources ALTER COLUMN id TYPE BIGINT;
+ALTER TABLE resources ALTER COLUMN user_id TYPE TEXT;
+ALTER TABLE resources ALTER COLUMN channel_id TYPE TEXT;
+ALTER TABLE resources ALTER COLUMN url TYPE TEXT;
+
+-- The legacy url and description allowed NULL, `Resource` reads them as text
+UPDATE resources SET url = '' WHERE url IS NULL;
+UPDATE resources SET description = '' WHERE description IS NULL;
+ALTER TABLE resources ALTER COLUMN url SET NOT NULL;
+ALTER TABLE resources ALTER COLUMN description SET DEFAULT '';
+ALTER TABLE resources ALTER COLUMN description SET NOT NULL;
+
+CREATE SEQUENCE IF NOT EXISTS resources_id_seq OWNED BY resources.id;
+ALTER TABLE resources ALTER COLUMN id SET DEFAULT nextval('resources_id_seq');
+SELECT setval('resources_id_seq', COALESCE(MAX(id), 0) + 1, false) FROM resources;
+
+CREATE INDEX IF NOT EXISTS ix_resources_user ON resources (user_id);
+CREATE INDEX IF NOT EXISTS ix_resources_type ON resources (type_id);
+
+-- Resource types, the legacy table used pk_types/type
+CREATE TABLE IF NOT EXISTS types
+(
+  id INTEGER PRIMARY KEY,
+  name TEXT NOT NULL
+);
+
+DO $$
+BEGIN
+  IF EXISTS (SELECT 1 FROM information_schema.columns
+             WHERE table_name = 'types' AND column_name = 'pk_types') THEN
+    ALTER TABLE types RENAME COLUMN pk_types TO id;
+    ALTER TABLE types RENAME COLUMN type TO name;
+  END IF;
+END $$;
@@ -0,0 +1,11 @@
+-- Full text search over the saved url and description. The description
+-- already carries the embed title, it weighs more than the url.
+ALTER TABLE resources ADD COLUMN IF NOT EXISTS search_vector tsvector
+  GENERATED ALWAYS AS (
+    setweight(to_tsvector('simple', coalesce(description, '')), 'A') ||
+    setweight(to_tsvector('simple', coalesce(url, '')), 'B')
+  ) STORED;
+
+CREATE INDEX IF NOT EXISTS ix_resources_search ON resources USING GIN (search_vector);
+
+DROP INDEX IF EXISTS ix_resources_description;
@@ -0,0 +1,5 @@
+-- When a resource was saved, used by the after:/before: search filters
+ALTER TABLE resources ADD COLUMN IF NOT EXISTS created_at TIMESTAMPTZ NOT NULL DEFAULT now();
+
+CREATE INDEX IF NOT EXISTS ix_resources_created_at ON resources (created_at);
+CREATE INDEX IF NOT EXISTS ix_resources_channel ON resources (channel_id);
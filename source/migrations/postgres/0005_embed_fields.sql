@@ -0,0 +1,21 @@
+-- Embed data gets its own columns instead of a single description blob.
+-- Old rows stored the url with JSON quotes around it.
+UPDATE resources SET url = replace(url, '"', '');
+
+ALTER TABLE resources ADD COLUMN IF NOT EXISTS title TEXT NOT NULL DEFAULT '';
+ALTER TABLE resources ADD COLUMN IF NOT EXISTS provider TEXT NOT NULL DEFAULT '';
+ALTER TABLE resources ADD COLUMN IF NOT EXISTS author TEXT NOT NULL DEFAULT '';
+ALTER TABLE resources ADD COLUMN IF NOT EXISTS thumbnail_url TEXT NOT NULL DEFAULT '';
+ALTER TABLE resources ADD COLUMN IF NOT EXISTS video_url TEXT NOT NULL DEFAULT '';
+
+DROP INDEX IF EXISTS ix_resources_search;
+ALTER TABLE resources DROP COLUMN IF EXISTS search_vector;
+ALTER TABLE resources ADD COLUMN search_vector tsvector
+  GENERATED ALWAYS AS (
+    setweight(to_tsvector('simple', title), 'A') ||
+    setweight(to_tsvector('simple', description), 'B') ||
+    setweight(to_tsvector('simple', provider || ' ' || author), 'C') ||
+    setweight(to_tsvector('simple', url), 'C')
+  ) STORED;
+
+CREATE INDEX IF NOT EXISTS ix_resources_search ON resources USING GIN (search_vector);
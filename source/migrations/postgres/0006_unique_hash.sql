@@ -0,0 +1,6 @@
+-- shash used to be a DefaultHasher value, which changes between builds.
+-- Legacy rows get no hash and don't take part in duplicate detection.
+UPDATE resources SET shash = '' WHERE length(shash) <> 64;
+
+CREATE UNIQUE INDEX IF NOT EXISTS ux_resources_guild_hash
+  ON resources (guild_id, shash) WHERE shash <> '';
@@ -0,0 +1,8 @@
+-- Default reply language of a server and the per user override
+ALTER TABLE guild_settings ADD COLUMN IF NOT EXISTS locale TEXT NOT NULL DEFAULT '';
+
+CREATE TABLE IF NOT EXISTS user_settings
+(
+  user_id TEXT PRIMARY KEY,
+  locale TEXT NOT NULL DEFAULT ''
+);
@@ -0,0 +1,17 @@
+-- Per server command prefix, channel allowlist and role permissions
+ALTER TABLE guild_settings ADD COLUMN IF NOT EXISTS prefix TEXT NOT NULL DEFAULT '';
+
+CREATE TABLE IF NOT EXISTS guild_channels
+(
+  guild_id TEXT NOT NULL,
+  channel_id TEXT NOT NULL,
+  PRIMARY KEY (guild_id, channel_id)
+);
+
+CREATE TABLE IF NOT EXISTS guild_roles
+(
+  guild_id TEXT NOT NULL,
+  role_id TEXT NOT NULL,
+  permission TEXT NOT NULL,
+  PRIMARY KEY (guild_id, role_id)
+);
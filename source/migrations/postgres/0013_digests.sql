@@ -0,0 +1,13 @@
+-- Weekly digest: where and when each server gets it, and when it got the
+-- last one so the next covers what came after
+ALTER TABLE guild_settings ADD COLUMN IF NOT EXISTS digest_channel TEXT NOT NULL DEFAULT '';
+ALTER TABLE guild_settings ADD COLUMN IF NOT EXISTS digest_day INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE guild_settings ADD COLUMN IF NOT EXISTS digest_hour INTEGER NOT NULL DEFAULT 9;
+
+CREATE TABLE IF NOT EXISTS digests
+(
+  guild_id TEXT NOT NULL,
+  posted_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS ix_digests_guild ON digests (guild_id, posted_at);
@@ -0,0 +1,9 @@
+-- Votes of 1 to 5 stars, one per user and resource
+CREATE TABLE IF NOT EXISTS ratings
+(
+  resource_id BIGINT NOT NULL REFERENCES resources (id) ON DELETE CASCADE,
+  user_id TEXT NOT NULL,
+  score INTEGER NOT NULL CHECK (score BETWEEN 1 AND 5),
+  rated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  PRIMARY KEY (resource_id, user_id)
+);
@@ -0,0 +1,29 @@
+-- Personal lists: resources a user bookmarked, and named collections of
+-- resources in the order their owner gave them
+CREATE TABLE IF NOT EXISTS bookmarks
+(
+  user_id TEXT NOT NULL,
+  resource_id BIGINT NOT NULL REFERENCES resources (id) ON DELETE CASCADE,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  PRIMARY KEY (user_id, resource_id)
+);
+
+CREATE TABLE IF NOT EXISTS collections
+(
+  id BIGSERIAL PRIMARY KEY,
+  guild_id TEXT NOT NULL,
+  user_id TEXT NOT NULL,
+  name TEXT NOT NULL,
+  description TEXT NOT NULL DEFAULT '',
+  created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  UNIQUE (guild_id, user_id, name)
+);
+
+CREATE TABLE IF NOT EXISTS collection_items
+(
+  collection_id BIGINT NOT NULL REFERENCES collections (id) ON DELETE CASCADE,
+  resource_id BIGINT NOT NULL REFERENCES resources (id) ON DELETE CASCADE,
+  position INTEGER NOT NULL,
+  note TEXT NOT NULL DEFAULT '',
+  PRIMARY KEY (collection_id, resource_id)
+);
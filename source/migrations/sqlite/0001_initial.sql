@@ -0,0 +1,21 @@
+-- Resources saved from discord messages
+CREATE TABLE IF NOT EXISTS resources
+(
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  user_id TEXT NOT NULL,
+  channel_id TEXT NOT NULL,
+  url TEXT NOT NULL,
+  description TEXT NOT NULL,
+  type_id INTEGER NOT NULL DEFAULT 10,
+  shash TEXT NOT NULL DEFAULT ''
+);
+
+CREATE INDEX IF NOT EXISTS ix_resources_user ON resources (user_id);
+CREATE INDEX IF NOT EXISTS ix_resources_type ON resources (type_id);
+
+-- Resource types
+CREATE TABLE IF NOT EXISTS types
+(
+  id INTEGER PRIMARY KEY,
+  name TEXT NOT NULL
+);
@@ -0,0 +1,2 @@
+-- SQLite matches every search term with LIKE, there is nothing to build
+SELECT 1;
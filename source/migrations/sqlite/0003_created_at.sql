@@ -0,0 +1,6 @@
+-- When a resource was saved, used by the after:/before: search filters.
+-- SQLite can't add a column defaulting to the current time, inserts set it.
+ALTER TABLE resources ADD COLUMN created_at TEXT NOT NULL DEFAULT '1970-01-01 00:00:00';
+
+CREATE INDEX IF NOT EXISTS ix_resources_created_at ON resources (created_at);
+CREATE INDEX IF NOT EXISTS ix_resources_channel ON resources (channel_id);
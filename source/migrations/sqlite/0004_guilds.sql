@@ -0,0 +1,10 @@
+-- Resources belong to the discord server (guild) they were saved in
+ALTER TABLE resources ADD COLUMN guild_id TEXT NOT NULL DEFAULT '';
+
+CREATE INDEX IF NOT EXISTS ix_resources_guild ON resources (guild_id);
+
+CREATE TABLE IF NOT EXISTS guild_settings
+(
+  guild_id TEXT PRIMARY KEY,
+  share_global INTEGER NOT NULL DEFAULT 0
+);
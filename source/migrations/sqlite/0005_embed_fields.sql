@@ -0,0 +1,9 @@
+-- Embed data gets its own columns instead of a single description blob.
+-- Old rows stored the url with JSON quotes around it.
+UPDATE resources SET url = replace(url, '"', '');
+
+ALTER TABLE resources ADD COLUMN title TEXT NOT NULL DEFAULT '';
+ALTER TABLE resources ADD COLUMN provider TEXT NOT NULL DEFAULT '';
+ALTER TABLE resources ADD COLUMN author TEXT NOT NULL DEFAULT '';
+ALTER TABLE resources ADD COLUMN thumbnail_url TEXT NOT NULL DEFAULT '';
+ALTER TABLE resources ADD COLUMN video_url TEXT NOT NULL DEFAULT '';
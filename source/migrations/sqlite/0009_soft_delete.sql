@@ -0,0 +1,8 @@
+-- Deleted resources are kept so admins can restore them
+ALTER TABLE resources ADD COLUMN deleted_at TEXT;
+ALTER TABLE resources ADD COLUMN deleted_by TEXT NOT NULL DEFAULT '';
+
+-- A deleted resource can be saved again
+DROP INDEX IF EXISTS ux_resources_guild_hash;
+CREATE UNIQUE INDEX ux_resources_guild_hash
+  ON resources (guild_id, shash) WHERE shash <> '' AND deleted_at IS NULL;
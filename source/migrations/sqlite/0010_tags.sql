@@ -0,0 +1,15 @@
+-- Tags shared by every guild, linked to resources many to many
+CREATE TABLE IF NOT EXISTS tags
+(
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  name TEXT NOT NULL UNIQUE
+);
+
+CREATE TABLE IF NOT EXISTS resource_tags
+(
+  resource_id INTEGER NOT NULL REFERENCES resources (id) ON DELETE CASCADE,
+  tag_id INTEGER NOT NULL REFERENCES tags (id) ON DELETE CASCADE,
+  PRIMARY KEY (resource_id, tag_id)
+);
+
+CREATE INDEX IF NOT EXISTS ix_resource_tags_tag ON resource_tags (tag_id);
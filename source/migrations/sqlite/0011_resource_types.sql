@@ -0,0 +1,19 @@
+-- Types assigned by `ResourceType::classify`, ids match the enum
+INSERT INTO types (id, name) VALUES
+  (1, 'video'),
+  (2, 'article'),
+  (3, 'repository'),
+  (4, 'documentation'),
+  (5, 'course'),
+  (6, 'podcast'),
+  (7, 'image'),
+  (8, 'pdf'),
+  (10, 'link')
+ON CONFLICT (id) DO UPDATE SET name = excluded.name;
+
+-- Classify what was saved before with the simplest rules
+UPDATE resources SET type_id = 1
+  WHERE type_id = 10 AND (url LIKE '%youtube.com/watch%' OR url LIKE '%vimeo.com/%');
+UPDATE resources SET type_id = 3
+  WHERE type_id = 10 AND (url LIKE '%://github.com/%' OR url LIKE '%://gitlab.com/%');
+UPDATE resources SET type_id = 8 WHERE type_id = 10 AND lower(url) LIKE '%.pdf';
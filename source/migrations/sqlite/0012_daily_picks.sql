@@ -0,0 +1,14 @@
+-- Resource of the day: where and when each server gets it, and what it
+-- got lately so it isn't repeated
+ALTER TABLE guild_settings ADD COLUMN daily_channel TEXT NOT NULL DEFAULT '';
+ALTER TABLE guild_settings ADD COLUMN daily_hour INTEGER NOT NULL DEFAULT 12;
+ALTER TABLE guild_settings ADD COLUMN daily_window INTEGER NOT NULL DEFAULT 30;
+
+CREATE TABLE IF NOT EXISTS daily_picks
+(
+  guild_id TEXT NOT NULL,
+  resource_id INTEGER NOT NULL REFERENCES resources (id) ON DELETE CASCADE,
+  picked_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+CREATE INDEX IF NOT EXISTS ix_daily_picks_guild ON daily_picks (guild_id, picked_at);
@@ -2,85 +2,427 @@ extern crate discord;
 
 mod lib;
 
-use discord::model::{Event, Message};
-use discord::Discord;
+use discord::model::{ChannelId, Event, Message, Reaction, ReactionEmoji};
+use discord::{Discord, State};
 use lib::bot_state::BotState;
-use lib::commands::cmd_save_resource::cmd_save_resource;
-use lib::commands::cmd_search_resource::cmd_search_resource;
-use lib::commands::cmd_status::cmd_status;
-use lib::commands::cmd_test::cmd_test;
-use lib::custom_database::DiscordDatabase;
-use regex::Regex;
+use lib::commands::command::Command;
+use lib::commands::response::Response;
+use lib::commands::{
+    cmd_config, cmd_rate, cmd_search_resource, registry, run_command, run_message,
+};
+use lib::gateway::{DiscordUpstream, Gateway};
+use lib::i18n::tr;
+use lib::interactions::{tag_choices, Interaction, InteractionClient, SLASH_PREFIX};
+use lib::migrations;
+use lib::resource_store;
+use lib::scheduler;
+use std::collections::HashMap;
 use std::env;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{mpsc, Semaphore};
 
-fn commands_array() -> Vec<(Regex, fn(s: &mut BotState, msg: &Message) -> String)> {
-    let mut commands: Vec<(Regex, fn(s: &mut BotState, msg: &Message) -> String)> = Vec::new();
-    commands.push((Regex::new(r"^!test").unwrap(), cmd_test));
-    commands.push((Regex::new(r"^!save ").unwrap(), cmd_save_resource));
-    commands.push((Regex::new(r"^!search ").unwrap(), cmd_search_resource));
-    commands.push((Regex::new(r"^!status").unwrap(), cmd_status));
+/// Commands running at the same time when `BOT_WORKERS` isn't set
+const DEFAULT_WORKERS: usize = 4;
+/// Gateway events waiting for the event loop
+const EVENT_QUEUE: usize = 256;
 
-    return commands;
+/// How often searches nobody pages through lose their controls
+const SESSION_SWEEP: Duration = Duration::from_secs(30);
+
+/// What the workers run: prefix commands, slash commands and reactions
+enum Work {
+    Message(Message),
+    Interaction(Interaction),
+    Reaction(Reaction),
+    /// A reaction taken back
+    ReactionRemove(Reaction),
+}
+
+impl Work {
+    /// Work of the same channel runs in order, see `Workers::dispatch`
+    fn channel_id(&self) -> ChannelId {
+        return match self {
+            Work::Message(message) => message.channel_id,
+            Work::Interaction(interaction) => interaction.message.channel_id,
+            Work::Reaction(reaction) => reaction.channel_id,
+            Work::ReactionRemove(reaction) => reaction.channel_id,
+        };
+    }
+}
+
+/// The worker pool and the work waiting for it
+#[derive(Clone)]
+struct Workers {
+    pool: Arc<Mutex<Vec<BotState>>>,
+    /// One per worker in the pool
+    permits: Arc<Semaphore>,
+    commands: Arc<Vec<Command>>,
+    client: Arc<InteractionClient>,
+    /// Work waiting for its turn, by channel
+    queues: Arc<Mutex<HashMap<ChannelId, mpsc::UnboundedSender<Work>>>>,
+}
+
+impl Workers {
+    /// Queues `work` behind the rest of its channel. Channels run at the
+    /// same time but the work of each one runs in order, so a quick `!save`
+    /// and `!undo` don't swap places.
+    fn dispatch(&self, work: Work) {
+        let channel_id = work.channel_id();
+        let mut queues = self.queues.lock().unwrap();
+        let work = match queues.get(&channel_id) {
+            Some(queue) => match queue.send(work) {
+                Ok(()) => return,
+                Err(mpsc::error::SendError(work)) => work,
+            },
+            None => work,
+        };
+
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let _ = sender.send(work);
+        queues.insert(channel_id, sender);
+        tokio::spawn(self.clone().run_queue(channel_id, receiver));
+    }
+
+    /// Runs the work of `channel_id` one at a time, ends with its queue
+    async fn run_queue(self, channel_id: ChannelId, mut receiver: mpsc::UnboundedReceiver<Work>) {
+        loop {
+            // Looked at with the queues locked, nothing is queued meanwhile
+            let work = {
+                let mut queues = self.queues.lock().unwrap();
+                match receiver.try_recv() {
+                    Ok(work) => work,
+                    Err(_) => {
+                        queues.remove(&channel_id);
+                        return;
+                    }
+                }
+            };
+
+            // Waits for a free worker, slow commands don't stall the gateway
+            let permit = self.permits.clone().acquire_owned().await.unwrap();
+            let workers = self.clone();
+            let _ = tokio::task::spawn_blocking(move || {
+                workers.run(&work);
+                drop(permit);
+            })
+            .await;
+        }
+    }
+
+    /// Runs `work` on a worker of the pool, the caller holds a permit
+    fn run(&self, work: &Work) {
+        let mut state = self
+            .pool
+            .lock()
+            .unwrap()
+            .pop()
+            .expect("A worker per permit");
+        let result = panic::catch_unwind(AssertUnwindSafe(|| match work {
+            Work::Message(message) => process_message(message, &self.commands, &mut state),
+            Work::Interaction(interaction) => {
+                process_interaction(interaction, &self.commands, &mut state, &self.client)
+            }
+            Work::Reaction(reaction) => process_reaction(reaction, &mut state),
+            Work::ReactionRemove(reaction) => process_reaction_remove(reaction, &mut state),
+        }));
+        if result.is_err() {
+            let content = match work {
+                Work::Message(message) => message.content.clone(),
+                Work::Interaction(interaction) => interaction.message.content.clone(),
+                Work::Reaction(reaction) | Work::ReactionRemove(reaction) => {
+                    format!("reaction to {}", reaction.message_id)
+                }
+            };
+            println!("Command panicked: {}", content);
+        }
+        self.pool.lock().unwrap().push(state);
+    }
+}
+
+fn process_message(message: &Message, commands: &[Command], state: &mut BotState) {
+    let response = match run_message(commands, state, message) {
+        Some(response) => response,
+        None => return,
+    };
+    let search = state.pending_search.take();
+
+    *state.last_command.lock().unwrap() = Some(message.clone());
+
+    match response {
+        Response::Text(text) => {
+            let _ = state
+                .discord
+                .send_message(message.channel_id, text.as_str(), "", false);
+        }
+        Response::Embed(embed) => {
+            let sent = state
+                .discord
+                .send_embed(message.channel_id, "", |builder| embed.build(builder));
+            if let (Ok(sent), Some(search)) = (sent, search) {
+                cmd_search_resource::start_session(state, search, sent.id);
+            }
+        }
+        Response::Reaction(emoji) => {
+            let _ = state.discord.add_reaction(
+                message.channel_id,
+                message.id,
+                ReactionEmoji::Unicode(emoji),
+            );
+        }
+        Response::Nothing => {}
+    }
 }
 
-fn process_message(
-    message: &Message,
-    commands_array: &Vec<(Regex, fn(s: &mut BotState, msg: &Message) -> String)>,
+fn process_reaction(reaction: &Reaction, state: &mut BotState) {
+    // The bot adds the controls itself
+    if reaction.user_id == state.cache.read().unwrap().user().id {
+        return;
+    }
+    cmd_search_resource::turn_page(state, reaction);
+    cmd_rate::rate_by_reaction(state, reaction);
+}
+
+fn process_reaction_remove(reaction: &Reaction, state: &mut BotState) {
+    // The controls the bot takes back off its messages carry no votes
+    if reaction.user_id == state.cache.read().unwrap().user().id {
+        return;
+    }
+    cmd_rate::unrate_by_reaction(state, reaction);
+}
+
+/// Slash commands run the same handlers as prefix commands, their message
+/// is the stand-in built by `Interaction`
+fn process_interaction(
+    interaction: &Interaction,
+    commands: &[Command],
     state: &mut BotState,
+    client: &InteractionClient,
 ) {
-    let content: &str = message.content.as_str();
-    for (command, function) in commands_array {
-        if command.is_match(content) {
-            let response = function(state, message);
-
-            state.last_command = Some(message.clone());
-
-            if !response.is_empty() {
-                let _ =
-                    state
-                        .discord
-                        .send_message(message.channel_id, response.as_str(), "", false);
-            }
-            break;
+    let message = &interaction.message;
+    let command = match commands
+        .iter()
+        .find(|command| command.name == interaction.command)
+    {
+        Some(command) => command,
+        None => return,
+    };
+
+    if interaction.is_autocomplete() {
+        let typed = interaction
+            .focused
+            .as_ref()
+            .and_then(|name| interaction.options.get(name))
+            .and_then(|value| value.as_str())
+            .unwrap_or("");
+        let choices = match state.guild_of(message.channel_id) {
+            Some(guild_id) => tag_choices(state, &guild_id.0.to_string(), typed),
+            None => Vec::new(),
+        };
+        client.autocomplete(interaction, &choices);
+        return;
+    }
+
+    let locale = state.locale_of(message);
+
+    // Unlike prefix commands slash commands can't go unanswered
+    if !state.channel_allowed(message) && command.name != cmd_config::NAME {
+        client.respond(interaction, &tr(&locale, "slash.channel_not_allowed"), true);
+        return;
+    }
+    if !client.defer(interaction, command.ephemeral) {
+        return;
+    }
+
+    let response = match command.args_from_options(&locale, SLASH_PREFIX, &interaction.options) {
+        Ok(args) => run_command(command, &args, message, state),
+        Err(error) => Response::error(error),
+    };
+    let search = state.pending_search.take();
+
+    *state.last_command.lock().unwrap() = Some(message.clone());
+
+    // There's no message to react to, the reaction goes with the output
+    // the command left behind
+    let response = match response {
+        Response::Reaction(emoji) => {
+            Response::Text(format!("{} {}", emoji, state.last_command_output))
         }
+        Response::Nothing if !state.last_command_output.is_empty() => {
+            Response::Text(state.last_command_output.clone())
+        }
+        Response::Nothing => Response::Text(tr(&locale, "slash.done")),
+        response => response,
+    };
+    // The reply is the interaction's `@original` message, searches are
+    // paged through there
+    let sent = client.edit_reply(interaction, &response);
+    if let (Some(message_id), Some(search)) = (sent, search) {
+        cmd_search_resource::start_session(state, search, message_id);
     }
 }
 
-fn main() {
+#[tokio::main]
+async fn main() {
     // Get env data
     dotenv::dotenv().expect("Failed to load .env file");
-    let token = env::var("DISCORD_TOKEN").expect("Expected a token in the environment");
     let database_uri = env::var("DATABASE_URI").expect("Expected a token in the environment");
 
-    // Test event
-    let commands_array = commands_array();
+    // Database, `bot migrate [status]` only manages the schema
+    let mut db = resource_store::connect(database_uri.clone());
+    let args: Vec<String> = env::args().collect();
+    if args.get(1).map(|arg| arg.as_str()) == Some("migrate") {
+        let action = args.get(2).map(|arg| arg.as_str());
+        let argument = args.get(3).map(|arg| arg.as_str());
+        if let Err(err) = migrations::run_command(db.as_mut(), action, argument) {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+        return;
+    }
+    migrations::migrate(db.as_mut()).expect("Failed to apply migrations");
+
+    // Resources from before servers existed go to `LEGACY_GUILD_ID`, the
+    // same as `bot migrate legacy <guild_id>`
+    if let Ok(guild_id) = env::var("LEGACY_GUILD_ID") {
+        if !guild_id.is_empty() {
+            let assigned = migrations::assign_legacy(db.as_mut(), &guild_id)
+                .expect("Failed to assign legacy resources");
+            if assigned > 0 {
+                println!("Assigned {} legacy resources to {}", assigned, guild_id);
+            }
+        }
+    }
+
+    // Only the bot itself needs to log in, `migrate` runs without a token
+    let token = env::var("DISCORD_TOKEN").expect("Expected a token in the environment");
+
+    // Command registry
+    let commands = Arc::new(registry());
+
+    // Log in to Discord using a bot token from the environment
+    let discord = Arc::new(Discord::from_bot_token(&token).expect("login failed"));
+
+    // Establish and use a websocket connection, `DISCORD_GATEWAY_URL`
+    // points the bot to another gateway, e.g. behind a proxy
+    let mut gateway = Gateway::new(DiscordUpstream::new(
+        discord.clone(),
+        &token,
+        env::var("DISCORD_GATEWAY_URL").ok(),
+    ));
+    let (connection, ready) = gateway.connect().expect("connect failed");
+
+    // Slash commands, the application id of a bot is its user id
+    let client = Arc::new(InteractionClient::new(&token, ready.user.id.0));
+    let registering = (client.clone(), commands.clone());
+    tokio::task::spawn_blocking(move || {
+        let (client, commands) = registering;
+        if !client.register(&commands) {
+            println!("Slash commands weren't registered, only prefix commands work");
+        }
+    });
 
     // State generation
-    let mut bot_state = BotState {
-        // Log in to Discord using a bot token from the environment
-        discord: Discord::from_bot_token(&token).expect("login failed"),
-        // Database
-        db: DiscordDatabase::new(database_uri),
-        last_command: None,
-        last_command_output: "".to_string(),
+    let bot_state = BotState::new(
+        discord,
+        State::new(ready),
+        db,
+        // Optional discord id of the bot owner
+        env::var("BOT_OWNER_ID")
+            .ok()
+            .and_then(|id| id.parse::<u64>().ok()),
+        // Optional url of the website, e.g. https://example.com
+        env::var("WEB_URL").ok().filter(|url| !url.is_empty()),
+    )
+    .with_client(client.clone());
+    let cache = bot_state.cache.clone();
+
+    // Searches nobody pages through for a while lose their controls
+    let sweeping = (bot_state.discord.clone(), bot_state.search_sessions.clone());
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(SESSION_SWEEP);
+        loop {
+            interval.tick().await;
+            let (discord, sessions) = (sweeping.0.clone(), sweeping.1.clone());
+            let _ = tokio::task::spawn_blocking(move || {
+                cmd_search_resource::expire_sessions(&discord, &sessions)
+            })
+            .await;
+        }
+    });
+
+    // Worker pool, each worker has its own database connection. Everything
+    // `memory://` saves lives in a single connection, it gets one worker.
+    let worker_count = match database_uri.starts_with("memory://") {
+        true => 1,
+        false => env::var("BOT_WORKERS")
+            .ok()
+            .and_then(|workers| workers.parse::<usize>().ok())
+            .unwrap_or(DEFAULT_WORKERS)
+            .max(1),
     };
+    let mut pool = Vec::with_capacity(worker_count);
+    for _ in 1..worker_count {
+        pool.push(bot_state.worker(resource_store::connect(database_uri.clone())));
+    }
+    pool.push(bot_state);
+    let pool = Arc::new(Mutex::new(pool));
+    let permits = Arc::new(Semaphore::new(worker_count));
 
-    // Establish and use a websocket connection
-    let (mut connection, _) = bot_state.discord.connect().expect("connect failed");
+    // Scheduled posts run on a worker like any command, one tick at a time
+    let scheduling = (pool.clone(), permits.clone());
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(scheduler::TICK);
+        loop {
+            interval.tick().await;
+            let permit = scheduling.1.clone().acquire_owned().await.unwrap();
+            let pool = scheduling.0.clone();
+            let _ = tokio::task::spawn_blocking(move || {
+                let mut state = pool.lock().unwrap().pop().expect("A worker per permit");
+                if panic::catch_unwind(AssertUnwindSafe(|| scheduler::tick(&mut state))).is_err() {
+                    println!("Scheduler panicked");
+                }
+                pool.lock().unwrap().push(state);
+                drop(permit);
+            })
+            .await;
+        }
+    });
+
+    // The gateway connection blocks, it gets its own thread and reconnects
+    // on its own until discord refuses us for good
+    let (sender, mut events) = mpsc::channel::<Event>(EVENT_QUEUE);
+    tokio::task::spawn_blocking(move || gateway.run(connection, sender));
+
+    let workers = Workers {
+        pool: pool,
+        permits: permits,
+        commands: commands,
+        client: client,
+        queues: Arc::new(Mutex::new(HashMap::new())),
+    };
 
     // Event loop
-    println!("Ready.");
-    loop {
-        match connection.recv_event() {
-            Ok(Event::MessageCreate(message)) => {
-                process_message(&message, &commands_array, &mut bot_state);
-            }
-            Ok(_) => {}
-            Err(discord::Error::Closed(code, body)) => {
-                println!("Gateway closed on us with code {:?}: {}", code, body);
-                break;
+    println!("Ready with {} workers.", worker_count);
+    while let Some(event) = events.recv().await {
+        cache.write().unwrap().update(&event);
+
+        let work = match event {
+            Event::MessageCreate(message) => Work::Message(message),
+            Event::ReactionAdd(reaction) => Work::Reaction(reaction),
+            Event::ReactionRemove(reaction) => Work::ReactionRemove(reaction),
+            Event::Unknown(name, data) if name == "INTERACTION_CREATE" => {
+                match Interaction::from_event(&data) {
+                    Some(interaction) => Work::Interaction(interaction),
+                    None => continue,
+                }
             }
-            Err(err) => println!("Receive error: {:?}", err),
-        }
+            _ => continue,
+        };
+
+        workers.dispatch(work);
     }
+
+    println!("Gateway stopped, bye.");
 }
@@ -1,7 +1,7 @@
-use super::custom_database::DiscordDatabase;
+use super::resource_store::ResourceStore;
 use std::sync::Mutex;
 
 #[allow(dead_code)]
 pub struct AppState {
-    pub bd: Mutex<DiscordDatabase>,
+    pub bd: Mutex<Box<dyn ResourceStore + Send>>,
 }
@@ -1,10 +1,314 @@
-use super::custom_database::DiscordDatabase;
-use discord::model::Message;
-use discord::Discord;
+use super::collection::Collection;
+use super::commands::cmd_search_resource::SearchSession;
+use super::commands::command::{Permission, DEFAULT_PREFIX};
+use super::guild_settings::GuildSettings;
+use super::i18n::DEFAULT_LOCALE;
+use super::interactions::InteractionClient;
+use super::resource::Resource;
+use super::resource_store::ResourceStore;
+use discord::model::{
+    ChannelId, Member, Message, MessageId, Permissions, RoleId, ServerId, UserId,
+};
+use discord::{ChannelRef, Discord, State};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
 
+/// What a worker needs to run commands. Every worker has its own database
+/// connection, the rest is shared between them.
 pub struct BotState {
-    pub discord: Discord,
-    pub db: DiscordDatabase,
-    pub last_command: Option<Message>,
+    pub discord: Arc<Discord>,
+    /// REST calls discord-rs doesn't have, `None` when not connected
+    pub client: Option<Arc<InteractionClient>>,
+    /// Servers, channels and members seen through the gateway
+    pub cache: Arc<RwLock<State>>,
+    pub db: Box<dyn ResourceStore + Send>,
+    pub owner_id: Option<u64>,
+    /// Where the website runs, resource pages are linked from there
+    pub web_url: Option<String>,
+    /// Settings of the servers seen so far, by guild id
+    pub guild_settings: Arc<Mutex<HashMap<String, GuildSettings>>>,
+    /// Locales chosen with `!lang` by user id, `None` for users who didn't
+    pub user_locales: Arc<Mutex<HashMap<String, Option<String>>>>,
+    /// Ids saved by the last `!save` of each user in each server, by guild
+    /// and user id, taken back by `!undo`
+    pub last_saves: Arc<Mutex<HashMap<(u64, u64), Vec<i64>>>>,
+    pub last_command: Arc<Mutex<Option<Message>>>,
+    /// Searches that can be paged through, by the message showing them
+    pub search_sessions: Arc<Mutex<HashMap<MessageId, SearchSession>>>,
+    /// Search the running command wants paged through, registered on the
+    /// message its reply ends up in
+    pub pending_search: Option<SearchSession>,
+    /// Output of the last command of any worker, shown by `!status`
+    pub last_output: Arc<Mutex<String>>,
+    /// Output of the command this worker is running, see `process_message`
     pub last_command_output: String,
 }
+
+impl BotState {
+    pub fn new(
+        discord: Arc<Discord>,
+        cache: State,
+        db: Box<dyn ResourceStore + Send>,
+        owner_id: Option<u64>,
+        web_url: Option<String>,
+    ) -> Self {
+        return Self {
+            discord: discord,
+            client: None,
+            cache: Arc::new(RwLock::new(cache)),
+            db: db,
+            owner_id: owner_id,
+            web_url: web_url,
+            guild_settings: Arc::new(Mutex::new(HashMap::new())),
+            user_locales: Arc::new(Mutex::new(HashMap::new())),
+            last_saves: Arc::new(Mutex::new(HashMap::new())),
+            last_command: Arc::new(Mutex::new(None)),
+            search_sessions: Arc::new(Mutex::new(HashMap::new())),
+            pending_search: None,
+            last_output: Arc::new(Mutex::new("".to_string())),
+            last_command_output: "".to_string(),
+        };
+    }
+
+    /// Makes the REST calls discord-rs doesn't have with `client`
+    pub fn with_client(mut self, client: Arc<InteractionClient>) -> Self {
+        self.client = Some(client);
+        return self;
+    }
+
+    /// Another worker sharing everything with this one but the database
+    pub fn worker(&self, db: Box<dyn ResourceStore + Send>) -> Self {
+        return Self {
+            discord: self.discord.clone(),
+            client: self.client.clone(),
+            cache: self.cache.clone(),
+            db: db,
+            owner_id: self.owner_id,
+            web_url: self.web_url.clone(),
+            guild_settings: self.guild_settings.clone(),
+            user_locales: self.user_locales.clone(),
+            last_saves: self.last_saves.clone(),
+            last_command: self.last_command.clone(),
+            search_sessions: self.search_sessions.clone(),
+            pending_search: None,
+            last_output: self.last_output.clone(),
+            last_command_output: "".to_string(),
+        };
+    }
+
+    /// Server (guild) owning `channel_id`, `None` for private messages
+    pub fn guild_of(&self, channel_id: ChannelId) -> Option<ServerId> {
+        return match self.cache.read().unwrap().find_channel(channel_id) {
+            Some(ChannelRef::Public(server, _)) => Some(server.id),
+            _ => None,
+        };
+    }
+
+    /// Locale to answer `message` in: the author's choice, then the
+    /// server default, then the bot default
+    pub fn locale_of(&mut self, message: &Message) -> String {
+        if let Some(locale) = self.user_locale(&message.author.id.to_string()) {
+            return locale;
+        }
+        if let Some(guild_id) = self.guild_of(message.channel_id) {
+            let settings = self.settings_of(&guild_id.0.to_string());
+            if !settings.locale.is_empty() {
+                return settings.locale;
+            }
+        }
+        return DEFAULT_LOCALE.to_string();
+    }
+
+    /// Locale chosen by `user_id`, loaded from the database the first time
+    pub fn user_locale(&mut self, user_id: &str) -> Option<String> {
+        if let Some(locale) = self.user_locales.lock().unwrap().get(user_id) {
+            return locale.clone();
+        }
+
+        let locale = self.db.user_locale(user_id);
+        self.user_locales
+            .lock()
+            .unwrap()
+            .insert(user_id.to_string(), locale.clone());
+        return locale;
+    }
+
+    /// Persists the locale of `user_id` and refreshes the cache, `false` if
+    /// the database refused it
+    pub fn save_user_locale(&mut self, user_id: &str, locale: &str) -> bool {
+        if !self.db.save_user_locale(user_id, locale) {
+            return false;
+        }
+        self.user_locales
+            .lock()
+            .unwrap()
+            .insert(user_id.to_string(), Some(locale.to_string()));
+        return true;
+    }
+
+    /// Settings of `guild_id`, loaded from the database the first time
+    pub fn settings_of(&mut self, guild_id: &str) -> GuildSettings {
+        if let Some(settings) = self.guild_settings.lock().unwrap().get(guild_id) {
+            return settings.clone();
+        }
+
+        let settings = self.db.guild_settings(guild_id);
+        self.guild_settings
+            .lock()
+            .unwrap()
+            .insert(guild_id.to_string(), settings.clone());
+        return settings;
+    }
+
+    /// Persists `settings` and refreshes the cache, `false` if the database refused them
+    pub fn save_settings(&mut self, settings: GuildSettings) -> bool {
+        if !self.db.save_guild_settings(&settings) {
+            return false;
+        }
+        self.guild_settings
+            .lock()
+            .unwrap()
+            .insert(settings.guild_id.clone(), settings);
+        return true;
+    }
+
+    /// Command prefix used in the channel of `message`
+    pub fn prefix_of(&mut self, message: &Message) -> String {
+        if let Some(guild_id) = self.guild_of(message.channel_id) {
+            let settings = self.settings_of(&guild_id.0.to_string());
+            if !settings.prefix.is_empty() {
+                return settings.prefix;
+            }
+        }
+        return DEFAULT_PREFIX.to_string();
+    }
+
+    /// `false` when the server restricts the bot to other channels
+    pub fn channel_allowed(&mut self, message: &Message) -> bool {
+        return match self.guild_of(message.channel_id) {
+            Some(guild_id) => self
+                .settings_of(&guild_id.0.to_string())
+                .allows_channel(&message.channel_id.0.to_string()),
+            None => true,
+        };
+    }
+
+    /// Page of `resource` on the website, only servers sharing their
+    /// resources have them
+    pub fn resource_page(&mut self, resource: &Resource) -> Option<String> {
+        let web_url = self.web_url.clone()?;
+        if !self.settings_of(&resource.guild_id).share_global {
+            return None;
+        }
+        return Some(format!(
+            "{}/guild/{}/resource/{}",
+            web_url.trim_end_matches('/'),
+            resource.guild_id,
+            resource.id
+        ));
+    }
+
+    /// Page of `collection` on the website, like `resource_page` only for
+    /// servers sharing their resources
+    pub fn collection_page(&mut self, collection: &Collection) -> Option<String> {
+        let web_url = self.web_url.clone()?;
+        if !self.settings_of(&collection.guild_id).share_global {
+            return None;
+        }
+        return Some(format!(
+            "{}/guild/{}/collection/{}",
+            web_url.trim_end_matches('/'),
+            collection.guild_id,
+            collection.id
+        ));
+    }
+
+    /// Access level of the author of `message`: the bot owner, then the
+    /// server owner and members with administrative discord permissions,
+    /// then the roles configured with `!config role`
+    pub fn permission_of(&mut self, message: &Message) -> Permission {
+        if self.owner_id == Some(message.author.id.0) {
+            return Permission::BotOwner;
+        }
+
+        let (server_id, owner_id, roles) =
+            match self.cache.read().unwrap().find_channel(message.channel_id) {
+                Some(ChannelRef::Public(server, _)) => {
+                    (server.id, server.owner_id, server.roles.clone())
+                }
+                _ => return Permission::Member,
+            };
+        if owner_id == message.author.id {
+            return Permission::Admin;
+        }
+
+        let member_roles = match self.member_roles(server_id, message.author.id) {
+            Some(member_roles) => member_roles,
+            None => return Permission::Member,
+        };
+
+        let mut permission = Permission::Member;
+        for role in roles.iter().filter(|role| member_roles.contains(&role.id)) {
+            if role.permissions.contains(Permissions::ADMINISTRATOR)
+                || role.permissions.contains(Permissions::MANAGE_SERVER)
+            {
+                return Permission::Admin;
+            }
+        }
+
+        let settings = self.settings_of(&server_id.0.to_string());
+        for granted in &settings.roles {
+            let role_id = match granted.role_id.parse::<u64>() {
+                Ok(role_id) => RoleId(role_id),
+                Err(_) => continue,
+            };
+            if !member_roles.contains(&role_id) {
+                continue;
+            }
+            if let Some(level) = Permission::from_name(&granted.permission) {
+                permission = permission.max(level);
+            }
+        }
+        return permission;
+    }
+
+    /// Name of `user_id` in the server, for replies that would ping them
+    /// with a mention. The id itself when they aren't a member anymore.
+    pub fn member_name(&self, server_id: ServerId, user_id: &str) -> String {
+        let member = match user_id.parse::<u64>() {
+            Ok(id) => self.member(server_id, UserId(id)),
+            Err(_) => None,
+        };
+        return match member {
+            Some(member) => member.nick.unwrap_or(member.user.name),
+            None => user_id.to_string(),
+        };
+    }
+
+    fn member_roles(&self, server_id: ServerId, user_id: UserId) -> Option<Vec<RoleId>> {
+        return self.member(server_id, user_id).map(|member| member.roles);
+    }
+
+    /// `user_id` in the server, from the gateway cache or the REST API for
+    /// members the gateway hasn't sent us
+    fn member(&self, server_id: ServerId, user_id: UserId) -> Option<Member> {
+        let cached = self
+            .cache
+            .read()
+            .unwrap()
+            .servers()
+            .iter()
+            .find(|server| server.id == server_id)
+            .and_then(|server| {
+                server
+                    .members
+                    .iter()
+                    .find(|member| member.user.id == user_id)
+            })
+            .cloned();
+        if cached.is_some() {
+            return cached;
+        }
+        return self.discord.get_member(server_id, user_id).ok();
+    }
+}
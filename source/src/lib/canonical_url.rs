@@ -0,0 +1,133 @@
+use sha2::{Digest, Sha256};
+use url::form_urlencoded;
+use url::Url;
+
+/// Query parameters that only track where a click came from
+const TRACKING_PARAMS: &[&str] = &[
+    "fbclid", "gclid", "dclid", "msclkid", "yclid", "igshid", "mc_cid", "mc_eid", "ref_src",
+    "ref_url", "si", "feature", "_hsenc", "_hsmi",
+];
+
+/// Normal form of `url` so the same link is always written the same way:
+/// lowercase host, no default port, fragment, tracking parameters or
+/// trailing slash, sorted query and youtu.be/shorts links as youtube watch
+/// links. Anything that doesn't parse is returned trimmed.
+pub fn canonicalize(url: &str) -> String {
+    let url = url.trim();
+    let parsed = match Url::parse(url) {
+        Ok(parsed) => parsed,
+        Err(_) => return url.to_string(),
+    };
+    let host = match parsed.host_str() {
+        Some(host) => host.to_lowercase(),
+        None => return url.to_string(),
+    };
+
+    let mut host = host;
+    let mut path = parsed.path().trim_end_matches('/').to_string();
+    let mut params: Vec<(String, String)> = parsed
+        .query_pairs()
+        .filter(|(key, _)| !is_tracking_param(key))
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect();
+
+    // Every youtube video as https://www.youtube.com/watch?v=ID
+    let video_id = match host.as_str() {
+        "youtu.be" => Some(path.trim_start_matches('/').to_string()),
+        "youtube.com" | "www.youtube.com" | "m.youtube.com" if path.starts_with("/shorts/") => {
+            Some(path.trim_start_matches("/shorts/").to_string())
+        }
+        _ => None,
+    };
+    if let Some(video_id) = video_id {
+        if !video_id.is_empty() {
+            host = "www.youtube.com".to_string();
+            path = "/watch".to_string();
+            params.push(("v".to_string(), video_id));
+        }
+    }
+    if host == "youtube.com" || host == "m.youtube.com" {
+        host = "www.youtube.com".to_string();
+    }
+
+    params.sort();
+
+    let mut canonical = format!("{}://{}", parsed.scheme(), host);
+    if let Some(port) = parsed.port() {
+        canonical = format!("{}:{}", canonical, port);
+    }
+    canonical.push_str(&path);
+    if !params.is_empty() {
+        let query = form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(params.iter())
+            .finish();
+        canonical = format!("{}?{}", canonical, query);
+    }
+    return canonical;
+}
+
+/// Stable SHA-256 (hex) identifying the link behind `url`, the same for
+/// http/https and with or without `www.`
+pub fn content_hash(url: &str) -> String {
+    let canonical = canonicalize(url);
+    let key = canonical
+        .trim_start_matches("https://")
+        .trim_start_matches("http://")
+        .trim_start_matches("www.");
+
+    return format!("{:x}", Sha256::digest(key.as_bytes()));
+}
+
+fn is_tracking_param(key: &str) -> bool {
+    let key = key.to_lowercase();
+    return key.starts_with("utm_") || TRACKING_PARAMS.contains(&key.as_str());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drops_what_doesnt_change_the_link() {
+        assert_eq!(
+            canonicalize(" HTTPS://Example.COM/Path/?utm_source=x&b=2&a=1&fbclid=y#top "),
+            "https://example.com/Path?a=1&b=2"
+        );
+        assert_eq!(
+            canonicalize("https://example.com:443/"),
+            "https://example.com"
+        );
+        assert_eq!(
+            canonicalize("http://example.com:8080/a"),
+            "http://example.com:8080/a"
+        );
+    }
+
+    #[test]
+    fn writes_youtube_videos_as_watch_links() {
+        let watch = "https://www.youtube.com/watch?v=abc123";
+        assert_eq!(canonicalize("https://youtu.be/abc123?si=share"), watch);
+        assert_eq!(canonicalize("https://youtube.com/shorts/abc123"), watch);
+        assert_eq!(
+            canonicalize("https://m.youtube.com/watch?feature=share&v=abc123"),
+            watch
+        );
+    }
+
+    #[test]
+    fn leaves_what_isnt_a_url() {
+        assert_eq!(canonicalize("  not a url "), "not a url");
+        assert_eq!(
+            canonicalize("mailto:someone@example.com"),
+            "mailto:someone@example.com"
+        );
+    }
+
+    #[test]
+    fn hashes_the_same_link_the_same_way() {
+        let hash = content_hash("https://www.example.com/a?utm_medium=x");
+        assert_eq!(hash, content_hash("http://example.com/a/"));
+        assert_ne!(hash, content_hash("https://example.com/b"));
+        assert_eq!(hash.len(), 64);
+    }
+}
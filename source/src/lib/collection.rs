@@ -0,0 +1,144 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::resource::Resource;
+
+/// Most resources a collection can hold
+pub const MAX_COLLECTION_ITEMS: usize = 50;
+
+/// A named list of resources a user put together, e.g. a learning path
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct Collection {
+    pub id: i64,
+    pub guild_id: String,
+    pub user_id: String,
+    /// Unique per user and server, normalized like a tag
+    pub name: String,
+    pub description: String,
+    pub created_at: Option<DateTime<Utc>>,
+    /// In the order the owner gave them
+    pub items: Vec<CollectionItem>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct CollectionItem {
+    pub resource: Resource,
+    /// What the owner says about it, can be empty
+    pub note: String,
+}
+
+#[allow(dead_code)]
+impl Collection {
+    pub fn new(guild_id: &str, user_id: &str, name: &str) -> Self {
+        return Self {
+            guild_id: guild_id.to_string(),
+            user_id: user_id.to_string(),
+            name: name.to_string(),
+            ..Default::default()
+        };
+    }
+
+    pub fn contains(&self, resource_id: i64) -> bool {
+        return self
+            .items
+            .iter()
+            .any(|item| item.resource.id == resource_id);
+    }
+
+    /// Items whose resource wasn't deleted, the ones anybody sees
+    pub fn visible_items(&self) -> Vec<&CollectionItem> {
+        return self
+            .items
+            .iter()
+            .filter(|item| item.resource.deleted_at.is_none())
+            .collect();
+    }
+
+    /// `false` when it isn't in the collection
+    pub fn remove(&mut self, resource_id: i64) -> bool {
+        let before = self.items.len();
+        self.items.retain(|item| item.resource.id != resource_id);
+        return self.items.len() < before;
+    }
+
+    /// Moves the item of `resource_id` to `position`, 1 is the first one
+    pub fn move_to(&mut self, resource_id: i64, position: usize) -> bool {
+        let index = match self
+            .items
+            .iter()
+            .position(|item| item.resource.id == resource_id)
+        {
+            Some(index) => index,
+            None => return false,
+        };
+        let item = self.items.remove(index);
+        let position = position.max(1).min(self.items.len() + 1);
+        self.items.insert(position - 1, item);
+        return true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A collection of the resources `ids`, in that order
+    fn collection(ids: &[i64]) -> Collection {
+        let mut collection = Collection::new("1", "10", "path");
+        for id in ids {
+            collection.items.push(CollectionItem {
+                resource: Resource {
+                    id: *id,
+                    ..Default::default()
+                },
+                note: String::new(),
+            });
+        }
+        return collection;
+    }
+
+    fn ids(collection: &Collection) -> Vec<i64> {
+        return collection
+            .items
+            .iter()
+            .map(|item| item.resource.id)
+            .collect();
+    }
+
+    #[test]
+    fn moves_items_within_bounds() {
+        let mut path = collection(&[1, 2, 3, 4]);
+        assert!(path.move_to(4, 1));
+        assert_eq!(ids(&path), vec![4, 1, 2, 3]);
+        assert!(path.move_to(4, 3));
+        assert_eq!(ids(&path), vec![1, 2, 4, 3]);
+        assert!(path.move_to(1, 99));
+        assert_eq!(ids(&path), vec![2, 4, 3, 1]);
+        assert!(path.move_to(1, 0));
+        assert_eq!(ids(&path), vec![1, 2, 4, 3]);
+        assert!(!path.move_to(5, 1));
+    }
+
+    #[test]
+    fn removes_items() {
+        let mut path = collection(&[1, 2, 3]);
+        assert!(path.remove(2));
+        assert!(!path.remove(2));
+        assert_eq!(ids(&path), vec![1, 3]);
+        assert!(path.contains(3));
+        assert!(!path.contains(2));
+    }
+
+    #[test]
+    fn hides_deleted_resources() {
+        let mut path = collection(&[1, 2]);
+        path.items[0].resource.deleted_at = Some(Utc::now());
+        let visible: Vec<i64> = path
+            .visible_items()
+            .iter()
+            .map(|item| item.resource.id)
+            .collect();
+        assert_eq!(visible, vec![2]);
+        assert_eq!(path.items.len(), 2);
+    }
+}
@@ -0,0 +1,52 @@
+use super::super::bot_state::BotState;
+use super::super::i18n::{tr, tr_args};
+use super::cmd_delete_resource::find_resource;
+use super::command::{Arg, ArgKind, Args, Command, Permission};
+use super::response::Response;
+use discord::model::Message;
+
+pub fn command() -> Command {
+    return Command {
+        name: "bookmark",
+        aliases: &["marcador"],
+        args: &[Arg {
+            name: "id|url",
+            kind: ArgKind::Word,
+            required: true,
+        }],
+        help: "help.bookmark",
+        permission: Permission::Member,
+        ephemeral: true,
+        guild_only: true,
+        handler: cmd_bookmark,
+    };
+}
+
+/// Bookmarks a resource for `!mylist`, bookmarking it again takes it out
+#[allow(dead_code)]
+pub fn cmd_bookmark(state: &mut BotState, message: &Message, args: &Args) -> Response {
+    let locale = args.locale.as_str();
+    let guild_id = match state.guild_of(message.channel_id) {
+        Some(guild_id) => guild_id.0.to_string(),
+        None => return Response::error(tr(locale, "command.guild_only")),
+    };
+    let reference = args.text("id|url").unwrap_or("").trim();
+    state.last_command_output = "".to_string();
+
+    let resource = match find_resource(state, &guild_id, reference) {
+        Some(resource) if resource.deleted_at.is_none() => resource,
+        _ => return Response::error(tr_args(locale, "resource.not_found", &[("id", reference)])),
+    };
+    let user_id = message.author.id.to_string();
+    let id = format!("#{}", resource.id);
+
+    if state.db.add_bookmark(&user_id, resource.id) {
+        state.last_command_output = tr_args(locale, "bookmark.added", &[("id", &id)]);
+        return Response::Reaction("🔖".to_string());
+    }
+    if state.db.remove_bookmark(&user_id, resource.id) {
+        return Response::Text(tr_args(locale, "bookmark.removed", &[("id", &id)]));
+    }
+    state.last_command_output = tr(locale, "bookmark.failed");
+    return Response::error(state.last_command_output.clone());
+}
@@ -0,0 +1,301 @@
+use super::super::bot_state::BotState;
+use super::super::collection::{Collection, CollectionItem, MAX_COLLECTION_ITEMS};
+use super::super::i18n::{tr, tr_args};
+use super::super::tags::normalize;
+use super::cmd_delete_resource::find_resource;
+use super::cmd_search_resource::resource_link;
+use super::command::{Arg, ArgKind, Args, Command, Permission};
+use super::response::{Response, ResponseEmbed};
+use discord::model::Message;
+
+pub fn command() -> Command {
+    return Command {
+        name: "collection",
+        aliases: &["coleccion"],
+        args: &[
+            Arg {
+                name: "accion",
+                kind: ArgKind::Word,
+                required: true,
+            },
+            Arg {
+                name: "nombre",
+                kind: ArgKind::Word,
+                required: false,
+            },
+            Arg {
+                name: "valor",
+                kind: ArgKind::Text,
+                required: false,
+            },
+        ],
+        help: "help.collection",
+        permission: Permission::Member,
+        ephemeral: false,
+        guild_only: true,
+        handler: cmd_collection,
+    };
+}
+
+/// `create|add|remove|move|describe|delete|show <name> ...` or `list`,
+/// every user manages their own collections
+#[allow(dead_code)]
+pub fn cmd_collection(state: &mut BotState, message: &Message, args: &Args) -> Response {
+    let locale = args.locale.as_str();
+    let guild_id = match state.guild_of(message.channel_id) {
+        Some(guild_id) => guild_id.0.to_string(),
+        None => return Response::error(tr(locale, "command.guild_only")),
+    };
+    let user_id = message.author.id.to_string();
+    let action = args.text("accion").unwrap_or("").to_lowercase();
+    let value = args.text("valor").unwrap_or("").trim().to_string();
+    state.last_command_output = "".to_string();
+
+    if action == "list" || action == "lista" {
+        return list(state, &guild_id, &user_id, locale);
+    }
+
+    let name = match args.text("nombre").and_then(normalize) {
+        Some(name) => name,
+        None => return Response::error(tr(locale, "collection.usage")),
+    };
+
+    if action == "create" || action == "crear" {
+        let mut collection = Collection::new(&guild_id, &user_id, &name);
+        collection.description = value;
+        return match state.db.insert_collection(&collection) {
+            Some(_) => Response::Text(tr_args(locale, "collection.created", &[("name", &name)])),
+            None => Response::error(tr_args(locale, "collection.exists", &[("name", &name)])),
+        };
+    }
+    if action == "show" || action == "ver" {
+        // Anybody's collection, yours unless someone is mentioned
+        let mentioned = value
+            .trim_start_matches("<@")
+            .trim_start_matches('!')
+            .trim_end_matches('>');
+        let owner_id = match mentioned {
+            "" => user_id,
+            id if id.chars().all(|character| character.is_ascii_digit()) => id.to_string(),
+            _ => return Response::error(tr(locale, "collection.usage")),
+        };
+        return match find_collection(state, &guild_id, &owner_id, &name) {
+            Some(collection) => Response::Embed(collection_embed(state, &collection, locale)),
+            None => Response::error(tr_args(locale, "collection.not_found", &[("name", &name)])),
+        };
+    }
+
+    let mut collection = match find_collection(state, &guild_id, &user_id, &name) {
+        Some(collection) => collection,
+        None => {
+            return Response::error(tr_args(locale, "collection.not_found", &[("name", &name)]))
+        }
+    };
+
+    if action == "delete" || action == "borrar" {
+        if !state.db.delete_collection(&guild_id, collection.id) {
+            state.last_command_output = tr(locale, "collection.save_failed");
+            return Response::error(state.last_command_output.clone());
+        }
+        return Response::Text(tr_args(locale, "collection.deleted", &[("name", &name)]));
+    }
+
+    let response = match action.as_str() {
+        "add" | "agregar" => add(state, &guild_id, &mut collection, &value, locale),
+        "remove" | "quitar" => remove(&mut collection, &value, locale),
+        "move" | "mover" => move_item(&mut collection, &value, locale),
+        "describe" | "describir" => {
+            collection.description = value;
+            Ok(tr_args(locale, "collection.described", &[("name", &name)]))
+        }
+        _ => Err(tr(locale, "collection.usage")),
+    };
+    let response = match response {
+        Ok(response) => response,
+        Err(error) => return Response::error(error),
+    };
+
+    if !state.db.update_collection(&collection) {
+        state.last_command_output = tr(locale, "collection.save_failed");
+        return Response::error(state.last_command_output.clone());
+    }
+    return Response::Text(response);
+}
+
+/// Collection `name` of `user_id`, names are unique per user
+fn find_collection(
+    state: &mut BotState,
+    guild_id: &str,
+    user_id: &str,
+    name: &str,
+) -> Option<Collection> {
+    return state
+        .db
+        .select_collections(guild_id, user_id)
+        .into_iter()
+        .find(|collection| collection.name == name);
+}
+
+/// Id of the resource, given as `12` or `#12`
+fn item_id(value: &str) -> Option<i64> {
+    return value.trim_start_matches('#').parse::<i64>().ok();
+}
+
+/// `add <name> <id|url> [note]`, the note says why it's there
+fn add(
+    state: &mut BotState,
+    guild_id: &str,
+    collection: &mut Collection,
+    value: &str,
+    locale: &str,
+) -> Result<String, String> {
+    let (reference, note) = match value.find(char::is_whitespace) {
+        Some(index) => (&value[..index], value[index..].trim()),
+        None => (value, ""),
+    };
+    if reference.is_empty() {
+        return Err(tr(locale, "collection.usage"));
+    }
+
+    let resource = match find_resource(state, guild_id, reference) {
+        Some(resource) if resource.deleted_at.is_none() => resource,
+        _ => return Err(tr_args(locale, "resource.not_found", &[("id", reference)])),
+    };
+    let id = format!("#{}", resource.id);
+    if collection.contains(resource.id) {
+        return Err(tr_args(
+            locale,
+            "collection.already_added",
+            &[("id", &id), ("name", &collection.name)],
+        ));
+    }
+    if collection.items.len() >= MAX_COLLECTION_ITEMS {
+        return Err(tr_args(
+            locale,
+            "collection.full",
+            &[("max", &MAX_COLLECTION_ITEMS.to_string())],
+        ));
+    }
+
+    collection.items.push(CollectionItem {
+        resource: resource,
+        note: note.to_string(),
+    });
+    return Ok(tr_args(
+        locale,
+        "collection.added",
+        &[("id", &id), ("name", &collection.name)],
+    ));
+}
+
+/// `remove <name> <id>`
+fn remove(collection: &mut Collection, value: &str, locale: &str) -> Result<String, String> {
+    let id = match item_id(value) {
+        Some(id) => id,
+        None => return Err(tr(locale, "collection.usage")),
+    };
+    if !collection.remove(id) {
+        return Err(tr_args(
+            locale,
+            "collection.not_in",
+            &[("id", &format!("#{}", id)), ("name", &collection.name)],
+        ));
+    }
+    return Ok(tr_args(
+        locale,
+        "collection.removed",
+        &[("id", &format!("#{}", id)), ("name", &collection.name)],
+    ));
+}
+
+/// `move <name> <id> <position>`, 1 is the first one
+fn move_item(collection: &mut Collection, value: &str, locale: &str) -> Result<String, String> {
+    let mut words = value.split_whitespace();
+    let id = words.next().and_then(item_id);
+    let position = words.next().and_then(|word| word.parse::<usize>().ok());
+    let (id, position) = match (id, position) {
+        (Some(id), Some(position)) if position >= 1 => (id, position),
+        _ => return Err(tr(locale, "collection.usage")),
+    };
+
+    if !collection.move_to(id, position) {
+        return Err(tr_args(
+            locale,
+            "collection.not_in",
+            &[("id", &format!("#{}", id)), ("name", &collection.name)],
+        ));
+    }
+    return Ok(tr_args(
+        locale,
+        "collection.moved",
+        &[
+            ("id", &format!("#{}", id)),
+            ("name", &collection.name),
+            (
+                "position",
+                &position.min(collection.items.len()).to_string(),
+            ),
+        ],
+    ));
+}
+
+fn list(state: &mut BotState, guild_id: &str, user_id: &str, locale: &str) -> Response {
+    let collections = state.db.select_collections(guild_id, user_id);
+    if collections.is_empty() {
+        return Response::Text(tr(locale, "collection.none"));
+    }
+
+    let lines: Vec<String> = collections
+        .iter()
+        .map(|collection| {
+            let count = collection.visible_items().len().to_string();
+            match collection.description.is_empty() {
+                true => format!("`{}` ({})", collection.name, count),
+                false => format!(
+                    "`{}` ({}) {}",
+                    collection.name, count, collection.description
+                ),
+            }
+        })
+        .collect();
+    let mut embed = ResponseEmbed::new(&tr(locale, "collection.list_title"));
+    embed.description = lines.join("\n");
+    return Response::Embed(embed);
+}
+
+/// The collection in order, each resource with its note, and its page on
+/// the website when the server shares its resources
+fn collection_embed(state: &mut BotState, collection: &Collection, locale: &str) -> ResponseEmbed {
+    let mut embed = ResponseEmbed::new(&collection.name);
+    if let Some(page) = state.collection_page(collection) {
+        embed.url = page;
+    }
+
+    let mut lines: Vec<String> = Vec::new();
+    if !collection.description.is_empty() {
+        lines.push(collection.description.clone());
+        lines.push("".to_string());
+    }
+    for (position, item) in collection.visible_items().iter().enumerate() {
+        lines.push(format!(
+            "{}. {}",
+            position + 1,
+            resource_link(&item.resource)
+        ));
+        if !item.note.is_empty() {
+            lines.push(format!("> {}", item.note));
+        }
+    }
+    if collection.visible_items().is_empty() {
+        lines.push(tr(locale, "collection.empty"));
+    }
+    embed.description = lines.join("\n");
+    embed.field(
+        &tr(locale, "collection.owner"),
+        &format!("<@{}>", collection.user_id),
+        true,
+    );
+    embed.timestamp = collection.created_at;
+
+    return embed;
+}
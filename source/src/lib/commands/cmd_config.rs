@@ -0,0 +1,489 @@
+use super::super::bot_state::BotState;
+use super::super::guild_settings::{GuildRole, GuildSettings};
+use super::super::i18n::{is_supported, locales, tr, tr_args};
+use super::command::{Arg, ArgKind, Args, Command, Permission, DEFAULT_PREFIX};
+use super::response::Response;
+use discord::model::{ChannelId, Message};
+use discord::ChannelRef;
+
+pub const NAME: &str = "config";
+
+/// Permission levels that can be granted to a discord role
+pub const ROLE_PERMISSIONS: &[&str] = &["curator", "admin"];
+/// Longest window, in days, a resource of the day isn't repeated in
+const MAX_DAILY_WINDOW: i32 = 365;
+/// Weekday names `digest day` takes, monday first
+const WEEKDAYS: &[&[&str]] = &[
+    &["monday", "lunes"],
+    &["tuesday", "martes"],
+    &["wednesday", "miercoles", "miércoles"],
+    &["thursday", "jueves"],
+    &["friday", "viernes"],
+    &["saturday", "sabado", "sábado"],
+    &["sunday", "domingo"],
+];
+
+pub fn command() -> Command {
+    return Command {
+        name: NAME,
+        aliases: &["configurar"],
+        args: &[
+            Arg {
+                name: "opcion",
+                kind: ArgKind::Word,
+                required: false,
+            },
+            Arg {
+                name: "valor",
+                kind: ArgKind::Text,
+                required: false,
+            },
+        ],
+        help: "help.config",
+        permission: Permission::Admin,
+        ephemeral: true,
+        guild_only: true,
+        handler: cmd_config,
+    };
+}
+
+#[allow(dead_code)]
+pub fn cmd_config(state: &mut BotState, message: &Message, args: &Args) -> Response {
+    let locale = args.locale.as_str();
+    let guild_id = match state.guild_of(message.channel_id) {
+        Some(guild_id) => guild_id.0.to_string(),
+        None => return Response::error(tr(locale, "command.guild_only")),
+    };
+
+    let mut settings = state.settings_of(&guild_id);
+    let value = args.text("valor").unwrap_or("").trim().to_string();
+    state.last_command_output = "".to_string();
+
+    let response = match args.text("opcion").map(|option| option.to_lowercase()) {
+        None => return Response::Text(summary(&settings, locale)),
+        Some(option) if option == "prefix" || option == "prefijo" => {
+            if value.is_empty() || value.contains(char::is_whitespace) || value.len() > 5 {
+                return Response::error(tr(locale, "config.bad_prefix"));
+            }
+            settings.prefix = match value.as_str() {
+                DEFAULT_PREFIX => "".to_string(),
+                _ => value.clone(),
+            };
+            tr_args(locale, "config.prefix_changed", &[("prefix", &value)])
+        }
+        Some(option) if option == "locale" || option == "idioma" => {
+            let value = value.to_lowercase();
+            if !is_supported(&value) {
+                return Response::error(tr_args(
+                    locale,
+                    "lang.unknown",
+                    &[("locale", &value), ("locales", &locales().join(", "))],
+                ));
+            }
+            settings.locale = value.clone();
+            tr_args(
+                locale,
+                "config.locale_changed",
+                &[("name", &tr(&value, "name"))],
+            )
+        }
+        Some(option) if option == "channels" || option == "canales" => {
+            match config_channels(state, message, &mut settings, &value, locale) {
+                Ok(response) => response,
+                Err(error) => return Response::error(error),
+            }
+        }
+        Some(option) if option == "role" || option == "rol" => {
+            match config_role(state, message, &mut settings, &value, locale) {
+                Ok(response) => response,
+                Err(error) => return Response::error(error),
+            }
+        }
+        Some(option) if option == "daily" || option == "diario" => {
+            match config_daily(state, message, &mut settings, &value, locale) {
+                Ok(response) => response,
+                Err(error) => return Response::error(error),
+            }
+        }
+        Some(option) if option == "digest" || option == "resumen" => {
+            match config_digest(state, message, &mut settings, &value, locale) {
+                Ok(response) => response,
+                Err(error) => return Response::error(error),
+            }
+        }
+        Some(option) => {
+            return Response::error(tr_args(
+                locale,
+                "config.unknown_option",
+                &[("option", &option)],
+            ))
+        }
+    };
+
+    if !state.save_settings(settings) {
+        state.last_command_output = tr(locale, "config.save_failed");
+        return Response::error(state.last_command_output.clone());
+    }
+    return Response::Text(response);
+}
+
+/// `channels add|remove|clear [#channel]`
+fn config_channels(
+    state: &BotState,
+    message: &Message,
+    settings: &mut GuildSettings,
+    value: &str,
+    locale: &str,
+) -> Result<String, String> {
+    let mut words = value.split_whitespace();
+    let action = words.next().unwrap_or("").to_lowercase();
+    let channel = words.next().unwrap_or("");
+
+    if action == "clear" || action == "limpiar" {
+        settings.allowed_channels.clear();
+        return Ok(tr(locale, "config.channels_cleared"));
+    }
+
+    let channel_id = match find_channel(state, message, channel) {
+        Some(channel_id) => channel_id,
+        None => return Err(tr_args(locale, "config.bad_channel", &[("value", channel)])),
+    };
+    let mention = format!("<#{}>", channel_id);
+
+    if action == "add" || action == "agregar" {
+        if !settings.allowed_channels.contains(&channel_id) {
+            settings.allowed_channels.push(channel_id);
+        }
+        return Ok(tr_args(
+            locale,
+            "config.channel_added",
+            &[("channel", &mention)],
+        ));
+    }
+    if action == "remove" || action == "quitar" {
+        settings
+            .allowed_channels
+            .retain(|allowed| allowed != &channel_id);
+        return Ok(tr_args(
+            locale,
+            "config.channel_removed",
+            &[("channel", &mention)],
+        ));
+    }
+    return Err(tr(locale, "config.channels_usage"));
+}
+
+/// `role curator|admin|none @role`
+fn config_role(
+    state: &BotState,
+    message: &Message,
+    settings: &mut GuildSettings,
+    value: &str,
+    locale: &str,
+) -> Result<String, String> {
+    let (permission, role) = match value.find(char::is_whitespace) {
+        Some(index) => (value[..index].to_lowercase(), value[index..].trim()),
+        None => (value.to_lowercase(), ""),
+    };
+
+    if permission != "none" && !ROLE_PERMISSIONS.contains(&permission.as_str()) {
+        return Err(tr_args(
+            locale,
+            "config.bad_permission",
+            &[
+                ("value", &permission),
+                ("permissions", &ROLE_PERMISSIONS.join(", ")),
+            ],
+        ));
+    }
+
+    let role_id = match find_role(state, message, role) {
+        Some(role_id) => role_id,
+        None => return Err(tr_args(locale, "config.bad_role", &[("value", role)])),
+    };
+    let mention = format!("<@&{}>", role_id);
+
+    settings.roles.retain(|granted| granted.role_id != role_id);
+    if permission == "none" {
+        return Ok(tr_args(
+            locale,
+            "config.role_removed",
+            &[("role", &mention)],
+        ));
+    }
+    settings.roles.push(GuildRole {
+        role_id: role_id,
+        permission: permission.clone(),
+    });
+    return Ok(tr_args(
+        locale,
+        "config.role_granted",
+        &[
+            ("role", &mention),
+            (
+                "permission",
+                &tr(locale, &format!("permission.{}", permission)),
+            ),
+        ],
+    ));
+}
+
+/// `daily #channel|off`, `daily hour 0-23` or `daily window <days>`
+fn config_daily(
+    state: &BotState,
+    message: &Message,
+    settings: &mut GuildSettings,
+    value: &str,
+    locale: &str,
+) -> Result<String, String> {
+    let mut words = value.split_whitespace();
+    let action = words.next().unwrap_or("").to_lowercase();
+    let number = words.next().and_then(|number| number.parse::<i32>().ok());
+
+    if action == "off" || action == "apagar" {
+        settings.daily_channel = "".to_string();
+        return Ok(tr(locale, "config.daily_off"));
+    }
+    if action == "hour" || action == "hora" {
+        return match number {
+            Some(hour) if hour >= 0 && hour <= 23 => {
+                settings.daily_hour = hour;
+                Ok(tr_args(
+                    locale,
+                    "config.daily_hour_changed",
+                    &[("hour", &hour.to_string())],
+                ))
+            }
+            _ => Err(tr(locale, "config.daily_usage")),
+        };
+    }
+    if action == "window" || action == "ventana" {
+        return match number {
+            Some(days) if days >= 1 && days <= MAX_DAILY_WINDOW => {
+                settings.daily_window = days;
+                Ok(tr_args(
+                    locale,
+                    "config.daily_window_changed",
+                    &[("days", &days.to_string())],
+                ))
+            }
+            _ => Err(tr(locale, "config.daily_usage")),
+        };
+    }
+    if action.is_empty() {
+        return Err(tr(locale, "config.daily_usage"));
+    }
+
+    let channel_id = match find_channel(state, message, &action) {
+        Some(channel_id) => channel_id,
+        None => return Err(tr_args(locale, "config.bad_channel", &[("value", &action)])),
+    };
+    settings.daily_channel = channel_id.clone();
+    return Ok(tr_args(
+        locale,
+        "config.daily_channel_changed",
+        &[
+            ("channel", &format!("<#{}>", channel_id)),
+            ("hour", &settings.daily_hour.to_string()),
+        ],
+    ));
+}
+
+/// `digest #channel|off`, `digest day monday-sunday|1-7` or `digest hour 0-23`
+fn config_digest(
+    state: &BotState,
+    message: &Message,
+    settings: &mut GuildSettings,
+    value: &str,
+    locale: &str,
+) -> Result<String, String> {
+    let mut words = value.split_whitespace();
+    let action = words.next().unwrap_or("").to_lowercase();
+    let argument = words.next().unwrap_or("").to_lowercase();
+
+    if action == "off" || action == "apagar" {
+        settings.digest_channel = "".to_string();
+        return Ok(tr(locale, "config.digest_off"));
+    }
+    if action == "day" || action == "dia" || action == "día" {
+        return match weekday(&argument) {
+            Some(day) => {
+                settings.digest_day = day;
+                Ok(tr_args(
+                    locale,
+                    "config.digest_day_changed",
+                    &[("day", &tr(locale, &format!("weekday.{}", day)))],
+                ))
+            }
+            None => Err(tr(locale, "config.digest_usage")),
+        };
+    }
+    if action == "hour" || action == "hora" {
+        return match argument.parse::<i32>() {
+            Ok(hour) if hour >= 0 && hour <= 23 => {
+                settings.digest_hour = hour;
+                Ok(tr_args(
+                    locale,
+                    "config.digest_hour_changed",
+                    &[("hour", &hour.to_string())],
+                ))
+            }
+            _ => Err(tr(locale, "config.digest_usage")),
+        };
+    }
+    if action.is_empty() {
+        return Err(tr(locale, "config.digest_usage"));
+    }
+
+    let channel_id = match find_channel(state, message, &action) {
+        Some(channel_id) => channel_id,
+        None => return Err(tr_args(locale, "config.bad_channel", &[("value", &action)])),
+    };
+    settings.digest_channel = channel_id.clone();
+    return Ok(tr_args(
+        locale,
+        "config.digest_channel_changed",
+        &[
+            ("channel", &format!("<#{}>", channel_id)),
+            (
+                "day",
+                &tr(locale, &format!("weekday.{}", settings.digest_day)),
+            ),
+            ("hour", &settings.digest_hour.to_string()),
+        ],
+    ));
+}
+
+/// 1 for monday to 7 for sunday, from the number or the name
+fn weekday(value: &str) -> Option<i32> {
+    if let Ok(day) = value.parse::<i32>() {
+        return match day >= 1 && day <= 7 {
+            true => Some(day),
+            false => None,
+        };
+    }
+    return WEEKDAYS
+        .iter()
+        .position(|names| names.contains(&value))
+        .map(|index| index as i32 + 1);
+}
+
+fn summary(settings: &GuildSettings, locale: &str) -> String {
+    let prefix = match settings.prefix.as_str() {
+        "" => DEFAULT_PREFIX,
+        prefix => prefix,
+    };
+    let language = match settings.locale.as_str() {
+        "" => tr(locale, "config.default"),
+        code => tr(code, "name"),
+    };
+    let channels = match settings.allowed_channels.is_empty() {
+        true => tr(locale, "config.all_channels"),
+        false => settings
+            .allowed_channels
+            .iter()
+            .map(|channel_id| format!("<#{}>", channel_id))
+            .collect::<Vec<String>>()
+            .join(", "),
+    };
+    let roles = match settings.roles.is_empty() {
+        true => tr(locale, "config.no_roles"),
+        false => settings
+            .roles
+            .iter()
+            .map(|role| {
+                let permission = tr(locale, &format!("permission.{}", role.permission));
+                format!("<@&{}> {}", role.role_id, permission)
+            })
+            .collect::<Vec<String>>()
+            .join(", "),
+    };
+    let daily = match settings.daily_channel.is_empty() {
+        true => tr(locale, "config.daily_disabled"),
+        false => tr_args(
+            locale,
+            "config.daily_enabled",
+            &[
+                ("channel", &format!("<#{}>", settings.daily_channel)),
+                ("hour", &settings.daily_hour.to_string()),
+                ("days", &settings.daily_window.to_string()),
+            ],
+        ),
+    };
+
+    let digest = match settings.digest_channel.is_empty() {
+        true => tr(locale, "config.digest_disabled"),
+        false => tr_args(
+            locale,
+            "config.digest_enabled",
+            &[
+                ("channel", &format!("<#{}>", settings.digest_channel)),
+                (
+                    "day",
+                    &tr(locale, &format!("weekday.{}", settings.digest_day)),
+                ),
+                ("hour", &settings.digest_hour.to_string()),
+            ],
+        ),
+    };
+
+    return tr_args(
+        locale,
+        "config.summary",
+        &[
+            ("prefix", prefix),
+            ("locale", &language),
+            ("channels", &channels),
+            ("roles", &roles),
+            ("daily", &daily),
+            ("digest", &digest),
+        ],
+    );
+}
+
+/// Id inside a discord mention like `<#123>` or `<@&123>`, or a raw id
+fn mention_id(value: &str, mention: &str) -> Option<String> {
+    let id = value.trim_start_matches(mention).trim_end_matches('>');
+    if id.is_empty() || !id.chars().all(|character| character.is_ascii_digit()) {
+        return None;
+    }
+    return Some(id.to_string());
+}
+
+/// Channel of the current server given as a mention, an id or a name.
+/// Channels of other servers or unknown to the cache are `None`.
+fn find_channel(state: &BotState, message: &Message, value: &str) -> Option<String> {
+    if let Some(channel_id) = mention_id(value, "<#") {
+        let guild_id = state.guild_of(message.channel_id)?;
+        let id = channel_id.parse::<u64>().ok()?;
+        return match state.guild_of(ChannelId(id)) {
+            Some(owner) if owner == guild_id => Some(channel_id),
+            _ => None,
+        };
+    }
+    let name = value.trim_start_matches('#').to_lowercase();
+    return match state.cache.read().unwrap().find_channel(message.channel_id) {
+        Some(ChannelRef::Public(server, _)) => server
+            .channels
+            .iter()
+            .find(|channel| channel.name.to_lowercase() == name)
+            .map(|channel| channel.id.0.to_string()),
+        _ => None,
+    };
+}
+
+/// Role of the current server given as a mention, an id or a name
+fn find_role(state: &BotState, message: &Message, value: &str) -> Option<String> {
+    if let Some(role_id) = mention_id(value, "<@&") {
+        return Some(role_id);
+    }
+    let name = value.trim_start_matches('@').to_lowercase();
+    return match state.cache.read().unwrap().find_channel(message.channel_id) {
+        Some(ChannelRef::Public(server, _)) => server
+            .roles
+            .iter()
+            .find(|role| role.name.to_lowercase() == name)
+            .map(|role| role.id.0.to_string()),
+        _ => None,
+    };
+}
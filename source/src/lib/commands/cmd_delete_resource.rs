@@ -0,0 +1,86 @@
+use super::super::bot_state::BotState;
+use super::super::canonical_url::{canonicalize, content_hash};
+use super::super::i18n::{tr, tr_args};
+use super::super::resource::Resource;
+use super::command::{Arg, ArgKind, Args, Command, Permission};
+use super::response::Response;
+use discord::model::Message;
+
+pub fn command() -> Command {
+    return Command {
+        name: "delete",
+        aliases: &["borrar", "eliminar"],
+        args: &[Arg {
+            name: "id|url",
+            kind: ArgKind::Text,
+            required: true,
+        }],
+        help: "help.delete",
+        permission: Permission::Member,
+        ephemeral: false,
+        guild_only: true,
+        handler: cmd_delete_resource,
+    };
+}
+
+#[allow(dead_code)]
+pub fn cmd_delete_resource(state: &mut BotState, message: &Message, args: &Args) -> Response {
+    let locale = args.locale.as_str();
+    let guild_id = match state.guild_of(message.channel_id) {
+        Some(guild_id) => guild_id.0.to_string(),
+        None => return Response::error(tr(locale, "command.guild_only")),
+    };
+    let reference = args.text("id|url").unwrap_or("").trim();
+    state.last_command_output = "".to_string();
+
+    let resource = match find_resource(state, &guild_id, reference) {
+        Some(resource) if resource.deleted_at.is_none() => resource,
+        Some(_) => {
+            return Response::error(tr_args(
+                locale,
+                "resource.already_deleted",
+                &[("id", reference)],
+            ))
+        }
+        None => {
+            return Response::error(tr_args(locale, "resource.not_found", &[("id", reference)]))
+        }
+    };
+    if !can_manage(state, message, &resource) {
+        return Response::error(tr(locale, "resource.not_owner"));
+    }
+
+    if !state
+        .db
+        .delete_resource(&guild_id, resource.id, &message.author.id.to_string())
+    {
+        state.last_command_output = tr(locale, "resource.save_failed");
+        return Response::error(state.last_command_output.clone());
+    }
+    return Response::Text(tr_args(
+        locale,
+        "delete.deleted",
+        &[("id", &resource.id.to_string()), ("url", &resource.url)],
+    ));
+}
+
+/// Resource of `guild_id` given as an id (`12` or `#12`) or as its url
+pub fn find_resource(state: &mut BotState, guild_id: &str, reference: &str) -> Option<Resource> {
+    if let Ok(id) = reference.trim_start_matches('#').parse::<i64>() {
+        return state.db.select_resource(guild_id, id);
+    }
+
+    let url = canonicalize(reference.trim_matches(|c| c == '<' || c == '>'));
+    if url.is_empty() {
+        return None;
+    }
+    return state
+        .db
+        .select_resource_by_hash(guild_id, &content_hash(&url));
+}
+
+/// Members manage the resources credited to them, curators and above any of them
+pub fn can_manage(state: &mut BotState, message: &Message, resource: &Resource) -> bool {
+    return resource.user_id == message.author.id.to_string()
+        || state.permission_of(message) >= Permission::Curator;
+}
@@ -0,0 +1,46 @@
+use super::super::bot_state::BotState;
+use super::super::i18n::tr;
+use super::super::scheduler::{digest_embed, digest_start};
+use super::command::{Arg, ArgKind, Args, Command, Permission};
+use super::response::Response;
+use chrono::Utc;
+use discord::model::Message;
+
+pub fn command() -> Command {
+    return Command {
+        name: "digest",
+        aliases: &["resumen"],
+        args: &[Arg {
+            name: "cuando",
+            kind: ArgKind::Word,
+            required: true,
+        }],
+        help: "help.digest",
+        permission: Permission::Member,
+        ephemeral: true,
+        guild_only: true,
+        handler: cmd_digest,
+    };
+}
+
+/// `digest now` shows what the next weekly digest would post so far
+#[allow(dead_code)]
+pub fn cmd_digest(state: &mut BotState, message: &Message, args: &Args) -> Response {
+    let locale = args.locale.as_str();
+    let guild_id = match state.guild_of(message.channel_id) {
+        Some(guild_id) => guild_id.0.to_string(),
+        None => return Response::error(tr(locale, "command.guild_only")),
+    };
+    state.last_command_output = "".to_string();
+
+    match args.text("cuando").unwrap_or("").to_lowercase().as_str() {
+        "now" | "ahora" => {}
+        _ => return Response::error(tr(locale, "digest.usage")),
+    }
+
+    let since = digest_start(state, &guild_id, Utc::now());
+    return match digest_embed(state, &guild_id, since, locale) {
+        Some(embed) => Response::Embed(embed),
+        None => Response::Text(tr(locale, "digest.empty")),
+    };
+}
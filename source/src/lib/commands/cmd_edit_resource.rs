@@ -0,0 +1,105 @@
+use super::super::bot_state::BotState;
+use super::super::i18n::{tr, tr_args};
+use super::super::resource_type::ResourceType;
+use super::super::tags::normalize;
+use super::cmd_delete_resource::{can_manage, find_resource};
+use super::command::{Arg, ArgKind, Args, Command, Permission};
+use super::response::Response;
+use discord::model::Message;
+
+pub fn command() -> Command {
+    return Command {
+        name: "edit",
+        aliases: &["editar"],
+        args: &[
+            Arg {
+                name: "id",
+                kind: ArgKind::Word,
+                required: true,
+            },
+            Arg {
+                name: "campo",
+                kind: ArgKind::Word,
+                required: true,
+            },
+            Arg {
+                name: "valor",
+                kind: ArgKind::Text,
+                required: true,
+            },
+        ],
+        help: "help.edit",
+        permission: Permission::Member,
+        ephemeral: false,
+        guild_only: true,
+        handler: cmd_edit_resource,
+    };
+}
+
+#[allow(dead_code)]
+pub fn cmd_edit_resource(state: &mut BotState, message: &Message, args: &Args) -> Response {
+    let locale = args.locale.as_str();
+    let guild_id = match state.guild_of(message.channel_id) {
+        Some(guild_id) => guild_id.0.to_string(),
+        None => return Response::error(tr(locale, "command.guild_only")),
+    };
+    let reference = args.text("id").unwrap_or("");
+    let value = args.text("valor").unwrap_or("").trim().to_string();
+    state.last_command_output = "".to_string();
+
+    let mut resource = match find_resource(state, &guild_id, reference) {
+        Some(resource) if resource.deleted_at.is_none() => resource,
+        Some(_) => {
+            return Response::error(tr_args(
+                locale,
+                "resource.already_deleted",
+                &[("id", reference)],
+            ))
+        }
+        None => {
+            return Response::error(tr_args(locale, "resource.not_found", &[("id", reference)]))
+        }
+    };
+    if !can_manage(state, message, &resource) {
+        return Response::error(tr(locale, "resource.not_owner"));
+    }
+
+    let field = args.text("campo").unwrap_or("").to_lowercase();
+    match field.as_str() {
+        "title" | "titulo" => resource.title = value,
+        "description" | "descripcion" => resource.description = value,
+        "tags" | "etiquetas" => {
+            let mut tags: Vec<String> = value.split_whitespace().filter_map(normalize).collect();
+            tags.sort();
+            tags.dedup();
+            if !state.db.set_resource_tags(resource.id, &tags) {
+                state.last_command_output = tr(locale, "resource.save_failed");
+                return Response::error(state.last_command_output.clone());
+            }
+            return Response::Text(tr_args(
+                locale,
+                "edit.edited",
+                &[("id", &resource.id.to_string())],
+            ));
+        }
+        "type" | "tipo" => {
+            resource.type_id = match ResourceType::from_name(&value) {
+                Some(kind) => kind.id(),
+                None => {
+                    return Response::error(tr_args(locale, "query.bad_type", &[("value", &value)]))
+                }
+            }
+        }
+        _ => return Response::error(tr_args(locale, "edit.unknown_field", &[("field", &field)])),
+    }
+
+    if !state.db.update_resource(&resource) {
+        state.last_command_output = tr(locale, "resource.save_failed");
+        return Response::error(state.last_command_output.clone());
+    }
+    return Response::Text(tr_args(
+        locale,
+        "edit.edited",
+        &[("id", &resource.id.to_string())],
+    ));
+}
@@ -0,0 +1,64 @@
+use super::super::bot_state::BotState;
+use super::super::i18n::{tr, tr_args};
+use super::command::{Arg, ArgKind, Args, Command, Permission};
+use super::registry;
+use super::response::Response;
+use discord::model::Message;
+
+pub fn command() -> Command {
+    return Command {
+        name: "help",
+        aliases: &["ayuda"],
+        args: &[Arg {
+            name: "comando",
+            kind: ArgKind::Word,
+            required: false,
+        }],
+        help: "help.help",
+        permission: Permission::Member,
+        ephemeral: true,
+        guild_only: false,
+        handler: cmd_help,
+    };
+}
+
+#[allow(dead_code)]
+pub fn cmd_help(state: &mut BotState, _: &Message, args: &Args) -> Response {
+    let locale = args.locale.as_str();
+    let commands = registry();
+    state.last_command_output = "".to_string();
+
+    if let Some(name) = args.text("comando") {
+        let name = name.trim_start_matches(args.prefix.as_str()).to_lowercase();
+        return match commands.iter().find(|command| command.matches(&name)) {
+            Some(command) => {
+                let mut response = format!(
+                    "`{}`\n{}",
+                    command.usage(&args.prefix),
+                    tr(locale, command.help)
+                );
+                if !command.aliases.is_empty() {
+                    let aliases = command.aliases.join(", ");
+                    response = format!(
+                        "{}\n{}",
+                        response,
+                        tr_args(locale, "help.aliases", &[("aliases", &aliases)])
+                    );
+                }
+                Response::Text(response)
+            }
+            None => Response::error(tr_args(locale, "help.unknown", &[("command", &name)])),
+        };
+    }
+
+    let mut response = tr(locale, "help.title");
+    for command in &commands {
+        response = format!(
+            "{}\n`{}` - {}",
+            response,
+            command.usage(&args.prefix),
+            tr(locale, command.help)
+        );
+    }
+    return Response::Text(response);
+}
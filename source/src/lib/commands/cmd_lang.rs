@@ -0,0 +1,59 @@
+use super::super::bot_state::BotState;
+use super::super::i18n::{is_supported, locales, tr, tr_args};
+use super::command::{Arg, ArgKind, Args, Command, Permission};
+use super::response::Response;
+use discord::model::Message;
+
+pub fn command() -> Command {
+    return Command {
+        name: "lang",
+        aliases: &["idioma", "language"],
+        args: &[Arg {
+            name: "codigo",
+            kind: ArgKind::Word,
+            required: false,
+        }],
+        help: "help.lang",
+        permission: Permission::Member,
+        ephemeral: true,
+        guild_only: false,
+        handler: cmd_lang,
+    };
+}
+
+#[allow(dead_code)]
+pub fn cmd_lang(state: &mut BotState, message: &Message, args: &Args) -> Response {
+    let available = locales().join(", ");
+    state.last_command_output = "".to_string();
+
+    let locale = match args.text("codigo") {
+        Some(locale) => locale.to_lowercase(),
+        None => {
+            return Response::Text(tr_args(
+                &args.locale,
+                "lang.current",
+                &[("name", &tr(&args.locale, "name")), ("locales", &available)],
+            ))
+        }
+    };
+
+    if !is_supported(&locale) {
+        return Response::error(tr_args(
+            &args.locale,
+            "lang.unknown",
+            &[("locale", &locale), ("locales", &available)],
+        ));
+    }
+
+    if !state.save_user_locale(&message.author.id.to_string(), &locale) {
+        state.last_command_output = tr(&args.locale, "lang.save_failed");
+        return Response::error(state.last_command_output.clone());
+    }
+
+    // Confirm in the new language
+    return Response::Text(tr_args(
+        &locale,
+        "lang.changed",
+        &[("name", &tr(&locale, "name"))],
+    ));
+}
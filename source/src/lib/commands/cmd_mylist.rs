@@ -0,0 +1,73 @@
+use super::super::bot_state::BotState;
+use super::super::i18n::{tr, tr_args};
+use super::cmd_search_resource::resource_link;
+use super::command::{Args, Command, Permission};
+use super::response::{Response, ResponseEmbed};
+use discord::model::Message;
+
+/// Bookmarks listed, the newest ones
+const MAX_LISTED: usize = 20;
+
+pub fn command() -> Command {
+    return Command {
+        name: "mylist",
+        aliases: &["milista"],
+        args: &[],
+        help: "help.mylist",
+        permission: Permission::Member,
+        ephemeral: true,
+        guild_only: true,
+        handler: cmd_mylist,
+    };
+}
+
+/// Your bookmarks and collections in this server
+#[allow(dead_code)]
+pub fn cmd_mylist(state: &mut BotState, message: &Message, args: &Args) -> Response {
+    let locale = args.locale.as_str();
+    let guild_id = match state.guild_of(message.channel_id) {
+        Some(guild_id) => guild_id.0.to_string(),
+        None => return Response::error(tr(locale, "command.guild_only")),
+    };
+    let user_id = message.author.id.to_string();
+    state.last_command_output = "".to_string();
+
+    let bookmarks = state.db.select_bookmarks(&guild_id, &user_id);
+    let collections = state.db.select_collections(&guild_id, &user_id);
+    if bookmarks.is_empty() && collections.is_empty() {
+        return Response::Text(tr(locale, "mylist.empty"));
+    }
+
+    let mut embed = ResponseEmbed::new(&tr(locale, "mylist.title"));
+    let mut lines: Vec<String> = bookmarks
+        .iter()
+        .take(MAX_LISTED)
+        .map(|resource| resource_link(resource))
+        .collect();
+    if bookmarks.len() > MAX_LISTED {
+        let more = (bookmarks.len() - MAX_LISTED).to_string();
+        lines.push(tr_args(locale, "digest.more", &[("count", &more)]));
+    }
+    embed.description = match lines.is_empty() {
+        true => tr(locale, "mylist.no_bookmarks"),
+        false => lines.join("\n"),
+    };
+
+    let collections: Vec<String> = collections
+        .iter()
+        .map(|collection| {
+            format!(
+                "`{}` ({})",
+                collection.name,
+                collection.visible_items().len()
+            )
+        })
+        .collect();
+    embed.field(
+        &tr(locale, "mylist.collections"),
+        &collections.join("\n"),
+        false,
+    );
+
+    return Response::Embed(embed);
+}
@@ -0,0 +1,49 @@
+use super::super::bot_state::BotState;
+use super::super::i18n::tr;
+use super::super::search_query::SearchQuery;
+use super::cmd_search_resource::resource_embed;
+use super::command::{Arg, ArgKind, Args, Command, Permission};
+use super::response::Response;
+use discord::model::Message;
+
+pub fn command() -> Command {
+    return Command {
+        name: "random",
+        aliases: &["aleatorio", "azar"],
+        args: &[Arg {
+            name: "consulta",
+            kind: ArgKind::Text,
+            required: false,
+        }],
+        help: "help.random",
+        permission: Permission::Member,
+        ephemeral: false,
+        guild_only: false,
+        handler: cmd_random,
+    };
+}
+
+#[allow(dead_code)]
+pub fn cmd_random(state: &mut BotState, message: &Message, args: &Args) -> Response {
+    let locale = args.locale.as_str();
+    // Without a query any resource will do
+    let mut query = match args.text("consulta").unwrap_or("").trim() {
+        "" => SearchQuery::default(),
+        input => match SearchQuery::parse(input, locale) {
+            Ok(query) => query,
+            Err(error) => return Response::error(error),
+        },
+    };
+
+    // Same scope as `!search`
+    if let Some(guild_id) = state.guild_of(message.channel_id) {
+        query = query.scoped_to(&guild_id.0.to_string());
+    }
+
+    state.last_command_output = "".to_string();
+
+    return match state.db.select_random_resource(&query, &[]).first() {
+        Some(resource) => Response::Embed(resource_embed(state, resource, locale)),
+        None => Response::Text(tr(locale, "random.nothing")),
+    };
+}
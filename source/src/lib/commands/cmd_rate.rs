@@ -0,0 +1,129 @@
+use super::super::bot_state::BotState;
+use super::super::i18n::{tr, tr_args};
+use super::cmd_delete_resource::find_resource;
+use super::command::{Arg, ArgKind, Args, Command, Permission};
+use super::response::Response;
+use discord::model::{Message, Reaction, ReactionEmoji};
+use serde_json::Value;
+
+/// Reactions that vote 1 to 5 stars on a resource shown by the bot
+pub const RATING_EMOJIS: &[&str] = &["1️⃣", "2️⃣", "3️⃣", "4️⃣", "5️⃣"];
+
+pub fn command() -> Command {
+    return Command {
+        name: "rate",
+        aliases: &["valorar", "puntuar"],
+        args: &[
+            Arg {
+                name: "id|url",
+                kind: ArgKind::Word,
+                required: true,
+            },
+            Arg {
+                name: "puntos",
+                kind: ArgKind::Integer,
+                required: true,
+            },
+        ],
+        help: "help.rate",
+        permission: Permission::Member,
+        ephemeral: false,
+        guild_only: true,
+        handler: cmd_rate,
+    };
+}
+
+#[allow(dead_code)]
+pub fn cmd_rate(state: &mut BotState, message: &Message, args: &Args) -> Response {
+    let locale = args.locale.as_str();
+    let guild_id = match state.guild_of(message.channel_id) {
+        Some(guild_id) => guild_id.0.to_string(),
+        None => return Response::error(tr(locale, "command.guild_only")),
+    };
+    state.last_command_output = "".to_string();
+
+    let reference = args.text("id|url").unwrap_or("").trim();
+    let score = match args.integer("puntos") {
+        Some(score) if score >= 1 && score <= RATING_EMOJIS.len() as i64 => score as i32,
+        _ => return Response::error(tr(locale, "rate.bad_score")),
+    };
+    let resource = match find_resource(state, &guild_id, reference) {
+        Some(resource) if resource.deleted_at.is_none() => resource,
+        _ => return Response::error(tr_args(locale, "resource.not_found", &[("id", reference)])),
+    };
+    let id = resource.id;
+
+    if !state
+        .db
+        .rate_resource(id, &message.author.id.to_string(), score)
+    {
+        state.last_command_output = tr(locale, "rate.failed");
+        return Response::error(state.last_command_output.clone());
+    }
+
+    // Read back for the new average
+    let resource = state.db.select_resource(&guild_id, id).unwrap_or(resource);
+    return Response::Text(tr_args(
+        locale,
+        "rate.rated",
+        &[
+            ("user", &message.author.mention().to_string()),
+            ("id", &id.to_string()),
+            ("score", &score.to_string()),
+            ("rating", &format!("{:.1}", resource.rating)),
+            ("votes", &resource.votes.to_string()),
+        ],
+    ));
+}
+
+/// Votes for the resource shown in the message `reaction` was added to
+pub fn rate_by_reaction(state: &mut BotState, reaction: &Reaction) {
+    if let Some((id, score)) = rated_resource(state, reaction) {
+        state
+            .db
+            .rate_resource(id, &reaction.user_id.to_string(), score);
+    }
+}
+
+/// Takes back the vote of a rating reaction that was removed
+pub fn unrate_by_reaction(state: &mut BotState, reaction: &Reaction) {
+    if let Some((id, score)) = rated_resource(state, reaction) {
+        state
+            .db
+            .unrate_resource(id, &reaction.user_id.to_string(), score);
+    }
+}
+
+/// Resource and score a rating reaction is about, resource embeds of the
+/// bot have `#id` as their footer
+fn rated_resource(state: &mut BotState, reaction: &Reaction) -> Option<(i64, i32)> {
+    let score = match &reaction.emoji {
+        ReactionEmoji::Unicode(emoji) => {
+            match RATING_EMOJIS.iter().position(|rating| rating == emoji) {
+                Some(index) => index as i32 + 1,
+                None => return None,
+            }
+        }
+        _ => return None,
+    };
+    let guild_id = state.guild_of(reaction.channel_id)?.0.to_string();
+    let message = state
+        .discord
+        .get_message(reaction.channel_id, reaction.message_id)
+        .ok()?;
+    if message.author.id != state.cache.read().unwrap().user().id {
+        return None;
+    }
+
+    let id = message.embeds.iter().find_map(footer_id)?;
+    return match state.db.select_resource(&guild_id, id) {
+        Some(resource) if resource.deleted_at.is_none() => Some((id, score)),
+        _ => None,
+    };
+}
+
+/// Id of the resource in a footer like `#42`
+fn footer_id(embed: &Value) -> Option<i64> {
+    let footer = embed.get("footer")?.get("text")?.as_str()?;
+    return footer.strip_prefix('#')?.parse::<i64>().ok();
+}
@@ -0,0 +1,89 @@
+use super::super::bot_state::BotState;
+use super::super::i18n::{tr, tr_args};
+use super::command::{Arg, ArgKind, Args, Command, Permission};
+use super::response::Response;
+use discord::model::Message;
+
+pub fn command() -> Command {
+    return Command {
+        name: "restore",
+        aliases: &["restaurar"],
+        args: &[Arg {
+            name: "id",
+            kind: ArgKind::Word,
+            required: false,
+        }],
+        help: "help.restore",
+        permission: Permission::Admin,
+        ephemeral: false,
+        guild_only: true,
+        handler: cmd_restore_resource,
+    };
+}
+
+#[allow(dead_code)]
+pub fn cmd_restore_resource(state: &mut BotState, message: &Message, args: &Args) -> Response {
+    let locale = args.locale.as_str();
+    let server_id = match state.guild_of(message.channel_id) {
+        Some(server_id) => server_id,
+        None => return Response::error(tr(locale, "command.guild_only")),
+    };
+    let guild_id = server_id.0.to_string();
+    state.last_command_output = "".to_string();
+
+    // Without an id, list what can be restored
+    let reference = match args.text("id") {
+        Some(reference) => reference,
+        None => {
+            let deleted = state.db.select_deleted_resources(&guild_id, 10);
+            if deleted.is_empty() {
+                return Response::Text(tr(locale, "restore.nothing"));
+            }
+
+            // Sent as text, names instead of mentions so nobody is pinged
+            let mut response = tr(locale, "restore.title");
+            for resource in deleted {
+                response = format!(
+                    "{}\n{}",
+                    response,
+                    tr_args(
+                        locale,
+                        "restore.item",
+                        &[
+                            ("id", &resource.id.to_string()),
+                            ("url", &resource.url),
+                            ("user", &state.member_name(server_id, &resource.deleted_by)),
+                        ],
+                    )
+                );
+            }
+            return Response::Text(response);
+        }
+    };
+
+    let id = match reference.trim_start_matches('#').parse::<i64>() {
+        Ok(id) => id,
+        Err(_) => {
+            return Response::error(tr_args(locale, "resource.not_found", &[("id", reference)]))
+        }
+    };
+    let resource = match state.db.select_resource(&guild_id, id) {
+        Some(resource) => resource,
+        None => {
+            return Response::error(tr_args(locale, "resource.not_found", &[("id", reference)]))
+        }
+    };
+    if resource.deleted_at.is_none() {
+        return Response::error(tr_args(locale, "restore.not_deleted", &[("id", reference)]));
+    }
+
+    if !state.db.restore_resource(&guild_id, id) {
+        state.last_command_output = tr_args(locale, "restore.failed", &[("url", &resource.url)]);
+        return Response::error(state.last_command_output.clone());
+    }
+    return Response::Text(tr_args(
+        locale,
+        "restore.restored",
+        &[("id", &id.to_string()), ("url", &resource.url)],
+    ));
+}
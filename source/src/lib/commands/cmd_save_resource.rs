@@ -1,31 +1,198 @@
 use super::super::bot_state::BotState;
-use super::super::custom_database::Resource;
-use discord::model::{Message, ReactionEmoji};
+use super::super::embed::find_message_link;
+use super::super::i18n::{tr, tr_args};
+use super::super::resource::Resource;
+use super::super::resource_type::ResourceType;
+use super::super::tags::find_hashtags;
+use super::command::{Arg, ArgKind, Args, Command, Permission};
+use super::response::Response;
+use discord::model::{ChannelId, Message, MessageId, ReactionEmoji};
+
+pub fn command() -> Command {
+    return Command {
+        name: "save",
+        aliases: &["guardar"],
+        args: &[Arg {
+            name: "enlace",
+            kind: ArgKind::Text,
+            required: false,
+        }],
+        help: "help.save",
+        permission: Permission::Member,
+        ephemeral: false,
+        guild_only: true,
+        handler: cmd_save_resource,
+    };
+}
 
 #[allow(dead_code)]
-pub fn cmd_save_resource(state: &mut BotState, message: &Message) -> String {
-    let resource = Resource::new(message);
-    let save_result = state.db.insert_resource(resource);
+pub fn cmd_save_resource(state: &mut BotState, message: &Message, args: &Args) -> Response {
+    let locale = args.locale.as_str();
+    let guild_id = match state.guild_of(message.channel_id) {
+        Some(guild_id) => guild_id,
+        None => return Response::error(tr(locale, "save.guild_only")),
+    };
+
+    // Other messages are saved through their link or by answering them with
+    // `!save`. Only messages of this server, the bot could copy what its
+    // members can't see otherwise.
+    let linked = args.text("enlace").and_then(find_message_link);
+    // discord-rs drops reply references, they are read from the REST API
+    let replied = match (&linked, &state.client) {
+        (None, Some(client)) => client.replied_to(message.channel_id, message.id),
+        _ => None,
+    };
+    let source = match (linked, replied) {
+        (Some((linked_guild, channel_id, message_id)), _) => {
+            let channel_id = ChannelId(channel_id);
+            if linked_guild != Some(guild_id.0) || state.guild_of(channel_id) != Some(guild_id) {
+                return Response::error(tr(locale, "save.other_server"));
+            }
+            state
+                .discord
+                .get_message(channel_id, MessageId(message_id))
+                .ok()
+        }
+        (None, Some((channel_id, message_id))) => {
+            if state.guild_of(channel_id) != Some(guild_id) {
+                return Response::error(tr(locale, "save.other_server"));
+            }
+            state.discord.get_message(channel_id, message_id).ok()
+        }
+        (None, None) => Some(message.clone()),
+    };
+
+    let mut resources = match &source {
+        Some(source) => Resource::from_message(source, &guild_id),
+        None => Vec::new(),
+    };
+    if resources.is_empty() && linked.is_none() && replied.is_none() {
+        state.last_command_output = tr(locale, "save.needs_link");
+        return Response::error(state.last_command_output.clone());
+    }
+
+    // `!save #rust #async type:course <url>` tags and types everything it saves
+    let tags = find_hashtags(args.text("enlace").unwrap_or(""));
+    let kind = match requested_type(args.text("enlace").unwrap_or("")) {
+        Ok(kind) => kind,
+        Err(value) => {
+            return Response::error(tr_args(locale, "query.bad_type", &[("value", &value)]))
+        }
+    };
+    for resource in resources.iter_mut() {
+        resource.tags = tags.clone();
+        if let Some(kind) = kind {
+            resource.type_id = kind.id();
+        }
+    }
+
+    let mut found = resources.len();
+    let mut saved = 0;
+    let mut duplicates: Vec<String> = Vec::new();
+    let mut ids: Vec<i64> = Vec::new();
+    for resource in resources {
+        let resource_guild = resource.guild_id.clone();
+        if let Some(saved_before) = state
+            .db
+            .select_resource_by_hash(&resource_guild, &resource.shash)
+        {
+            let date = match saved_before.created_at {
+                Some(date) => date.format("%Y-%m-%d").to_string(),
+                None => tr(locale, "save.long_ago"),
+            };
+            duplicates.push(tr_args(
+                locale,
+                "save.duplicate",
+                &[
+                    ("user", &state.member_name(guild_id, &saved_before.user_id)),
+                    ("date", &date),
+                    ("url", &saved_before.url),
+                ],
+            ));
+            found -= 1;
+            continue;
+        }
+        let shash = resource.shash.clone();
+        if state.db.insert_resource(resource) {
+            saved += 1;
+            if let Some(inserted) = state.db.select_resource_by_hash(&resource_guild, &shash) {
+                ids.push(inserted.id);
+            }
+        }
+    }
+
+    // What `!undo` takes back
+    if !ids.is_empty() {
+        state
+            .last_saves
+            .lock()
+            .unwrap()
+            .insert((guild_id.0, message.author.id.0), ids.clone());
+    }
+    let ids = ids
+        .iter()
+        .map(|id| format!("#{}", id))
+        .collect::<Vec<String>>()
+        .join(", ");
 
     let unicode_reaction;
-    if save_result {
-        unicode_reaction = "👍";
+    if found == 0 && !duplicates.is_empty() {
+        unicode_reaction = "🔁";
         state.last_command_output = "".to_string();
+    } else if found > 0 && saved == found {
+        unicode_reaction = "👍";
+        state.last_command_output = tr_args(
+            locale,
+            "save.saved",
+            &[("count", &saved.to_string()), ("ids", &ids)],
+        );
+    } else if found == 0 {
+        unicode_reaction = "🤷";
+        state.last_command_output = tr_args(
+            locale,
+            "save.nothing_found",
+            &[("user", &message.author.mention().to_string())],
+        );
     } else {
         unicode_reaction = "👎";
 
-        state.last_command_output = format!(
-            "No se pudo salvar la informacion de el mensaje de {}",
-            message.author.mention()
+        state.last_command_output = tr_args(
+            locale,
+            "save.partial",
+            &[
+                ("saved", &saved.to_string()),
+                ("found", &found.to_string()),
+                ("user", &message.author.mention().to_string()),
+            ],
         );
     }
 
-    let reaction = ReactionEmoji::Unicode(unicode_reaction.to_string());
+    if duplicates.is_empty() {
+        return Response::Reaction(unicode_reaction.to_string());
+    }
 
-    state
+    // The duplicates need a reply, the reaction still tells how it went
+    let reaction = ReactionEmoji::Unicode(unicode_reaction.to_string());
+    let _ = state
         .discord
-        .add_reaction(message.channel_id, message.id, reaction)
-        .unwrap();
+        .add_reaction(message.channel_id, message.id, reaction);
 
-    return "".to_string();
+    return Response::Text(duplicates.join("\n"));
+}
+
+/// Type given with `type:` or `tipo:`, the rejected value on error
+fn requested_type(text: &str) -> Result<Option<ResourceType>, String> {
+    for word in text.split_whitespace() {
+        let value = match word.find(':') {
+            Some(index) if ["type", "tipo"].contains(&word[..index].to_lowercase().as_str()) => {
+                &word[index + 1..]
+            }
+            _ => continue,
+        };
+        return match ResourceType::from_name(value) {
+            Some(kind) => Ok(Some(kind)),
+            None => Err(value.to_string()),
+        };
+    }
+    return Ok(None);
 }
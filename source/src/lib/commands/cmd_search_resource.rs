@@ -1,35 +1,300 @@
 use super::super::bot_state::BotState;
-use discord::model::Message;
-use regex::Regex;
+use super::super::i18n::{tr, tr_args};
+use super::super::resource::Resource;
+use super::super::resource_type::ResourceType;
+use super::super::search_query::SearchQuery;
+use super::command::{Arg, ArgKind, Args, Command, Permission};
+use super::response::{Response, ResponseEmbed};
+use discord::model::{ChannelId, Message, MessageId, Reaction, ReactionEmoji};
+use discord::Discord;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Results per page of `!search`
+pub const PAGE_SIZE: u16 = 5;
+pub const PREVIOUS_PAGE: &str = "⬅️";
+pub const NEXT_PAGE: &str = "➡️";
+/// Time a search can be paged through, it starts over with every page
+pub const SESSION_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// A search that can still be paged through with reactions
+#[derive(Clone)]
+pub struct SearchSession {
+    pub query: SearchQuery,
+    /// What was searched for, shown in the title
+    pub input: String,
+    pub page: u16,
+    pub locale: String,
+    pub channel_id: ChannelId,
+    pub expires_at: Instant,
+}
+
+pub fn command() -> Command {
+    return Command {
+        name: "search",
+        aliases: &["buscar"],
+        args: &[Arg {
+            name: "consulta",
+            kind: ArgKind::Text,
+            required: true,
+        }],
+        help: "help.search",
+        permission: Permission::Member,
+        ephemeral: false,
+        guild_only: false,
+        handler: cmd_search_resource,
+    };
+}
 
 #[allow(dead_code)]
-pub fn cmd_search_resource(state: &mut BotState, message: &Message) -> String {
-    let input = message.content.as_str();
-    let re_query: Regex = Regex::new(r"!search (?P<query>.*)").unwrap();
+pub fn cmd_search_resource(state: &mut BotState, message: &Message, args: &Args) -> Response {
+    let locale = args.locale.as_str();
+    let mut query = match SearchQuery::parse(args.text("consulta").unwrap_or(""), locale) {
+        Ok(query) => query,
+        Err(error) => return Response::error(error),
+    };
+
+    // Only this server's resources, private messages see the global index
+    if let Some(guild_id) = state.guild_of(message.channel_id) {
+        query = query.scoped_to(&guild_id.0.to_string());
+    }
+
+    // First page of the best ranked matches
+    let resources = state.db.select_resources(&query, PAGE_SIZE, 0);
+
+    state.last_command_output = "".to_string();
+
+    if resources.len() == 0 {
+        let author_mention = message.author.mention().to_string();
+        return Response::Text(tr_args(
+            locale,
+            "search.not_found",
+            &[("user", &author_mention)],
+        ));
+    }
+    if resources.len() == 1 {
+        return Response::Embed(resource_embed(state, &resources[0], locale));
+    }
 
-    let query = re_query
-        .captures(input)
-        .and_then(|cap| cap.name("query").map(|login| login.as_str()))
-        .unwrap();
+    let input = args.text("consulta").unwrap_or("").trim().to_string();
+    let embed = page_embed(state, &resources, &input, 0, locale);
 
-    let resource = state.db.select_random_resource(query);
+    // Only a full page may have more behind it, the session starts once
+    // the reply is sent, see `start_session`
+    if resources.len() == PAGE_SIZE as usize {
+        state.pending_search = Some(SearchSession {
+            query: query,
+            input: input,
+            page: 0,
+            locale: locale.to_string(),
+            channel_id: message.channel_id,
+            expires_at: Instant::now() + SESSION_TIMEOUT,
+        });
+    }
+    return Response::Embed(embed);
+}
 
-    let author_mention = message.author.mention();
+/// Adds the paging controls to `message_id`, the reply of the search that
+/// left `session` behind, and lets it be paged through
+pub fn start_session(state: &mut BotState, session: SearchSession, message_id: MessageId) {
+    for emoji in &[PREVIOUS_PAGE, NEXT_PAGE] {
+        let _ = state.discord.add_reaction(
+            session.channel_id,
+            message_id,
+            ReactionEmoji::Unicode(emoji.to_string()),
+        );
+    }
+    state
+        .search_sessions
+        .lock()
+        .unwrap()
+        .insert(message_id, session);
+}
+
+/// Shows the previous or the next page of the search in the message
+/// `reaction` was added to
+pub fn turn_page(state: &mut BotState, reaction: &Reaction) {
+    let step = match &reaction.emoji {
+        ReactionEmoji::Unicode(emoji) if emoji == PREVIOUS_PAGE => -1,
+        ReactionEmoji::Unicode(emoji) if emoji == NEXT_PAGE => 1,
+        _ => return,
+    };
+    let session = match state
+        .search_sessions
+        .lock()
+        .unwrap()
+        .get(&reaction.message_id)
+    {
+        Some(session) if session.expires_at > Instant::now() => session.clone(),
+        _ => return,
+    };
 
-    let mut response = format!(
-        "No he podido encontrar nada relacionado {} \
-        por favor prueba con otras palabras claves",
-        author_mention
+    // Taking the reaction back lets it be pressed again
+    let _ = state.discord.delete_reaction(
+        reaction.channel_id,
+        reaction.message_id,
+        Some(reaction.user_id),
+        reaction.emoji.clone(),
     );
 
-    if resource.len() > 0 {
-        response = format!(
-            "He encontrado eso {} espero que te sirva {}",
-            author_mention, resource[0].url
+    if step < 0 && session.page == 0 {
+        return;
+    }
+    let page = (session.page as i32 + step) as u16;
+    let resources = state.db.select_resources(&session.query, PAGE_SIZE, page);
+    if resources.is_empty() {
+        return;
+    }
+
+    let embed = page_embed(state, &resources, &session.input, page, &session.locale);
+    let _ = state
+        .discord
+        .edit_embed(reaction.channel_id, reaction.message_id, |builder| {
+            embed.build(builder)
+        });
+
+    if let Some(session) = state
+        .search_sessions
+        .lock()
+        .unwrap()
+        .get_mut(&reaction.message_id)
+    {
+        session.page = page;
+        session.expires_at = Instant::now() + SESSION_TIMEOUT;
+    }
+}
+
+/// Removes the controls of the searches nobody paged through for a while
+pub fn expire_sessions(discord: &Discord, sessions: &Mutex<HashMap<MessageId, SearchSession>>) {
+    let now = Instant::now();
+    let expired: Vec<(MessageId, SearchSession)> = {
+        let mut sessions = sessions.lock().unwrap();
+        let ids: Vec<MessageId> = sessions
+            .iter()
+            .filter(|(_, session)| session.expires_at <= now)
+            .map(|(message_id, _)| *message_id)
+            .collect();
+        ids.into_iter()
+            .filter_map(|message_id| {
+                sessions
+                    .remove(&message_id)
+                    .map(|session| (message_id, session))
+            })
+            .collect()
+    };
+
+    for (message_id, session) in expired {
+        for emoji in &[PREVIOUS_PAGE, NEXT_PAGE] {
+            let _ = discord.delete_reaction(
+                session.channel_id,
+                message_id,
+                None,
+                ReactionEmoji::Unicode(emoji.to_string()),
+            );
+        }
+    }
+}
+
+/// A page of results, a field per resource
+fn page_embed(
+    state: &mut BotState,
+    resources: &[Resource],
+    input: &str,
+    page: u16,
+    locale: &str,
+) -> ResponseEmbed {
+    let mut embed = ResponseEmbed::new(&tr_args(locale, "search.results", &[("query", input)]));
+    embed.footer = tr_args(locale, "search.page", &[("page", &(page + 1).to_string())]);
+
+    for resource in resources {
+        let title = match resource.title.is_empty() {
+            true => &resource.url,
+            false => &resource.title,
+        };
+        let kind = ResourceType::from_id(resource.type_id).unwrap_or(ResourceType::Link);
+        let mut details = vec![
+            resource.url.clone(),
+            kind.name().to_string(),
+            format!("<@{}>", resource.user_id),
+        ];
+        if !resource.tags.is_empty() {
+            details.push(hashtags(resource));
+        }
+        if resource.votes > 0 {
+            details.push(stars(resource));
+        }
+        if let Some(page) = state.resource_page(resource) {
+            details.push(format!("[{}]({})", tr(locale, "search.open"), page));
+        }
+        embed.field(
+            &format!("#{} {}", resource.id, title),
+            &details.join(" · "),
+            false,
         );
     }
+    return embed;
+}
 
-    state.last_command_output = "".to_string();
+/// A resource as an embed: what it is, who saved it and when, and its page
+/// on the website when the server shares its resources
+pub fn resource_embed(state: &mut BotState, resource: &Resource, locale: &str) -> ResponseEmbed {
+    let title = match resource.title.is_empty() {
+        true => &resource.url,
+        false => &resource.title,
+    };
+    let mut embed = ResponseEmbed::new(title);
+    embed.url = resource.url.clone();
+    embed.description = resource.description.clone();
+    embed.thumbnail_url = resource.thumbnail_url.clone();
+    embed.footer = format!("#{}", resource.id);
+    embed.timestamp = resource.created_at;
+
+    let kind = ResourceType::from_id(resource.type_id).unwrap_or(ResourceType::Link);
+    embed.field(
+        &tr(locale, "search.saved_by"),
+        &format!("<@{}>", resource.user_id),
+        true,
+    );
+    embed.field(&tr(locale, "search.type"), kind.name(), true);
+    embed.field(&tr(locale, "search.tags"), &hashtags(resource), true);
+    embed.field(&tr(locale, "search.rating"), &stars(resource), true);
+    if let Some(page) = state.resource_page(resource) {
+        embed.field(
+            &tr(locale, "search.web"),
+            &format!("[{}]({})", tr(locale, "search.open"), page),
+            false,
+        );
+    }
+    return embed;
+}
+
+/// `#id [title](url)` and its votes, a line of a list of resources
+pub fn resource_link(resource: &Resource) -> String {
+    let title = match resource.title.is_empty() {
+        true => &resource.url,
+        false => &resource.title,
+    };
+    let link = format!("#{} [{}]({})", resource.id, title, resource.url);
+    return match resource.votes {
+        0 => link,
+        _ => format!("{} {}", link, stars(resource)),
+    };
+}
+
+/// Average and number of votes, empty without votes
+pub fn stars(resource: &Resource) -> String {
+    if resource.votes == 0 {
+        return "".to_string();
+    }
+    return format!("⭐ {:.1} ({})", resource.rating, resource.votes);
+}
 
-    return response.to_string();
+fn hashtags(resource: &Resource) -> String {
+    return resource
+        .tags
+        .iter()
+        .map(|tag| format!("#{}", tag))
+        .collect::<Vec<String>>()
+        .join(" ");
 }
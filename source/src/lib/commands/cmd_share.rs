@@ -0,0 +1,59 @@
+use super::super::bot_state::BotState;
+use super::super::i18n::{tr, tr_args};
+use super::command::{Arg, ArgKind, Args, Command, Permission};
+use super::response::Response;
+use discord::model::Message;
+
+pub fn command() -> Command {
+    return Command {
+        name: "share",
+        aliases: &["compartir"],
+        args: &[Arg {
+            name: "on|off",
+            kind: ArgKind::Word,
+            required: false,
+        }],
+        help: "help.share",
+        permission: Permission::Admin,
+        ephemeral: false,
+        guild_only: true,
+        handler: cmd_share,
+    };
+}
+
+#[allow(dead_code)]
+pub fn cmd_share(state: &mut BotState, message: &Message, args: &Args) -> Response {
+    let locale = args.locale.as_str();
+    let guild_id = match state.guild_of(message.channel_id) {
+        Some(guild_id) => guild_id.0.to_string(),
+        None => return Response::error(tr(locale, "command.guild_only")),
+    };
+
+    let mut settings = state.settings_of(&guild_id);
+    state.last_command_output = "".to_string();
+
+    settings.share_global = match args.text("on|off").map(|value| value.to_lowercase()) {
+        None => {
+            return match settings.share_global {
+                true => Response::Text(tr(locale, "share.is_public")),
+                false => Response::Text(tr(locale, "share.is_private")),
+            }
+        }
+        Some(value) if value == "on" || value == "si" => true,
+        Some(value) if value == "off" || value == "no" => false,
+        Some(value) => {
+            return Response::error(tr_args(locale, "share.unknown_value", &[("value", &value)]))
+        }
+    };
+
+    let share_global = settings.share_global;
+    if !state.save_settings(settings) {
+        state.last_command_output = tr(locale, "share.save_failed");
+        return Response::error(state.last_command_output.clone());
+    }
+
+    return match share_global {
+        true => Response::Text(tr(locale, "share.now_public")),
+        false => Response::Text(tr(locale, "share.now_private")),
+    };
+}
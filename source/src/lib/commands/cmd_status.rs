@@ -1,11 +1,27 @@
 use super::super::bot_state::BotState;
+use super::super::i18n::tr;
+use super::command::{Args, Command, Permission};
+use super::response::Response;
 use discord::model::Message;
 
+pub fn command() -> Command {
+    return Command {
+        name: "status",
+        aliases: &["estado"],
+        args: &[],
+        help: "help.status",
+        permission: Permission::Member,
+        ephemeral: true,
+        guild_only: false,
+        handler: cmd_status,
+    };
+}
+
 #[allow(dead_code)]
-pub fn cmd_status(state: &mut BotState, _: &Message) -> String {
+pub fn cmd_status(state: &mut BotState, _: &Message, args: &Args) -> Response {
     if state.last_command_output.is_empty() {
-        return "No hay nada que reportar".to_string();
+        return Response::Text(tr(&args.locale, "status.nothing"));
     }
 
-    return state.last_command_output.clone();
+    return Response::Text(state.last_command_output.clone());
 }
@@ -0,0 +1,97 @@
+use super::super::bot_state::BotState;
+use super::super::i18n::{tr, tr_args};
+use super::super::tags::normalize;
+use super::cmd_delete_resource::{can_manage, find_resource};
+use super::command::{Arg, ArgKind, Args, Command, Permission};
+use super::response::Response;
+use discord::model::Message;
+
+pub fn command() -> Command {
+    return Command {
+        name: "tag",
+        aliases: &["etiquetar"],
+        args: &[
+            Arg {
+                name: "id",
+                kind: ArgKind::Word,
+                required: true,
+            },
+            Arg {
+                name: "cambios",
+                kind: ArgKind::Text,
+                required: true,
+            },
+        ],
+        help: "help.tag",
+        permission: Permission::Member,
+        ephemeral: false,
+        guild_only: true,
+        handler: cmd_tag,
+    };
+}
+
+#[allow(dead_code)]
+pub fn cmd_tag(state: &mut BotState, message: &Message, args: &Args) -> Response {
+    let locale = args.locale.as_str();
+    let guild_id = match state.guild_of(message.channel_id) {
+        Some(guild_id) => guild_id.0.to_string(),
+        None => return Response::error(tr(locale, "command.guild_only")),
+    };
+    let reference = args.text("id").unwrap_or("");
+    state.last_command_output = "".to_string();
+
+    let resource = match find_resource(state, &guild_id, reference) {
+        Some(resource) if resource.deleted_at.is_none() => resource,
+        Some(_) => {
+            return Response::error(tr_args(
+                locale,
+                "resource.already_deleted",
+                &[("id", reference)],
+            ))
+        }
+        None => {
+            return Response::error(tr_args(locale, "resource.not_found", &[("id", reference)]))
+        }
+    };
+    if !can_manage(state, message, &resource) {
+        return Response::error(tr(locale, "resource.not_owner"));
+    }
+
+    // `+tag` or `tag` adds it, `-tag` removes it
+    let mut tags = resource.tags.clone();
+    for change in args.text("cambios").unwrap_or("").split_whitespace() {
+        let tag = match normalize(change.trim_start_matches(|c| c == '+' || c == '-')) {
+            Some(tag) => tag,
+            None => continue,
+        };
+        if change.starts_with('-') {
+            tags.retain(|current| current != &tag);
+        } else if !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    tags.sort();
+
+    if !state.db.set_resource_tags(resource.id, &tags) {
+        state.last_command_output = tr(locale, "resource.save_failed");
+        return Response::error(state.last_command_output.clone());
+    }
+
+    if tags.is_empty() {
+        return Response::Text(tr_args(
+            locale,
+            "tag.untagged",
+            &[("id", &resource.id.to_string())],
+        ));
+    }
+    let tags = tags
+        .iter()
+        .map(|tag| format!("#{}", tag))
+        .collect::<Vec<String>>()
+        .join(" ");
+    return Response::Text(tr_args(
+        locale,
+        "tag.tagged",
+        &[("id", &resource.id.to_string()), ("tags", &tags)],
+    ));
+}
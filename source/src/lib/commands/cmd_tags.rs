@@ -0,0 +1,40 @@
+use super::super::bot_state::BotState;
+use super::super::i18n::{tr, tr_args};
+use super::command::{Args, Command, Permission};
+use super::response::Response;
+use discord::model::Message;
+
+pub fn command() -> Command {
+    return Command {
+        name: "tags",
+        aliases: &["etiquetas"],
+        args: &[],
+        help: "help.tags",
+        permission: Permission::Member,
+        ephemeral: false,
+        guild_only: true,
+        handler: cmd_tags,
+    };
+}
+
+#[allow(dead_code)]
+pub fn cmd_tags(state: &mut BotState, message: &Message, args: &Args) -> Response {
+    let locale = args.locale.as_str();
+    let guild_id = match state.guild_of(message.channel_id) {
+        Some(guild_id) => guild_id.0.to_string(),
+        None => return Response::error(tr(locale, "command.guild_only")),
+    };
+    state.last_command_output = "".to_string();
+
+    let popular = state.db.popular_tags(&guild_id, 20);
+    if popular.is_empty() {
+        return Response::Text(tr(locale, "tags.nothing"));
+    }
+
+    let tags = popular
+        .iter()
+        .map(|(tag, uses)| format!("#{} ({})", tag, uses))
+        .collect::<Vec<String>>()
+        .join(", ");
+    return Response::Text(tr_args(locale, "tags.popular", &[("tags", &tags)]));
+}
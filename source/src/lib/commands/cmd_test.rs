@@ -1,10 +1,26 @@
 use super::super::bot_state::BotState;
+use super::super::i18n::tr_args;
+use super::command::{Args, Command, Permission};
+use super::response::Response;
 use discord::model::Message;
 
+pub fn command() -> Command {
+    return Command {
+        name: "test",
+        aliases: &["hola"],
+        args: &[],
+        help: "help.test",
+        permission: Permission::Member,
+        ephemeral: false,
+        guild_only: false,
+        handler: cmd_test,
+    };
+}
+
 #[allow(dead_code)]
-pub fn cmd_test(state: &mut BotState, message: &Message) -> String {
+pub fn cmd_test(state: &mut BotState, message: &Message, args: &Args) -> Response {
     let author = &message.author.name;
-    let response = format!("Has dicho hola {}", author);
+    let response = tr_args(&args.locale, "test.hello", &[("user", author)]);
     state.last_command_output = "".to_string();
-    return response;
+    return Response::Text(response);
 }
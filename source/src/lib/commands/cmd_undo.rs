@@ -0,0 +1,55 @@
+use super::super::bot_state::BotState;
+use super::super::i18n::{tr, tr_args};
+use super::command::{Args, Command, Permission};
+use super::response::Response;
+use discord::model::Message;
+
+pub fn command() -> Command {
+    return Command {
+        name: "undo",
+        aliases: &["deshacer"],
+        args: &[],
+        help: "help.undo",
+        permission: Permission::Member,
+        ephemeral: false,
+        guild_only: true,
+        handler: cmd_undo,
+    };
+}
+
+#[allow(dead_code)]
+pub fn cmd_undo(state: &mut BotState, message: &Message, args: &Args) -> Response {
+    let locale = args.locale.as_str();
+    let guild_id = match state.guild_of(message.channel_id) {
+        Some(guild_id) => guild_id,
+        None => return Response::error(tr(locale, "command.guild_only")),
+    };
+    state.last_command_output = "".to_string();
+
+    let saved = state
+        .last_saves
+        .lock()
+        .unwrap()
+        .remove(&(guild_id.0, message.author.id.0));
+    let guild_id = guild_id.0.to_string();
+    let ids = match saved {
+        Some(ids) => ids,
+        None => return Response::error(tr(locale, "undo.nothing")),
+    };
+
+    let deleted_by = message.author.id.to_string();
+    let undone: Vec<String> = ids
+        .iter()
+        .filter(|id| state.db.delete_resource(&guild_id, **id, &deleted_by))
+        .map(|id| format!("#{}", id))
+        .collect();
+
+    if undone.is_empty() {
+        return Response::error(tr(locale, "undo.nothing"));
+    }
+    return Response::Text(tr_args(
+        locale,
+        "undo.undone",
+        &[("ids", &undone.join(", "))],
+    ));
+}
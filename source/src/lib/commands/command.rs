@@ -0,0 +1,266 @@
+use super::super::bot_state::BotState;
+use super::super::i18n::tr_args;
+use super::response::Response;
+use discord::model::Message;
+use serde_json::Value;
+use std::collections::HashMap;
+
+pub const DEFAULT_PREFIX: &str = "!";
+
+pub type Handler = fn(state: &mut BotState, msg: &Message, args: &Args) -> Response;
+
+/// Access levels, ordered from the least to the most privileged.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Permission {
+    Member,
+    Curator,
+    Admin,
+    BotOwner,
+}
+
+#[allow(dead_code)]
+impl Permission {
+    /// Name used in `!config role` and in the database
+    pub fn name(&self) -> &'static str {
+        return match self {
+            Permission::Member => "member",
+            Permission::Curator => "curator",
+            Permission::Admin => "admin",
+            Permission::BotOwner => "owner",
+        };
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        return match name {
+            "member" => Some(Permission::Member),
+            "curator" => Some(Permission::Curator),
+            "admin" => Some(Permission::Admin),
+            "owner" => Some(Permission::BotOwner),
+            _ => None,
+        };
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ArgKind {
+    /// A single whitespace separated token
+    Word,
+    /// A single token parsed as an integer
+    Integer,
+    /// Everything left in the message, must be the last argument
+    Text,
+}
+
+pub struct Arg {
+    pub name: &'static str,
+    pub kind: ArgKind,
+    pub required: bool,
+}
+
+#[allow(dead_code)]
+impl Arg {
+    /// Name of the slash command option, discord doesn't allow `|` in them
+    pub fn option_name(&self) -> String {
+        return self.name.replace('|', "_");
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum ArgValue {
+    Text(String),
+    Integer(i64),
+}
+
+#[derive(Debug, Default)]
+pub struct Args {
+    values: HashMap<&'static str, ArgValue>,
+    /// Locale the reply has to be written in
+    pub locale: String,
+    /// Command prefix of the server the command came from
+    pub prefix: String,
+}
+
+#[allow(dead_code)]
+impl Args {
+    pub fn text(&self, name: &str) -> Option<&str> {
+        match self.values.get(name) {
+            Some(ArgValue::Text(value)) => Some(value.as_str()),
+            _ => None,
+        }
+    }
+
+    pub fn integer(&self, name: &str) -> Option<i64> {
+        match self.values.get(name) {
+            Some(ArgValue::Integer(value)) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+pub struct Command {
+    pub name: &'static str,
+    pub aliases: &'static [&'static str],
+    pub args: &'static [Arg],
+    /// Catalog key of the description shown by `!help`
+    pub help: &'static str,
+    pub permission: Permission,
+    /// Whether the slash command replies only to whoever ran it
+    pub ephemeral: bool,
+    /// Whether it only works inside a server, slash commands don't
+    /// offer it in private messages
+    pub guild_only: bool,
+    pub handler: Handler,
+}
+
+#[allow(dead_code)]
+impl Command {
+    pub fn matches(&self, name: &str) -> bool {
+        return self.name == name || self.aliases.contains(&name);
+    }
+
+    pub fn usage(&self, prefix: &str) -> String {
+        let mut usage = format!("{}{}", prefix, self.name);
+        for arg in self.args {
+            let name = match arg.kind {
+                ArgKind::Text => format!("{}...", arg.name),
+                _ => arg.name.to_string(),
+            };
+            if arg.required {
+                usage = format!("{} <{}>", usage, name);
+            } else {
+                usage = format!("{} [{}]", usage, name);
+            }
+        }
+        return usage;
+    }
+
+    pub fn parse_args(&self, locale: &str, prefix: &str, input: &str) -> Result<Args, String> {
+        let mut values = HashMap::new();
+        let mut rest = input.trim();
+
+        for arg in self.args {
+            if rest.is_empty() {
+                if arg.required {
+                    return Err(tr_args(
+                        locale,
+                        "command.missing_arg",
+                        &[("arg", arg.name), ("usage", &self.usage(prefix))],
+                    ));
+                }
+                continue;
+            }
+
+            let (token, remainder) = match arg.kind {
+                ArgKind::Text => (rest, ""),
+                _ => match rest.find(char::is_whitespace) {
+                    Some(index) => (&rest[..index], rest[index..].trim_start()),
+                    None => (rest, ""),
+                },
+            };
+
+            let value = match arg.kind {
+                ArgKind::Integer => match token.parse::<i64>() {
+                    Ok(number) => ArgValue::Integer(number),
+                    Err(_) => {
+                        return Err(tr_args(
+                            locale,
+                            "command.not_a_number",
+                            &[
+                                ("arg", arg.name),
+                                ("value", token),
+                                ("usage", &self.usage(prefix)),
+                            ],
+                        ))
+                    }
+                },
+                _ => ArgValue::Text(token.to_string()),
+            };
+
+            values.insert(arg.name, value);
+            rest = remainder;
+        }
+
+        return Ok(Args {
+            values: values,
+            locale: locale.to_string(),
+            prefix: prefix.to_string(),
+        });
+    }
+
+    /// Like `parse_args` for the options of a slash command, by option name
+    pub fn args_from_options(
+        &self,
+        locale: &str,
+        prefix: &str,
+        options: &HashMap<String, Value>,
+    ) -> Result<Args, String> {
+        let mut values = HashMap::new();
+
+        for arg in self.args {
+            let token = match options.get(&arg.option_name()) {
+                Some(Value::String(text)) => text.trim().to_string(),
+                Some(Value::Number(number)) => number.to_string(),
+                _ => "".to_string(),
+            };
+            if token.is_empty() {
+                if arg.required {
+                    return Err(tr_args(
+                        locale,
+                        "command.missing_arg",
+                        &[("arg", arg.name), ("usage", &self.usage(prefix))],
+                    ));
+                }
+                continue;
+            }
+
+            let value = match arg.kind {
+                ArgKind::Integer => match token.parse::<i64>() {
+                    Ok(number) => ArgValue::Integer(number),
+                    Err(_) => {
+                        return Err(tr_args(
+                            locale,
+                            "command.not_a_number",
+                            &[
+                                ("arg", arg.name),
+                                ("value", &token),
+                                ("usage", &self.usage(prefix)),
+                            ],
+                        ))
+                    }
+                },
+                _ => ArgValue::Text(token),
+            };
+            values.insert(arg.name, value);
+        }
+
+        return Ok(Args {
+            values: values,
+            locale: locale.to_string(),
+            prefix: prefix.to_string(),
+        });
+    }
+}
+
+/// Finds the command invoked by `content` and parses its arguments.
+/// Returns `None` when the message is not addressed to the bot.
+pub fn parse<'a>(
+    commands: &'a [Command],
+    locale: &str,
+    prefix: &str,
+    content: &str,
+) -> Option<Result<(&'a Command, Args), String>> {
+    let body = content.strip_prefix(prefix)?;
+    let (name, rest) = match body.find(char::is_whitespace) {
+        Some(index) => (&body[..index], &body[index..]),
+        None => (body, ""),
+    };
+    let name = name.to_lowercase();
+
+    let command = commands.iter().find(|command| command.matches(&name))?;
+
+    return Some(
+        command
+            .parse_args(locale, prefix, rest)
+            .map(|args| (command, args)),
+    );
+}
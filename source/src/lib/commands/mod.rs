@@ -1,4 +1,120 @@
+pub mod cmd_bookmark;
+pub mod cmd_collection;
+pub mod cmd_config;
+pub mod cmd_delete_resource;
+pub mod cmd_digest;
+pub mod cmd_edit_resource;
+pub mod cmd_help;
+pub mod cmd_lang;
+pub mod cmd_mylist;
+pub mod cmd_random;
+pub mod cmd_rate;
+pub mod cmd_restore_resource;
 pub mod cmd_save_resource;
 pub mod cmd_search_resource;
+pub mod cmd_share;
 pub mod cmd_status;
+pub mod cmd_tag;
+pub mod cmd_tags;
 pub mod cmd_test;
+pub mod cmd_undo;
+pub mod command;
+pub mod response;
+#[cfg(test)]
+mod tests;
+
+use super::bot_state::BotState;
+use super::i18n::{tr, tr_args};
+use command::{Args, Command};
+use discord::model::Message;
+use response::Response;
+
+/// Every command understood by the bot, in the order listed by `!help`
+pub fn registry() -> Vec<Command> {
+    return vec![
+        cmd_test::command(),
+        cmd_save_resource::command(),
+        cmd_search_resource::command(),
+        cmd_random::command(),
+        cmd_digest::command(),
+        cmd_rate::command(),
+        cmd_bookmark::command(),
+        cmd_mylist::command(),
+        cmd_collection::command(),
+        cmd_delete_resource::command(),
+        cmd_edit_resource::command(),
+        cmd_undo::command(),
+        cmd_tag::command(),
+        cmd_tags::command(),
+        cmd_restore_resource::command(),
+        cmd_share::command(),
+        cmd_config::command(),
+        cmd_status::command(),
+        cmd_lang::command(),
+        cmd_help::command(),
+    ];
+}
+
+/// Reply to `message` when it invokes one of `commands`, `None` when it
+/// isn't addressed to the bot or comes from a channel the bot must ignore
+#[allow(dead_code)]
+pub fn run_message(
+    commands: &[Command],
+    state: &mut BotState,
+    message: &Message,
+) -> Option<Response> {
+    let prefix = state.prefix_of(message);
+    if !message.content.starts_with(prefix.as_str()) {
+        return None;
+    }
+    let locale = state.locale_of(message);
+    let parsed = command::parse(commands, &locale, &prefix, message.content.as_str())?;
+
+    // Outside the allowed channels only `config` answers, so admins can't lock themselves out
+    if !state.channel_allowed(message) {
+        match &parsed {
+            Ok((command, _)) if command.name == cmd_config::NAME => {}
+            _ => return None,
+        }
+    }
+
+    return match parsed {
+        Ok((command, args)) => Some(run_command(command, &args, message, state)),
+        Err(error) => Some(Response::error(error)),
+    };
+}
+
+/// Runs `command` for `message` if its author is allowed to, returns the reply
+#[allow(dead_code)]
+pub fn run_command(
+    command: &Command,
+    args: &Args,
+    message: &Message,
+    state: &mut BotState,
+) -> Response {
+    if state.permission_of(message) < command.permission {
+        return Response::error(tr_args(
+            &args.locale,
+            "command.forbidden",
+            &[
+                ("user", &message.author.mention().to_string()),
+                ("command", &format!("{}{}", args.prefix, command.name)),
+                (
+                    "permission",
+                    &tr(
+                        &args.locale,
+                        &format!("permission.{}", command.permission.name()),
+                    ),
+                ),
+            ],
+        ));
+    }
+
+    // Handlers read and write the output of the last command as
+    // if they were alone, workers share it through `last_output`
+    state.last_command_output = state.last_output.lock().unwrap().clone();
+    state.pending_search = None;
+    let response = (command.handler)(state, message, args);
+    *state.last_output.lock().unwrap() = state.last_command_output.clone();
+    return response;
+}
@@ -0,0 +1,177 @@
+use chrono::{DateTime, Utc};
+use discord::builders::EmbedBuilder;
+use serde_json::{json, Value};
+
+/// Colour of the embeds the bot answers with
+pub const COLOUR: u64 = 0xDEA584;
+/// Colour of the embeds reporting that something went wrong
+pub const ERROR_COLOUR: u64 = 0xE74C3C;
+
+/// Discord limits, longer texts are cut
+const MAX_TITLE: usize = 256;
+const MAX_DESCRIPTION: usize = 4096;
+const MAX_FIELD_VALUE: usize = 1024;
+
+/// What a command answers with
+#[allow(dead_code)]
+pub enum Response {
+    Text(String),
+    Embed(ResponseEmbed),
+    /// An emoji reaction to the message of the command
+    Reaction(String),
+    Nothing,
+}
+
+#[allow(dead_code)]
+impl Response {
+    /// Something the user did wrong or that failed, in the error colour
+    pub fn error(text: String) -> Self {
+        let mut embed = ResponseEmbed::new("");
+        embed.description = text;
+        embed.colour = ERROR_COLOUR;
+        return Response::Embed(embed);
+    }
+
+    /// Plain text version, for replies that can't be an embed
+    pub fn text(&self) -> String {
+        return match self {
+            Response::Text(text) => text.clone(),
+            Response::Embed(embed) => embed.text(),
+            Response::Reaction(emoji) => emoji.clone(),
+            Response::Nothing => "".to_string(),
+        };
+    }
+}
+
+pub struct EmbedField {
+    pub name: String,
+    pub value: String,
+    pub inline: bool,
+}
+
+/// An embed to send, unlike `embed::Embed` which is one discord received
+pub struct ResponseEmbed {
+    pub title: String,
+    pub url: String,
+    pub description: String,
+    pub colour: u64,
+    pub thumbnail_url: String,
+    pub fields: Vec<EmbedField>,
+    pub footer: String,
+    pub timestamp: Option<DateTime<Utc>>,
+}
+
+#[allow(dead_code)]
+impl ResponseEmbed {
+    pub fn new(title: &str) -> Self {
+        return Self {
+            title: title.to_string(),
+            url: "".to_string(),
+            description: "".to_string(),
+            colour: COLOUR,
+            thumbnail_url: "".to_string(),
+            fields: Vec::new(),
+            footer: "".to_string(),
+            timestamp: None,
+        };
+    }
+
+    /// Adds a field unless `value` is empty, discord rejects empty fields
+    pub fn field(&mut self, name: &str, value: &str, inline: bool) {
+        if value.trim().is_empty() {
+            return;
+        }
+        self.fields.push(EmbedField {
+            name: cut(name, MAX_TITLE),
+            value: cut(value, MAX_FIELD_VALUE),
+            inline: inline,
+        });
+    }
+
+    /// Fills a discord-rs builder, for `send_embed`
+    pub fn build(&self, builder: EmbedBuilder) -> EmbedBuilder {
+        let mut builder = builder.color(self.colour);
+        if !self.title.is_empty() {
+            builder = builder.title(&cut(&self.title, MAX_TITLE));
+        }
+        if !self.url.is_empty() {
+            builder = builder.url(&self.url);
+        }
+        if !self.description.is_empty() {
+            builder = builder.description(&cut(&self.description, MAX_DESCRIPTION));
+        }
+        if !self.thumbnail_url.is_empty() {
+            builder = builder.thumbnail(&self.thumbnail_url);
+        }
+        if !self.fields.is_empty() {
+            builder = builder.fields(|mut fields| {
+                for field in &self.fields {
+                    fields = fields.field(&field.name, &field.value, field.inline);
+                }
+                fields
+            });
+        }
+        if !self.footer.is_empty() {
+            builder = builder.footer(|footer| footer.text(&self.footer));
+        }
+        if let Some(timestamp) = self.timestamp {
+            builder = builder.timestamp(&timestamp.to_rfc3339());
+        }
+        return builder;
+    }
+
+    /// The embed as the REST API takes it, for interaction replies
+    pub fn to_json(&self) -> Value {
+        let mut embed = json!({ "color": self.colour });
+        if !self.title.is_empty() {
+            embed["title"] = json!(cut(&self.title, MAX_TITLE));
+        }
+        if !self.url.is_empty() {
+            embed["url"] = json!(self.url);
+        }
+        if !self.description.is_empty() {
+            embed["description"] = json!(cut(&self.description, MAX_DESCRIPTION));
+        }
+        if !self.thumbnail_url.is_empty() {
+            embed["thumbnail"] = json!({ "url": self.thumbnail_url });
+        }
+        if !self.fields.is_empty() {
+            embed["fields"] = Value::Array(
+                self.fields
+                    .iter()
+                    .map(|field| {
+                        json!({"name": field.name, "value": field.value, "inline": field.inline})
+                    })
+                    .collect(),
+            );
+        }
+        if !self.footer.is_empty() {
+            embed["footer"] = json!({ "text": self.footer });
+        }
+        if let Some(timestamp) = self.timestamp {
+            embed["timestamp"] = json!(timestamp.to_rfc3339());
+        }
+        return embed;
+    }
+
+    pub fn text(&self) -> String {
+        let mut lines: Vec<String> = Vec::new();
+        for line in &[&self.title, &self.description, &self.url] {
+            if !line.is_empty() {
+                lines.push(line.to_string());
+            }
+        }
+        for field in &self.fields {
+            lines.push(format!("{}: {}", field.name, field.value));
+        }
+        return lines.join("\n");
+    }
+}
+
+fn cut(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+    let cut: String = text.chars().take(max - 1).collect();
+    return format!("{}…", cut);
+}
@@ -0,0 +1,637 @@
+use super::super::i18n::{tr, tr_args, DEFAULT_LOCALE};
+use super::super::resource_type::ResourceType;
+use super::super::testing::*;
+use super::cmd_search_resource::PAGE_SIZE;
+use super::response::Response;
+use serde_json::json;
+
+const LOCALE: &str = DEFAULT_LOCALE;
+
+fn guild() -> String {
+    return GUILD_ID.to_string();
+}
+
+#[test]
+fn test_greets_the_author() {
+    let mut state = bot();
+    let response = run(&mut state, MEMBER_ID, "!test");
+    assert_eq!(
+        response.text(),
+        tr_args(
+            LOCALE,
+            "test.hello",
+            &[("user", &format!("user{}", MEMBER_ID))]
+        )
+    );
+}
+
+#[test]
+fn unknown_commands_and_other_prefixes_are_ignored() {
+    let mut state = bot();
+    assert!(matches!(
+        run(&mut state, MEMBER_ID, "!nope"),
+        Response::Nothing
+    ));
+    assert!(matches!(
+        run(&mut state, MEMBER_ID, "hello"),
+        Response::Nothing
+    ));
+}
+
+#[test]
+fn save_stores_links_with_their_tags() {
+    let mut state = bot();
+    let response = run(&mut state, MEMBER_ID, "!save #Rust https://example.com/a");
+    assert_eq!(response.text(), "👍");
+
+    let resource = state.db.select_resource(&guild(), 1).unwrap();
+    assert_eq!(resource.url, "https://example.com/a");
+    assert_eq!(resource.user_id, MEMBER_ID.to_string());
+    assert_eq!(resource.tags, vec!["rust"]);
+}
+
+#[test]
+fn save_takes_the_type_asked_for() {
+    let mut state = bot();
+    run(
+        &mut state,
+        MEMBER_ID,
+        "!save type:course https://example.com/a",
+    );
+    let resource = state.db.select_resource(&guild(), 1).unwrap();
+    assert_eq!(resource.type_id, ResourceType::Course.id());
+
+    let response = run(
+        &mut state,
+        MEMBER_ID,
+        "!save type:nope https://example.com/b",
+    );
+    assert!(is_error(&response));
+}
+
+#[test]
+fn save_names_whoever_saved_a_duplicate_first() {
+    let mut state = bot();
+    run(&mut state, MEMBER_ID, "!save https://example.com/a");
+    let response = run(&mut state, OWNER_ID, "!save https://example.com/a");
+
+    let text = response.text();
+    assert!(text.contains("member"), "{}", text);
+    assert!(!text.contains(&format!("<@{}>", MEMBER_ID)), "{}", text);
+}
+
+#[test]
+fn save_needs_something_to_save() {
+    let mut state = bot();
+    let response = run(&mut state, MEMBER_ID, "!save");
+    assert_eq!(response.text(), tr(LOCALE, "save.needs_link"));
+}
+
+#[test]
+fn save_refuses_messages_of_other_servers() {
+    let mut state = bot();
+    let link = format!("!save https://discord.com/channels/999/{}/1", CHANNEL_ID);
+    let response = run(&mut state, MEMBER_ID, &link);
+    assert_eq!(response.text(), tr(LOCALE, "save.other_server"));
+
+    let link = format!("!save https://discord.com/channels/{}/999/1", GUILD_ID);
+    let response = run(&mut state, MEMBER_ID, &link);
+    assert_eq!(response.text(), tr(LOCALE, "save.other_server"));
+}
+
+#[test]
+fn save_only_works_in_servers() {
+    let mut state = bot();
+    let message = message_with(
+        PRIVATE_CHANNEL_ID,
+        MEMBER_ID,
+        "!save https://example.com/a",
+        json!([]),
+        json!([]),
+    );
+    let response = run_message(&mut state, &message);
+    assert_eq!(response.text(), tr(LOCALE, "save.guild_only"));
+}
+
+#[test]
+fn save_classifies_attachments_by_extension() {
+    let mut state = bot();
+    let attachment = |id: &str, filename: &str| {
+        json!({
+            "id": id,
+            "filename": filename,
+            "size": 2048,
+            "url": format!("https://cdn.discordapp.com/attachments/1/{}/{}", id, filename),
+            "proxy_url": format!("https://media.discordapp.net/attachments/1/{}/{}", id, filename),
+            "width": 640,
+            "height": 480,
+        })
+    };
+    let message = message_with(
+        CHANNEL_ID,
+        MEMBER_ID,
+        "!save",
+        json!([attachment("1", "clip.mp4"), attachment("2", "photo.png")]),
+        json!([]),
+    );
+    run_message(&mut state, &message);
+
+    let video = state.db.select_resource(&guild(), 1).unwrap();
+    assert_eq!(video.type_id, ResourceType::Video.id());
+    assert!(video.thumbnail_url.is_empty());
+    let image = state.db.select_resource(&guild(), 2).unwrap();
+    assert_eq!(image.type_id, ResourceType::Image.id());
+    assert_eq!(image.thumbnail_url, image.url);
+}
+
+#[test]
+fn undo_deletes_the_last_save_of_the_author() {
+    let mut state = bot();
+    run(&mut state, MEMBER_ID, "!save https://example.com/a");
+    run(&mut state, OWNER_ID, "!save https://example.com/b");
+
+    let response = run(&mut state, MEMBER_ID, "!undo");
+    assert_eq!(
+        response.text(),
+        tr_args(LOCALE, "undo.undone", &[("ids", "#1")])
+    );
+    assert!(state
+        .db
+        .select_resource(&guild(), 1)
+        .unwrap()
+        .deleted_at
+        .is_some());
+    assert!(state
+        .db
+        .select_resource(&guild(), 2)
+        .unwrap()
+        .deleted_at
+        .is_none());
+
+    let response = run(&mut state, MEMBER_ID, "!undo");
+    assert_eq!(response.text(), tr(LOCALE, "undo.nothing"));
+}
+
+#[test]
+fn search_shows_a_single_match_whole() {
+    let mut state = bot();
+    saved(&mut state, MEMBER_ID, "https://example.com/a", "Async Rust");
+    saved(&mut state, MEMBER_ID, "https://example.com/b", "Python");
+
+    match run(&mut state, MEMBER_ID, "!search rust") {
+        Response::Embed(embed) => assert_eq!(embed.title, "Async Rust"),
+        _ => panic!("Expected the resource"),
+    }
+    assert!(state.pending_search.is_none());
+}
+
+#[test]
+fn search_with_only_filters_lists_what_matches_them() {
+    let mut state = bot();
+    saved(&mut state, MEMBER_ID, "https://example.com/a", "Async Rust");
+    saved(&mut state, CURATOR_ID, "https://example.com/b", "Python");
+
+    let search = format!("!search author:<@{}>", MEMBER_ID);
+    match run(&mut state, MEMBER_ID, &search) {
+        Response::Embed(embed) => assert_eq!(embed.title, "Async Rust"),
+        _ => panic!("Expected the resource"),
+    }
+}
+
+#[test]
+fn search_pages_through_full_pages() {
+    let mut state = bot();
+    for index in 0..PAGE_SIZE + 1 {
+        let url = format!("https://example.com/{}", index);
+        saved(&mut state, MEMBER_ID, &url, &format!("Rust {}", index));
+    }
+
+    match run(&mut state, MEMBER_ID, "!search rust") {
+        Response::Embed(embed) => assert_eq!(embed.fields.len(), PAGE_SIZE as usize),
+        _ => panic!("Expected the first page"),
+    }
+    let session = state
+        .pending_search
+        .clone()
+        .expect("A search to page through");
+    assert_eq!(session.page, 0);
+    assert_eq!(session.input, "rust");
+}
+
+#[test]
+fn search_tells_when_nothing_matches() {
+    let mut state = bot();
+    saved(&mut state, MEMBER_ID, "https://example.com/a", "Rust");
+
+    let response = run(&mut state, MEMBER_ID, "!search haskell");
+    assert!(matches!(response, Response::Text(_)));
+    let response = run(&mut state, MEMBER_ID, "!search type:nope");
+    assert!(is_error(&response));
+}
+
+#[test]
+fn random_picks_a_saved_resource() {
+    let mut state = bot();
+    let response = run(&mut state, MEMBER_ID, "!random");
+    assert_eq!(response.text(), tr(LOCALE, "random.nothing"));
+
+    saved(&mut state, MEMBER_ID, "https://example.com/a", "Rust");
+    match run(&mut state, MEMBER_ID, "!random") {
+        Response::Embed(embed) => assert_eq!(embed.title, "Rust"),
+        _ => panic!("Expected the resource"),
+    }
+}
+
+#[test]
+fn digest_shows_what_was_saved_this_week() {
+    let mut state = bot();
+    let response = run(&mut state, MEMBER_ID, "!digest now");
+    assert_eq!(response.text(), tr(LOCALE, "digest.empty"));
+
+    saved(&mut state, MEMBER_ID, "https://example.com/a", "Rust");
+    assert!(matches!(
+        run(&mut state, MEMBER_ID, "!digest now"),
+        Response::Embed(_)
+    ));
+    assert!(is_error(&run(&mut state, MEMBER_ID, "!digest later")));
+}
+
+#[test]
+fn rate_votes_by_id_or_url() {
+    let mut state = bot();
+    let id = saved(&mut state, MEMBER_ID, "https://example.com/a", "Rust");
+
+    run(&mut state, MEMBER_ID, "!rate #1 4");
+    run(&mut state, OWNER_ID, "!rate https://example.com/a 2");
+    let resource = state.db.select_resource(&guild(), id).unwrap();
+    assert_eq!(resource.votes, 2);
+    assert_eq!(resource.rating, 3.0);
+
+    // Voting again replaces the vote
+    run(&mut state, OWNER_ID, "!rate 1 5");
+    let resource = state.db.select_resource(&guild(), id).unwrap();
+    assert_eq!(resource.votes, 2);
+    assert_eq!(resource.rating, 4.5);
+
+    let response = run(&mut state, MEMBER_ID, "!rate 1 9");
+    assert_eq!(response.text(), tr(LOCALE, "rate.bad_score"));
+    assert!(is_error(&run(&mut state, MEMBER_ID, "!rate 7 3")));
+}
+
+#[test]
+fn removed_votes_are_taken_back_only_if_unchanged() {
+    let mut state = bot();
+    let id = saved(&mut state, MEMBER_ID, "https://example.com/a", "Rust");
+    let user_id = MEMBER_ID.to_string();
+
+    assert!(state.db.rate_resource(id, &user_id, 4));
+    assert!(!state.db.unrate_resource(id, &user_id, 2));
+    assert!(state.db.unrate_resource(id, &user_id, 4));
+
+    let resource = state.db.select_resource(&guild(), id).unwrap();
+    assert_eq!(resource.votes, 0);
+    assert_eq!(resource.rating, 0.0);
+}
+
+#[test]
+fn bookmark_toggles_and_mylist_lists_them() {
+    let mut state = bot();
+    let response = run(&mut state, MEMBER_ID, "!mylist");
+    assert_eq!(response.text(), tr(LOCALE, "mylist.empty"));
+
+    saved(&mut state, OWNER_ID, "https://example.com/a", "Rust");
+    assert_eq!(run(&mut state, MEMBER_ID, "!bookmark 1").text(), "🔖");
+    match run(&mut state, MEMBER_ID, "!mylist") {
+        Response::Embed(embed) => assert!(embed.description.contains("Rust")),
+        _ => panic!("Expected the bookmarks"),
+    }
+    // Nobody else sees them
+    let response = run(&mut state, OWNER_ID, "!mylist");
+    assert_eq!(response.text(), tr(LOCALE, "mylist.empty"));
+
+    let response = run(&mut state, MEMBER_ID, "!bookmark https://example.com/a");
+    assert_eq!(
+        response.text(),
+        tr_args(LOCALE, "bookmark.removed", &[("id", "#1")])
+    );
+}
+
+#[test]
+fn collections_keep_their_order() {
+    let mut state = bot();
+    saved(&mut state, MEMBER_ID, "https://example.com/a", "First");
+    saved(&mut state, MEMBER_ID, "https://example.com/b", "Second");
+
+    run(
+        &mut state,
+        MEMBER_ID,
+        "!collection create path Learning path",
+    );
+    let response = run(&mut state, MEMBER_ID, "!collection create path");
+    assert!(is_error(&response));
+
+    run(&mut state, MEMBER_ID, "!collection add path 1 start here");
+    run(&mut state, MEMBER_ID, "!collection add path #2");
+    assert!(is_error(&run(
+        &mut state,
+        MEMBER_ID,
+        "!collection add path 1"
+    )));
+    run(&mut state, MEMBER_ID, "!collection move path 2 1");
+
+    let collection = state
+        .db
+        .select_collections(&guild(), &MEMBER_ID.to_string())
+        .pop()
+        .unwrap();
+    assert_eq!(collection.description, "Learning path");
+    let ids: Vec<i64> = collection
+        .items
+        .iter()
+        .map(|item| item.resource.id)
+        .collect();
+    assert_eq!(ids, vec![2, 1]);
+    assert_eq!(collection.items[1].note, "start here");
+
+    // Anybody can look at it
+    let show = format!("!collection show path <@{}>", MEMBER_ID);
+    match run(&mut state, OWNER_ID, &show) {
+        Response::Embed(embed) => {
+            let second = embed.description.find("Second").unwrap();
+            let first = embed.description.find("First").unwrap();
+            assert!(second < first);
+        }
+        _ => panic!("Expected the collection"),
+    }
+
+    run(&mut state, MEMBER_ID, "!collection remove path 2");
+    run(
+        &mut state,
+        MEMBER_ID,
+        "!collection describe path Start with Rust",
+    );
+    let collection = state
+        .db
+        .select_collections(&guild(), &MEMBER_ID.to_string())
+        .pop()
+        .unwrap();
+    assert_eq!(collection.items.len(), 1);
+    assert_eq!(collection.description, "Start with Rust");
+
+    assert!(matches!(
+        run(&mut state, MEMBER_ID, "!collection list"),
+        Response::Embed(_)
+    ));
+    run(&mut state, MEMBER_ID, "!collection delete path");
+    let response = run(&mut state, MEMBER_ID, "!collection list");
+    assert_eq!(response.text(), tr(LOCALE, "collection.none"));
+}
+
+#[test]
+fn delete_is_for_the_saver_and_curators() {
+    let mut state = bot();
+    saved(&mut state, OWNER_ID, "https://example.com/a", "Rust");
+
+    let response = run(&mut state, MEMBER_ID, "!delete 1");
+    assert_eq!(response.text(), tr(LOCALE, "resource.not_owner"));
+    let response = run(&mut state, CURATOR_ID, "!delete 1");
+    assert_eq!(response.text(), tr(LOCALE, "resource.not_owner"));
+
+    // Curators once the role is configured
+    run(&mut state, OWNER_ID, "!config role curator @curators");
+    run(&mut state, CURATOR_ID, "!delete https://example.com/a");
+    assert!(state
+        .db
+        .select_resource(&guild(), 1)
+        .unwrap()
+        .deleted_at
+        .is_some());
+
+    let response = run(&mut state, OWNER_ID, "!delete 1");
+    assert_eq!(
+        response.text(),
+        tr_args(LOCALE, "resource.already_deleted", &[("id", "1")])
+    );
+}
+
+#[test]
+fn restore_lists_and_restores_deleted_resources() {
+    let mut state = bot();
+    saved(&mut state, MEMBER_ID, "https://example.com/a", "Rust");
+
+    let response = run(&mut state, OWNER_ID, "!restore");
+    assert_eq!(response.text(), tr(LOCALE, "restore.nothing"));
+    assert!(is_error(&run(&mut state, MEMBER_ID, "!restore")));
+
+    run(&mut state, MEMBER_ID, "!delete 1");
+    let listing = run(&mut state, OWNER_ID, "!restore").text();
+    assert!(listing.contains("https://example.com/a"));
+    // Who deleted it is named, not pinged
+    assert!(listing.contains("member"));
+    assert!(!listing.contains("<@"));
+    run(&mut state, OWNER_ID, "!restore 1");
+    assert!(state
+        .db
+        .select_resource(&guild(), 1)
+        .unwrap()
+        .deleted_at
+        .is_none());
+}
+
+#[test]
+fn edit_changes_the_fields_of_a_resource() {
+    let mut state = bot();
+    saved(&mut state, MEMBER_ID, "https://example.com/a", "Rust");
+
+    run(&mut state, MEMBER_ID, "!edit 1 title The Rust book");
+    run(&mut state, MEMBER_ID, "!edit 1 type docs");
+    run(&mut state, MEMBER_ID, "!edit 1 tags rust Books rust");
+    let resource = state.db.select_resource(&guild(), 1).unwrap();
+    assert_eq!(resource.title, "The Rust book");
+    assert_eq!(resource.type_id, ResourceType::Documentation.id());
+    assert_eq!(resource.tags, vec!["books", "rust"]);
+
+    let response = run(&mut state, MEMBER_ID, "!edit 1 colour red");
+    assert_eq!(
+        response.text(),
+        tr_args(LOCALE, "edit.unknown_field", &[("field", "colour")])
+    );
+    let response = run(&mut state, OWNER_ID, "!edit 1 title Mine");
+    assert!(!is_error(&response));
+}
+
+#[test]
+fn tag_adds_and_removes_tags_and_tags_counts_them() {
+    let mut state = bot();
+    saved(&mut state, MEMBER_ID, "https://example.com/a", "Rust");
+    saved(&mut state, MEMBER_ID, "https://example.com/b", "Wasm");
+
+    let response = run(&mut state, MEMBER_ID, "!tags");
+    assert_eq!(response.text(), tr(LOCALE, "tags.nothing"));
+
+    run(&mut state, MEMBER_ID, "!tag 1 +rust old");
+    run(&mut state, MEMBER_ID, "!tag 1 -old");
+    run(&mut state, MEMBER_ID, "!tag 2 rust wasm");
+    let resource = state.db.select_resource(&guild(), 1).unwrap();
+    assert_eq!(resource.tags, vec!["rust"]);
+
+    let response = run(&mut state, MEMBER_ID, "!tags");
+    assert_eq!(
+        response.text(),
+        tr_args(LOCALE, "tags.popular", &[("tags", "#rust (2), #wasm (1)")])
+    );
+}
+
+#[test]
+fn share_is_for_admins() {
+    let mut state = bot();
+    assert!(is_error(&run(&mut state, MEMBER_ID, "!share on")));
+
+    let response = run(&mut state, OWNER_ID, "!share on");
+    assert_eq!(response.text(), tr(LOCALE, "share.now_public"));
+    assert!(state.settings_of(&guild()).share_global);
+    let response = run(&mut state, OWNER_ID, "!share");
+    assert_eq!(response.text(), tr(LOCALE, "share.is_public"));
+}
+
+#[test]
+fn config_changes_the_settings_of_the_server() {
+    let mut state = bot();
+    assert!(is_error(&run(&mut state, MEMBER_ID, "!config prefix ?")));
+
+    run(&mut state, OWNER_ID, "!config channels add #links");
+    run(&mut state, OWNER_ID, "!config digest day monday");
+    run(&mut state, OWNER_ID, "!config daily hour 8");
+    assert!(is_error(&run(&mut state, OWNER_ID, "!config colour red")));
+    assert!(matches!(
+        run(&mut state, OWNER_ID, "!config"),
+        Response::Text(_)
+    ));
+
+    run(&mut state, OWNER_ID, "!config prefix ?");
+    let settings = state.settings_of(&guild());
+    assert_eq!(settings.prefix, "?");
+    assert_eq!(
+        settings.allowed_channels,
+        vec![OTHER_CHANNEL_ID.to_string()]
+    );
+    assert_eq!(settings.digest_day, 1);
+    assert_eq!(settings.daily_hour, 8);
+    assert!(!state.channel_allowed(&message(MEMBER_ID, "?test")));
+
+    // The old prefix stops working
+    assert!(matches!(
+        run(&mut state, OWNER_ID, "!config"),
+        Response::Nothing
+    ));
+    assert!(matches!(
+        run(&mut state, OWNER_ID, "?config"),
+        Response::Text(_)
+    ));
+}
+
+#[test]
+fn config_channels_only_takes_channels_of_the_server() {
+    let mut state = bot();
+    for channel in &[
+        format!("<#{}>", FOREIGN_CHANNEL_ID),
+        FOREIGN_CHANNEL_ID.to_string(),
+        "123".to_string(),
+    ] {
+        let response = run(
+            &mut state,
+            OWNER_ID,
+            &format!("!config channels add {}", channel),
+        );
+        assert!(is_error(&response));
+    }
+    assert!(state.settings_of(&guild()).allowed_channels.is_empty());
+
+    run(
+        &mut state,
+        OWNER_ID,
+        &format!("!config channels add <#{}>", OTHER_CHANNEL_ID),
+    );
+    assert_eq!(
+        state.settings_of(&guild()).allowed_channels,
+        vec![OTHER_CHANNEL_ID.to_string()]
+    );
+}
+
+#[test]
+fn config_daily_only_posts_in_the_server() {
+    let mut state = bot();
+    let response = run(
+        &mut state,
+        OWNER_ID,
+        &format!("!config daily <#{}>", FOREIGN_CHANNEL_ID),
+    );
+    assert!(is_error(&response));
+    assert_eq!(state.settings_of(&guild()).daily_channel, "");
+
+    run(&mut state, OWNER_ID, "!config daily #links");
+    assert_eq!(
+        state.settings_of(&guild()).daily_channel,
+        OTHER_CHANNEL_ID.to_string()
+    );
+}
+
+#[test]
+fn config_digest_only_posts_in_the_server() {
+    let mut state = bot();
+    let response = run(
+        &mut state,
+        OWNER_ID,
+        &format!("!config digest {}", FOREIGN_CHANNEL_ID),
+    );
+    assert!(is_error(&response));
+    assert_eq!(state.settings_of(&guild()).digest_channel, "");
+
+    run(
+        &mut state,
+        OWNER_ID,
+        &format!("!config digest <#{}>", OTHER_CHANNEL_ID),
+    );
+    assert_eq!(
+        state.settings_of(&guild()).digest_channel,
+        OTHER_CHANNEL_ID.to_string()
+    );
+}
+
+#[test]
+fn status_shows_the_output_of_the_last_command() {
+    let mut state = bot();
+    let response = run(&mut state, MEMBER_ID, "!status");
+    assert_eq!(response.text(), tr(LOCALE, "status.nothing"));
+
+    run(&mut state, MEMBER_ID, "!save https://example.com/a");
+    let response = run(&mut state, MEMBER_ID, "!status");
+    assert_eq!(
+        response.text(),
+        tr_args(LOCALE, "save.saved", &[("count", "1"), ("ids", "#1")])
+    );
+}
+
+#[test]
+fn lang_changes_the_language_of_the_author() {
+    let mut state = bot();
+    let response = run(&mut state, MEMBER_ID, "!lang en");
+    assert_eq!(
+        response.text(),
+        tr_args("en", "lang.changed", &[("name", &tr("en", "name"))])
+    );
+    assert_eq!(state.locale_of(&message(MEMBER_ID, "")), "en");
+    assert_eq!(state.locale_of(&message(OWNER_ID, "")), LOCALE);
+
+    assert!(is_error(&run(&mut state, MEMBER_ID, "!lang xx")));
+}
+
+#[test]
+fn help_lists_and_explains_commands() {
+    let mut state = bot();
+    assert!(run(&mut state, MEMBER_ID, "!help").text().contains("!save"));
+    assert!(run(&mut state, MEMBER_ID, "!help guardar")
+        .text()
+        .starts_with("`!save"));
+    assert!(is_error(&run(&mut state, MEMBER_ID, "!help nope")));
+}
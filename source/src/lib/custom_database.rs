@@ -1,63 +1,21 @@
+use chrono::{DateTime, NaiveTime, TimeZone, Utc};
 use openssl::ssl::{SslConnector, SslMethod, SslVerifyMode};
-use postgres::Client;
+use postgres::types::ToSql;
+use postgres::{Client, Row};
 use postgres_openssl::MakeTlsConnector;
-use serde::{Deserialize, Serialize};
-use std::collections::hash_map::DefaultHasher;
-use std::hash::{Hash, Hasher};
-
-use discord::model;
-
-#[derive(Serialize, Deserialize, Debug, Default)]
-pub struct Resource {
-    pub user_id: String,
-    pub channel_id: String,
-    pub url: String,
-    pub description: String,
-    pub shash: String,
-    pub type_id: i32,
-}
 
-#[allow(dead_code)]
-impl Resource {
-    pub fn new(message: &model::Message) -> Self {
-        let author_id = &message.author.id;
-        let channel_id = &message.channel_id.0;
-        let embeds = &message.embeds;
-
-        let mut url: String = "".to_string();
-        let mut description: String = "".to_string();
-
-        for embed in embeds {
-            let embed_title = embed.get("title").unwrap().to_string();
-            let embed_description = embed.get("description").unwrap().to_string();
-            url = embed.get("url").unwrap().to_string();
-            url = url.to_lowercase().trim().to_string();
-
-            description = format!(
-                "{}|url: {} + {} + {}",
-                description, url, embed_title, embed_description
-            );
-        }
-        description = description.to_lowercase().trim().to_string();
-
-        let shash = Resource::_calculate_hash(&description).to_string();
-
-        return Self {
-            user_id: author_id.to_string(),
-            channel_id: channel_id.to_string(),
-            url: url,
-            description: description,
-            type_id: 10,
-            shash: shash,
-        };
-    }
+use super::collection::{Collection, CollectionItem};
+use super::guild_settings::{GuildRole, GuildSettings};
+use super::migrations::Migration;
+use super::resource::Resource;
+use super::resource_store::ResourceStore;
+use super::search_query::{Scope, SearchQuery, PRIOR_RATING, PRIOR_VOTES};
 
-    fn _calculate_hash<T: Hash>(t: &T) -> u64 {
-        let mut s = DefaultHasher::new();
-        t.hash(&mut s);
-        s.finish()
-    }
-}
+/// Every resource column plus its tag names
+const RESOURCE_COLUMNS: &str = "*, ARRAY(SELECT t.name FROM resource_tags rt \
+    JOIN tags t ON t.id = rt.tag_id WHERE rt.resource_id = resources.id ORDER BY t.name) AS tags, \
+    COALESCE((SELECT avg(r.score)::float8 FROM ratings r WHERE r.resource_id = resources.id), 0) \
+    AS rating, (SELECT count(*) FROM ratings r WHERE r.resource_id = resources.id) AS votes";
 
 pub struct DiscordDatabase {
     db: postgres::Client,
@@ -74,139 +32,727 @@ impl DiscordDatabase {
         return Self { db: db };
     }
 
-    pub fn insert_resource(&mut self, resource: Resource) -> bool {
-        if resource.url.is_empty() || resource.description.is_empty() {
+    /// `search_query::rating_weight` of each row
+    fn rating_weight() -> String {
+        return format!(
+            "(SELECT (COALESCE(sum(r.score), 0) + {1:.1} * {0:.1}) / (count(*) + {1:.1}) \
+            FROM ratings r WHERE r.resource_id = resources.id)::float8",
+            PRIOR_RATING, PRIOR_VOTES
+        );
+    }
+
+    /// WHERE clause and parameters for `query`, plus the expression ranking the matches
+    fn search_conditions(query: &SearchQuery) -> (String, String, Vec<Box<dyn ToSql + Sync>>) {
+        let mut conditions: Vec<String> = vec!["deleted_at IS NULL".to_string()];
+        let mut params: Vec<Box<dyn ToSql + Sync>> = Vec::new();
+        let mut rank = "0".to_string();
+
+        match &query.scope {
+            Scope::Guild(guild_id) => {
+                params.push(Box::new(guild_id.clone()));
+                conditions.push(format!("guild_id = ${}", params.len()));
+            }
+            Scope::Public => conditions.push(
+                "guild_id IN (SELECT guild_id FROM guild_settings WHERE share_global)".to_string(),
+            ),
+        }
+        if !query.text.is_empty() {
+            params.push(Box::new(query.text.clone()));
+            let tsquery = format!("websearch_to_tsquery('simple', ${})", params.len());
+            conditions.push(format!("search_vector @@ {}", tsquery));
+            rank = format!("ts_rank(search_vector, {})", tsquery);
+        }
+        if let Some(user_id) = &query.user_id {
+            params.push(Box::new(user_id.clone()));
+            conditions.push(format!("user_id = ${}", params.len()));
+        }
+        if let Some(channel_id) = &query.channel_id {
+            params.push(Box::new(channel_id.clone()));
+            conditions.push(format!("channel_id = ${}", params.len()));
+        }
+        if let Some(domain) = &query.domain {
+            params.push(Box::new(domain.clone()));
+            conditions.push(format!(
+                "(substring(url from '://([^/:?#]+)') = ${0} \
+                OR substring(url from '://([^/:?#]+)') LIKE '%.' || ${0})",
+                params.len()
+            ));
+        }
+        for tag in &query.tags {
+            params.push(Box::new(tag.clone()));
+            conditions.push(format!(
+                "id IN (SELECT rt.resource_id FROM resource_tags rt \
+                JOIN tags t ON t.id = rt.tag_id WHERE t.name = ${})",
+                params.len()
+            ));
+        }
+        if let Some(type_id) = query.type_id {
+            params.push(Box::new(type_id));
+            conditions.push(format!("type_id = ${}", params.len()));
+        }
+        if let Some(after) = query.after {
+            params.push(Box::new(
+                Utc.from_utc_datetime(&after.and_time(NaiveTime::MIN)),
+            ));
+            conditions.push(format!("created_at >= ${}", params.len()));
+        }
+        if let Some(before) = query.before {
+            params.push(Box::new(
+                Utc.from_utc_datetime(&before.and_time(NaiveTime::MIN)),
+            ));
+            conditions.push(format!("created_at < ${}", params.len()));
+        }
+
+        return (conditions.join(" AND "), rank, params);
+    }
+
+    fn query_resources(&mut self, query: &str, params: &[Box<dyn ToSql + Sync>]) -> Vec<Resource> {
+        let params: Vec<&(dyn ToSql + Sync)> = params.iter().map(|param| param.as_ref()).collect();
+
+        match self.db.query(query, &params) {
+            Ok(data) => return data.iter().map(DiscordDatabase::row_to_resource).collect(),
+            Err(err) => {
+                println!("Search error: {}", err);
+                return Vec::new();
+            }
+        }
+    }
+
+    fn row_to_resource(row: &Row) -> Resource {
+        return Resource {
+            id: row.get("id"),
+            guild_id: row.get("guild_id"),
+            user_id: row.get("user_id"),
+            channel_id: row.get("channel_id"),
+            url: row.get("url"),
+            title: row.get("title"),
+            description: row.get("description"),
+            provider: row.get("provider"),
+            author: row.get("author"),
+            thumbnail_url: row.get("thumbnail_url"),
+            video_url: row.get("video_url"),
+            shash: row.get("shash"),
+            type_id: row.get("type_id"),
+            created_at: Some(row.get("created_at")),
+            deleted_at: row.get("deleted_at"),
+            deleted_by: row.get("deleted_by"),
+            tags: row.get("tags"),
+            rating: row.get("rating"),
+            votes: row.get("votes"),
+        };
+    }
+
+    fn row_to_collection(row: &Row) -> Collection {
+        return Collection {
+            id: row.get("id"),
+            guild_id: row.get("guild_id"),
+            user_id: row.get("user_id"),
+            name: row.get("name"),
+            description: row.get("description"),
+            created_at: Some(row.get("created_at")),
+            items: Vec::new(),
+        };
+    }
+
+    /// Collections of `query` with their items
+    fn query_collections(
+        &mut self,
+        query: &str,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> Vec<Collection> {
+        let mut collections: Vec<Collection> = match self.db.query(query, params) {
+            Ok(data) => data
+                .iter()
+                .map(DiscordDatabase::row_to_collection)
+                .collect(),
+            Err(_) => return Vec::new(),
+        };
+        for collection in collections.iter_mut() {
+            collection.items = self.collection_items(collection.id);
+        }
+        return collections;
+    }
+
+    fn collection_items(&mut self, collection_id: i64) -> Vec<CollectionItem> {
+        let query = format!(
+            "SELECT {} FROM collection_items ci JOIN resources ON resources.id = ci.resource_id \
+            WHERE ci.collection_id = $1 ORDER BY ci.position",
+            RESOURCE_COLUMNS
+        );
+
+        return match self.db.query(query.as_str(), &[&collection_id]) {
+            Ok(data) => data
+                .iter()
+                .map(|row| CollectionItem {
+                    resource: DiscordDatabase::row_to_resource(row),
+                    note: row.get("note"),
+                })
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+    }
+}
+
+impl ResourceStore for DiscordDatabase {
+    fn insert_resource(&mut self, resource: Resource) -> bool {
+        if resource.url.is_empty() {
             return false;
         }
 
         let query = "INSERT INTO public.resources(\
-            user_id, channel_id, url, description, type_id, shash)
-            VALUES ($1, $2, $3, $4, $5, $6);";
+            guild_id, user_id, channel_id, url, title, description, provider, \
+            author, thumbnail_url, video_url, type_id, shash)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12) RETURNING id;";
 
-        let result = self.db.execute(
+        let result = self.db.query_one(
             query,
             &[
+                &resource.guild_id,
                 &resource.user_id,
                 &resource.channel_id,
                 &resource.url,
+                &resource.title,
                 &resource.description,
+                &resource.provider,
+                &resource.author,
+                &resource.thumbnail_url,
+                &resource.video_url,
                 &resource.type_id,
                 &resource.shash,
             ],
         );
         match result {
-            Ok(_) => return true,
+            Ok(row) => {
+                if !resource.tags.is_empty() {
+                    self.set_resource_tags(row.get("id"), &resource.tags);
+                }
+                return true;
+            }
             Err(_) => return false,
         }
     }
 
-    pub fn select_resources(&mut self, description: &str, limit: u16, page: u16) -> Vec<Resource> {
-        let mut resources: Vec<Resource> = Vec::new();
+    fn select_resources(&mut self, query: &SearchQuery, limit: u16, page: u16) -> Vec<Resource> {
+        let (conditions, rank, params) = DiscordDatabase::search_conditions(query);
 
-        let description = format!("%{}%", description).to_string();
+        // Relevance times the votes, the votes alone when there's no text
+        let query = format!(
+            "SELECT {0} FROM resources WHERE {1} ORDER BY {2} * {3} DESC, {3} DESC, id DESC",
+            RESOURCE_COLUMNS,
+            conditions,
+            rank,
+            DiscordDatabase::rating_weight()
+        );
+        let query = format!("{} OFFSET {} LIMIT {}", query, page * limit, limit);
 
-        let query = "SELECT * FROM resources WHERE \
-            description LIKE $1 ORDER BY id DESC";
+        return self.query_resources(query.as_str(), &params);
+    }
 
-        let query = format!("{} OFFSET {} LIMIT {}", query, page * limit, limit);
-        let query = query.as_str();
+    fn select_random_resource(&mut self, query: &SearchQuery, excluded: &[i64]) -> Vec<Resource> {
+        let (conditions, _, mut params) = DiscordDatabase::search_conditions(query);
+        let mut conditions = conditions;
+        if !excluded.is_empty() {
+            params.push(Box::new(excluded.to_vec()));
+            conditions = format!("{} AND id <> ALL(${})", conditions, params.len());
+        }
 
-        let data = self.db.query(query, &[&description]).unwrap();
+        let query = format!(
+            "SELECT {} FROM resources WHERE {} ORDER BY random() LIMIT 1",
+            RESOURCE_COLUMNS, conditions
+        );
 
-        for row in data {
-            let url: String = row.get("url");
-            let url = url.replace("\"", "");
+        return self.query_resources(query.as_str(), &params);
+    }
 
-            let description: String = row.get("description");
-            let user_id: String = row.get("user_id");
-            let channel_id: String = row.get("channel_id");
+    fn select_resource_by_hash(&mut self, guild_id: &str, shash: &str) -> Option<Resource> {
+        let query = format!(
+            "SELECT {} FROM resources \
+            WHERE guild_id = $1 AND shash = $2 AND deleted_at IS NULL LIMIT 1",
+            RESOURCE_COLUMNS
+        );
+        let params: Vec<Box<dyn ToSql + Sync>> =
+            vec![Box::new(guild_id.to_string()), Box::new(shash.to_string())];
 
-            let resource = Resource {
-                user_id: user_id,
-                channel_id: channel_id,
-                url: url,
-                description: description,
-                ..Default::default()
-            };
+        return self.query_resources(query.as_str(), &params).pop();
+    }
+
+    fn select_resource(&mut self, guild_id: &str, id: i64) -> Option<Resource> {
+        let query = format!(
+            "SELECT {} FROM resources WHERE guild_id = $1 AND id = $2",
+            RESOURCE_COLUMNS
+        );
+        let params: Vec<Box<dyn ToSql + Sync>> = vec![Box::new(guild_id.to_string()), Box::new(id)];
 
-            resources.push(resource);
+        return self.query_resources(query.as_str(), &params).pop();
+    }
+
+    fn update_resource(&mut self, resource: &Resource) -> bool {
+        let query = "UPDATE resources SET title = $1, description = $2, type_id = $3 \
+            WHERE guild_id = $4 AND id = $5";
+
+        let result = self.db.execute(
+            query,
+            &[
+                &resource.title,
+                &resource.description,
+                &resource.type_id,
+                &resource.guild_id,
+                &resource.id,
+            ],
+        );
+        match result {
+            Ok(updated) => return updated == 1,
+            Err(_) => return false,
         }
-        return resources;
     }
 
-    pub fn select_random_resource(&mut self, description: &str) -> Vec<Resource> {
-        let mut resources: Vec<Resource> = Vec::new();
+    fn delete_resource(&mut self, guild_id: &str, id: i64, deleted_by: &str) -> bool {
+        let query = "UPDATE resources SET deleted_at = now(), deleted_by = $1 \
+            WHERE guild_id = $2 AND id = $3 AND deleted_at IS NULL";
 
-        let description = format!("%{}%", description).to_string();
+        match self.db.execute(query, &[&deleted_by, &guild_id, &id]) {
+            Ok(updated) => return updated == 1,
+            Err(_) => return false,
+        }
+    }
 
-        let query = "SELECT * FROM resources WHERE \
-            description LIKE $1 order by random() limit 1";
-        let data = self.db.query(query, &[&description]).unwrap();
+    fn restore_resource(&mut self, guild_id: &str, id: i64) -> bool {
+        let query = "UPDATE resources SET deleted_at = NULL, deleted_by = '' \
+            WHERE guild_id = $1 AND id = $2 AND deleted_at IS NOT NULL";
 
-        for row in data {
-            let url: String = row.get("url");
-            let url = url.replace("\"", "");
+        match self.db.execute(query, &[&guild_id, &id]) {
+            Ok(updated) => return updated == 1,
+            Err(_) => return false,
+        }
+    }
+
+    fn select_deleted_resources(&mut self, guild_id: &str, limit: u16) -> Vec<Resource> {
+        let query = format!(
+            "SELECT {} FROM resources WHERE guild_id = $1 AND deleted_at IS NOT NULL \
+            ORDER BY deleted_at DESC LIMIT {}",
+            RESOURCE_COLUMNS, limit
+        );
+        let params: Vec<Box<dyn ToSql + Sync>> = vec![Box::new(guild_id.to_string())];
 
-            let description: String = row.get("description");
+        return self.query_resources(query.as_str(), &params);
+    }
 
-            let user_id: String = row.get("user_id");
-            let channel_id: String = row.get("channel_id");
+    fn set_resource_tags(&mut self, resource_id: i64, tags: &[String]) -> bool {
+        let mut transaction = match self.db.transaction() {
+            Ok(transaction) => transaction,
+            Err(_) => return false,
+        };
 
-            let resource = Resource {
-                user_id: user_id,
-                channel_id: channel_id,
-                url: url,
-                description: description,
+        let mut result = transaction.execute(
+            "DELETE FROM resource_tags WHERE resource_id = $1",
+            &[&resource_id],
+        );
+        for tag in tags {
+            result = result.and_then(|_| {
+                transaction.execute(
+                    "INSERT INTO tags (name) VALUES ($1) ON CONFLICT (name) DO NOTHING",
+                    &[tag],
+                )
+            });
+            result = result.and_then(|_| {
+                transaction.execute(
+                    "INSERT INTO resource_tags (resource_id, tag_id) \
+                    SELECT $1, id FROM tags WHERE name = $2 ON CONFLICT DO NOTHING",
+                    &[&resource_id, tag],
+                )
+            });
+        }
+
+        match result.and_then(|_| transaction.commit()) {
+            Ok(_) => return true,
+            Err(_) => return false,
+        }
+    }
+
+    fn popular_tags(&mut self, guild_id: &str, limit: u16) -> Vec<(String, i64)> {
+        let query = format!(
+            "SELECT t.name, count(*) AS uses FROM tags t \
+            JOIN resource_tags rt ON rt.tag_id = t.id \
+            JOIN resources r ON r.id = rt.resource_id \
+            WHERE r.guild_id = $1 AND r.deleted_at IS NULL \
+            GROUP BY t.name ORDER BY uses DESC, t.name LIMIT {}",
+            limit
+        );
+
+        match self.db.query(query.as_str(), &[&guild_id]) {
+            Ok(data) => {
+                return data
+                    .iter()
+                    .map(|row| (row.get("name"), row.get("uses")))
+                    .collect()
+            }
+            Err(_) => return Vec::new(),
+        }
+    }
+
+    fn guild_settings(&mut self, guild_id: &str) -> GuildSettings {
+        let query = "SELECT * FROM guild_settings WHERE guild_id = $1";
+
+        let mut settings = match self.db.query_opt(query, &[&guild_id]) {
+            Ok(Some(row)) => GuildSettings {
+                guild_id: row.get("guild_id"),
+                share_global: row.get("share_global"),
+                locale: row.get("locale"),
+                prefix: row.get("prefix"),
+                daily_channel: row.get("daily_channel"),
+                daily_hour: row.get("daily_hour"),
+                daily_window: row.get("daily_window"),
+                digest_channel: row.get("digest_channel"),
+                digest_day: row.get("digest_day"),
+                digest_hour: row.get("digest_hour"),
                 ..Default::default()
-            };
-
-            resources.push(resource);
-        }
-        return resources;
-    }
-
-    pub fn _startup(mut self) {
-        let instructions = vec![
-            "CREATE TABLE channels
-            (
-              pk_channels integer NOT NULL,
-              channel_id bigint NOT NULL,
-              type integer NOT NULL
-            );",
-            "ALTER TABLE channels ADD CONSTRAINT pk_channels
-            PRIMARY KEY (pk_channels);",
-            "CREATE TABLE resources
-            (
-              resource_id integer NOT NULL,
-              user_id integer NOT NULL,
-              channel_id integer NOT NULL,
-              url varchar(255),
-              description text,
-              type_id integer NOT NULL
-            );",
-            "ALTER TABLE resources ADD CONSTRAINT pk_resources
-            PRIMARY KEY (resource_id);",
-            "CREATE TABLE types
-            (
-              pk_types integer NOT NULL,
-              type varchar(255) NOT NULL
-            );",
-            "ALTER TABLE types ADD CONSTRAINT pk_types
-            PRIMARY KEY (pk_types);",
-            "CREATE INDEX ix_channels_
-            ON channels (channel_id);",
-            "CREATE INDEX ix_resources_description
-            ON resources (description);",
-            "CREATE INDEX ix_resources_type
-            ON resources (type_id);",
-            "CREATE INDEX ix_resources_user
-            ON resources (user_id);",
+            },
+            _ => return GuildSettings::new(guild_id),
+        };
+
+        let query = "SELECT channel_id FROM guild_channels WHERE guild_id = $1";
+        if let Ok(data) = self.db.query(query, &[&guild_id]) {
+            settings.allowed_channels = data.iter().map(|row| row.get("channel_id")).collect();
+        }
+
+        let query = "SELECT role_id, permission FROM guild_roles WHERE guild_id = $1";
+        if let Ok(data) = self.db.query(query, &[&guild_id]) {
+            settings.roles = data
+                .iter()
+                .map(|row| GuildRole {
+                    role_id: row.get("role_id"),
+                    permission: row.get("permission"),
+                })
+                .collect();
+        }
+        return settings;
+    }
+
+    fn save_guild_settings(&mut self, settings: &GuildSettings) -> bool {
+        let mut transaction = match self.db.transaction() {
+            Ok(transaction) => transaction,
+            Err(_) => return false,
+        };
+
+        let query = "INSERT INTO guild_settings (guild_id, share_global, locale, prefix, \
+            daily_channel, daily_hour, daily_window, digest_channel, digest_day, digest_hour) \
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10) \
+            ON CONFLICT (guild_id) DO UPDATE SET \
+            share_global = EXCLUDED.share_global, locale = EXCLUDED.locale, \
+            prefix = EXCLUDED.prefix, daily_channel = EXCLUDED.daily_channel, \
+            daily_hour = EXCLUDED.daily_hour, daily_window = EXCLUDED.daily_window, \
+            digest_channel = EXCLUDED.digest_channel, digest_day = EXCLUDED.digest_day, \
+            digest_hour = EXCLUDED.digest_hour";
+        let mut result = transaction.execute(
+            query,
+            &[
+                &settings.guild_id,
+                &settings.share_global,
+                &settings.locale,
+                &settings.prefix,
+                &settings.daily_channel,
+                &settings.daily_hour,
+                &settings.daily_window,
+                &settings.digest_channel,
+                &settings.digest_day,
+                &settings.digest_hour,
+            ],
+        );
+
+        // Lists are rewritten as a whole
+        result = result.and_then(|_| {
+            transaction.execute(
+                "DELETE FROM guild_channels WHERE guild_id = $1",
+                &[&settings.guild_id],
+            )
+        });
+        for channel_id in &settings.allowed_channels {
+            result = result.and_then(|_| {
+                transaction.execute(
+                    "INSERT INTO guild_channels (guild_id, channel_id) VALUES ($1, $2)",
+                    &[&settings.guild_id, channel_id],
+                )
+            });
+        }
+        result = result.and_then(|_| {
+            transaction.execute(
+                "DELETE FROM guild_roles WHERE guild_id = $1",
+                &[&settings.guild_id],
+            )
+        });
+        for role in &settings.roles {
+            result = result.and_then(|_| {
+                transaction.execute(
+                    "INSERT INTO guild_roles (guild_id, role_id, permission) VALUES ($1, $2, $3)",
+                    &[&settings.guild_id, &role.role_id, &role.permission],
+                )
+            });
+        }
+
+        match result.and_then(|_| transaction.commit()) {
+            Ok(_) => return true,
+            Err(_) => return false,
+        }
+    }
+
+    fn daily_picks(&mut self, guild_id: &str, since: DateTime<Utc>) -> Vec<(i64, DateTime<Utc>)> {
+        let query = "SELECT resource_id, picked_at FROM daily_picks \
+            WHERE guild_id = $1 AND picked_at >= $2 ORDER BY picked_at DESC";
+
+        return match self.db.query(query, &[&guild_id, &since]) {
+            Ok(data) => data
+                .iter()
+                .map(|row| (row.get("resource_id"), row.get("picked_at")))
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+    }
+
+    fn save_daily_pick(&mut self, guild_id: &str, resource_id: i64) -> bool {
+        let query = "INSERT INTO daily_picks (guild_id, resource_id) VALUES ($1, $2)";
+
+        match self.db.execute(query, &[&guild_id, &resource_id]) {
+            Ok(_) => return true,
+            Err(_) => return false,
+        }
+    }
+
+    fn add_bookmark(&mut self, user_id: &str, resource_id: i64) -> bool {
+        let query = "INSERT INTO bookmarks (user_id, resource_id) VALUES ($1, $2) \
+            ON CONFLICT DO NOTHING";
+
+        match self.db.execute(query, &[&user_id, &resource_id]) {
+            Ok(inserted) => return inserted == 1,
+            Err(_) => return false,
+        }
+    }
+
+    fn remove_bookmark(&mut self, user_id: &str, resource_id: i64) -> bool {
+        let query = "DELETE FROM bookmarks WHERE user_id = $1 AND resource_id = $2";
+
+        match self.db.execute(query, &[&user_id, &resource_id]) {
+            Ok(deleted) => return deleted == 1,
+            Err(_) => return false,
+        }
+    }
+
+    fn select_bookmarks(&mut self, guild_id: &str, user_id: &str) -> Vec<Resource> {
+        let query = format!(
+            "SELECT {} FROM resources WHERE guild_id = $1 AND deleted_at IS NULL \
+            AND id IN (SELECT resource_id FROM bookmarks WHERE user_id = $2) \
+            ORDER BY (SELECT b.created_at FROM bookmarks b \
+            WHERE b.resource_id = resources.id AND b.user_id = $2) DESC",
+            RESOURCE_COLUMNS
+        );
+        let params: Vec<Box<dyn ToSql + Sync>> = vec![
+            Box::new(guild_id.to_string()),
+            Box::new(user_id.to_string()),
         ];
-        for instruction in &instructions {
-            // Create resource_type
-            self.db
-                .batch_execute(instruction)
-                .expect("Connection error at create");
+
+        return self.query_resources(query.as_str(), &params);
+    }
+
+    fn insert_collection(&mut self, collection: &Collection) -> Option<i64> {
+        let query = "INSERT INTO collections (guild_id, user_id, name, description) \
+            VALUES ($1, $2, $3, $4) ON CONFLICT DO NOTHING RETURNING id";
+
+        return match self.db.query_opt(
+            query,
+            &[
+                &collection.guild_id,
+                &collection.user_id,
+                &collection.name,
+                &collection.description,
+            ],
+        ) {
+            Ok(Some(row)) => Some(row.get("id")),
+            _ => None,
+        };
+    }
+
+    fn select_collection(&mut self, guild_id: &str, id: i64) -> Option<Collection> {
+        return self
+            .query_collections(
+                "SELECT * FROM collections WHERE guild_id = $1 AND id = $2",
+                &[&guild_id, &id],
+            )
+            .pop();
+    }
+
+    fn select_collections(&mut self, guild_id: &str, user_id: &str) -> Vec<Collection> {
+        return self.query_collections(
+            "SELECT * FROM collections WHERE guild_id = $1 AND user_id = $2 ORDER BY name",
+            &[&guild_id, &user_id],
+        );
+    }
+
+    fn update_collection(&mut self, collection: &Collection) -> bool {
+        let mut transaction = match self.db.transaction() {
+            Ok(transaction) => transaction,
+            Err(_) => return false,
+        };
+
+        let mut result = transaction.execute(
+            "UPDATE collections SET description = $1 WHERE id = $2",
+            &[&collection.description, &collection.id],
+        );
+        result = result.and_then(|_| {
+            transaction.execute(
+                "DELETE FROM collection_items WHERE collection_id = $1",
+                &[&collection.id],
+            )
+        });
+        for (position, item) in collection.items.iter().enumerate() {
+            result = result.and_then(|_| {
+                transaction.execute(
+                    "INSERT INTO collection_items (collection_id, resource_id, position, note) \
+                    VALUES ($1, $2, $3, $4)",
+                    &[
+                        &collection.id,
+                        &item.resource.id,
+                        &(position as i32),
+                        &item.note,
+                    ],
+                )
+            });
         }
+
+        match result.and_then(|_| transaction.commit()) {
+            Ok(_) => return true,
+            Err(_) => return false,
+        }
+    }
+
+    fn delete_collection(&mut self, guild_id: &str, id: i64) -> bool {
+        let query = "DELETE FROM collections WHERE guild_id = $1 AND id = $2";
+
+        match self.db.execute(query, &[&guild_id, &id]) {
+            Ok(deleted) => return deleted == 1,
+            Err(_) => return false,
+        }
+    }
+
+    fn select_resources_since(
+        &mut self,
+        guild_id: &str,
+        since: DateTime<Utc>,
+        limit: u16,
+    ) -> Vec<Resource> {
+        let query = format!(
+            "SELECT {} FROM resources WHERE guild_id = $1 AND deleted_at IS NULL \
+            AND created_at >= $2 ORDER BY created_at DESC, id DESC LIMIT {}",
+            RESOURCE_COLUMNS, limit
+        );
+        let params: Vec<Box<dyn ToSql + Sync>> =
+            vec![Box::new(guild_id.to_string()), Box::new(since)];
+
+        return self.query_resources(query.as_str(), &params);
+    }
+
+    fn rate_resource(&mut self, resource_id: i64, user_id: &str, score: i32) -> bool {
+        let query = "INSERT INTO ratings (resource_id, user_id, score) VALUES ($1, $2, $3) \
+            ON CONFLICT (resource_id, user_id) DO UPDATE SET \
+            score = EXCLUDED.score, rated_at = now()";
+
+        match self.db.execute(query, &[&resource_id, &user_id, &score]) {
+            Ok(_) => return true,
+            Err(_) => return false,
+        }
+    }
+
+    fn unrate_resource(&mut self, resource_id: i64, user_id: &str, score: i32) -> bool {
+        let query = "DELETE FROM ratings WHERE resource_id = $1 AND user_id = $2 AND score = $3";
+
+        match self.db.execute(query, &[&resource_id, &user_id, &score]) {
+            Ok(deleted) => return deleted == 1,
+            Err(_) => return false,
+        }
+    }
+
+    fn last_digest(&mut self, guild_id: &str) -> Option<DateTime<Utc>> {
+        let query = "SELECT max(posted_at) AS posted_at FROM digests WHERE guild_id = $1";
+
+        return match self.db.query_one(query, &[&guild_id]) {
+            Ok(row) => row.get("posted_at"),
+            Err(_) => None,
+        };
+    }
+
+    fn save_digest(&mut self, guild_id: &str) -> bool {
+        match self
+            .db
+            .execute("INSERT INTO digests (guild_id) VALUES ($1)", &[&guild_id])
+        {
+            Ok(_) => return true,
+            Err(_) => return false,
+        }
+    }
+
+    fn user_locale(&mut self, user_id: &str) -> Option<String> {
+        let query = "SELECT locale FROM user_settings WHERE user_id = $1 AND locale <> ''";
+
+        return match self.db.query_opt(query, &[&user_id]) {
+            Ok(Some(row)) => Some(row.get("locale")),
+            _ => None,
+        };
+    }
+
+    fn save_user_locale(&mut self, user_id: &str, locale: &str) -> bool {
+        let query = "INSERT INTO user_settings (user_id, locale) VALUES ($1, $2) \
+            ON CONFLICT (user_id) DO UPDATE SET locale = EXCLUDED.locale";
+
+        match self.db.execute(query, &[&user_id, &locale]) {
+            Ok(_) => return true,
+            Err(_) => return false,
+        }
+    }
+
+    fn applied_migrations(&mut self) -> Result<Vec<i64>, String> {
+        self.db
+            .batch_execute(
+                "CREATE TABLE IF NOT EXISTS schema_migrations
+                (
+                  version BIGINT PRIMARY KEY,
+                  name TEXT NOT NULL,
+                  applied_at TIMESTAMPTZ NOT NULL DEFAULT now()
+                );",
+            )
+            .map_err(|err| err.to_string())?;
+
+        let data = self
+            .db
+            .query(
+                "SELECT version FROM schema_migrations ORDER BY version",
+                &[],
+            )
+            .map_err(|err| err.to_string())?;
+
+        return Ok(data.iter().map(|row| row.get("version")).collect());
+    }
+
+    fn apply_migration(&mut self, migration: &Migration) -> Result<(), String> {
+        let mut transaction = self.db.transaction().map_err(|err| err.to_string())?;
+
+        transaction
+            .batch_execute(migration.postgres)
+            .map_err(|err| err.to_string())?;
+        transaction
+            .execute(
+                "INSERT INTO schema_migrations (version, name) VALUES ($1, $2)",
+                &[&migration.version, &migration.name],
+            )
+            .map_err(|err| err.to_string())?;
+
+        return transaction.commit().map_err(|err| err.to_string());
+    }
+
+    fn assign_legacy_resources(&mut self, guild_id: &str) -> Result<u64, String> {
+        return self
+            .db
+            .execute(
+                "UPDATE resources SET guild_id = $1 WHERE guild_id = ''",
+                &[&guild_id],
+            )
+            .map_err(|err| err.to_string());
     }
 }
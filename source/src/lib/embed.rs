@@ -0,0 +1,183 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::Value;
+
+lazy_static! {
+    static ref URL_REGEX: Regex = Regex::new(r"https?://[^\s<>]+").unwrap();
+    static ref MESSAGE_LINK_REGEX: Regex = Regex::new(
+        r"https?://(?:\w+\.)?discord(?:app)?\.com/channels/(?P<guild>\d+|@me)/(?P<channel>\d+)/(?P<message>\d+)"
+    )
+    .unwrap();
+}
+
+/// The parts of a discord embed we care about, every field is optional
+/// because discord fills them depending on the site.
+#[derive(Deserialize, Debug, Default, Clone)]
+pub struct Embed {
+    #[serde(rename = "type")]
+    pub kind: Option<String>,
+    pub url: Option<String>,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub provider: Option<EmbedName>,
+    pub author: Option<EmbedName>,
+    pub thumbnail: Option<EmbedMedia>,
+    pub video: Option<EmbedMedia>,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+pub struct EmbedName {
+    pub name: Option<String>,
+    pub url: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+pub struct EmbedMedia {
+    pub url: Option<String>,
+    pub width: Option<u64>,
+    pub height: Option<u64>,
+}
+
+#[allow(dead_code)]
+impl Embed {
+    /// `None` when the value isn't an embed at all
+    pub fn parse(value: &Value) -> Option<Self> {
+        return serde_json::from_value(value.clone()).ok();
+    }
+
+    pub fn url(&self) -> String {
+        return clean(&self.url);
+    }
+
+    pub fn title(&self) -> String {
+        return clean(&self.title);
+    }
+
+    pub fn description(&self) -> String {
+        return clean(&self.description);
+    }
+
+    pub fn provider(&self) -> String {
+        return clean(
+            &self
+                .provider
+                .as_ref()
+                .and_then(|provider| provider.name.clone()),
+        );
+    }
+
+    pub fn author(&self) -> String {
+        return clean(&self.author.as_ref().and_then(|author| author.name.clone()));
+    }
+
+    pub fn thumbnail_url(&self) -> String {
+        return clean(
+            &self
+                .thumbnail
+                .as_ref()
+                .and_then(|thumbnail| thumbnail.url.clone()),
+        );
+    }
+
+    pub fn video_url(&self) -> String {
+        return clean(&self.video.as_ref().and_then(|video| video.url.clone()));
+    }
+}
+
+fn clean(value: &Option<String>) -> String {
+    return match value {
+        Some(value) => value.trim().to_string(),
+        None => "".to_string(),
+    };
+}
+
+/// Links written in `text`, for messages discord hasn't embedded yet.
+/// Links to other discord messages are not resources and are skipped.
+pub fn find_urls(text: &str) -> Vec<String> {
+    return URL_REGEX
+        .find_iter(text)
+        .map(|found| {
+            found
+                .as_str()
+                .trim_end_matches(|character| ".,;:!?)]}>\"'".contains(character))
+                .to_string()
+        })
+        .filter(|url| !MESSAGE_LINK_REGEX.is_match(url))
+        .collect();
+}
+
+/// Guild, channel and message ids of the first discord message link in
+/// `text`, links to private messages (`@me`) have no guild
+pub fn find_message_link(text: &str) -> Option<(Option<u64>, u64, u64)> {
+    let captures = MESSAGE_LINK_REGEX.captures(text)?;
+    let guild = captures.name("guild")?.as_str().parse::<u64>().ok();
+    let channel = captures.name("channel")?.as_str().parse::<u64>().ok()?;
+    let message = captures.name("message")?.as_str().parse::<u64>().ok()?;
+    return Some((guild, channel, message));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn parses_whatever_discord_filled() {
+        let embed = Embed::parse(&json!({
+            "type": "video",
+            "url": " https://youtu.be/1 ",
+            "title": "Talk",
+            "provider": {"name": "YouTube"},
+            "author": {"url": "https://youtube.com/c/someone"},
+            "video": {"url": "https://youtube.com/embed/1", "width": 1280},
+        }))
+        .unwrap();
+
+        assert_eq!(embed.kind.as_deref(), Some("video"));
+        assert_eq!(embed.url(), "https://youtu.be/1");
+        assert_eq!(embed.title(), "Talk");
+        assert_eq!(embed.description(), "");
+        assert_eq!(embed.provider(), "YouTube");
+        assert_eq!(embed.author(), "");
+        assert_eq!(embed.thumbnail_url(), "");
+        assert_eq!(embed.video_url(), "https://youtube.com/embed/1");
+
+        assert!(Embed::parse(&json!({})).is_some());
+        assert!(Embed::parse(&json!("not an embed")).is_none());
+    }
+
+    #[test]
+    fn finds_links_but_not_message_links() {
+        assert_eq!(
+            find_urls(
+                "see https://example.com/a, (https://example.com/b) <https://example.com/c> \
+                 https://discord.com/channels/1/2/3 and http://example.com/d?x=1."
+            ),
+            vec![
+                "https://example.com/a",
+                "https://example.com/b",
+                "https://example.com/c",
+                "http://example.com/d?x=1",
+            ]
+        );
+        assert!(find_urls("no links").is_empty());
+    }
+
+    #[test]
+    fn finds_message_links() {
+        assert_eq!(
+            find_message_link("look https://discord.com/channels/1/2/3 here"),
+            Some((Some(1), 2, 3))
+        );
+        assert_eq!(
+            find_message_link("https://ptb.discordapp.com/channels/@me/2/3"),
+            Some((None, 2, 3))
+        );
+        assert_eq!(
+            find_message_link("https://example.com/channels/1/2/3"),
+            None
+        );
+        assert_eq!(find_message_link("https://discord.com/channels/1/2"), None);
+    }
+}
@@ -0,0 +1,118 @@
+use super::super::app_state::AppState;
+use super::super::collection::Collection;
+use super::super::i18n::catalog;
+use super::super::resource::Resource;
+use super::super::resource_type::ResourceType;
+use super::locale::request_locale;
+use actix_web::http::{ContentEncoding, StatusCode};
+use actix_web::{error, http, web, HttpRequest, HttpResponse};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+/// A collection as shared on the web, only with the resources still saved
+#[derive(Serialize)]
+struct CollectionBody<'a> {
+    id: i64,
+    name: &'a str,
+    description: &'a str,
+    user_id: &'a str,
+    created_at: Option<DateTime<Utc>>,
+    items: Vec<ItemBody<'a>>,
+}
+
+#[derive(Serialize)]
+struct ItemBody<'a> {
+    position: usize,
+    note: &'a str,
+    type_name: &'static str,
+    resource: &'a Resource,
+}
+
+impl<'a> CollectionBody<'a> {
+    fn new(collection: &'a Collection) -> Self {
+        return Self {
+            id: collection.id,
+            name: &collection.name,
+            description: &collection.description,
+            user_id: &collection.user_id,
+            created_at: collection.created_at,
+            items: collection
+                .visible_items()
+                .into_iter()
+                .enumerate()
+                .map(|(index, item)| ItemBody {
+                    position: index + 1,
+                    note: &item.note,
+                    type_name: ResourceType::from_id(item.resource.type_id)
+                        .unwrap_or(ResourceType::Link)
+                        .name(),
+                    resource: &item.resource,
+                })
+                .collect(),
+        };
+    }
+}
+
+/// Collection of `/guild/{guild}/collection/{id}`, like resource pages only
+/// for servers that opted into sharing
+fn shared_collection(data: &web::Data<AppState>, req: &HttpRequest) -> Option<Collection> {
+    let guild_id = req.match_info().get("guild").unwrap_or("");
+    let id = req.match_info().get("id")?.parse::<i64>().ok()?;
+
+    let mut db = data.bd.lock().unwrap();
+    if !db.guild_settings(guild_id).share_global {
+        return None;
+    }
+    return db.select_collection(guild_id, id);
+}
+
+/// Page of a collection, linked from `!collection show`
+#[allow(dead_code)]
+pub async fn collection_page(
+    tmpl: web::Data<tera::Tera>,
+    data: web::Data<AppState>,
+    req: HttpRequest,
+) -> HttpResponse {
+    let locale = request_locale(&req);
+    let collection = match shared_collection(&data, &req) {
+        Some(collection) => collection,
+        None => return HttpResponse::NotFound().finish(),
+    };
+
+    let mut context = tera::Context::new();
+    context.insert("locale", &locale);
+    context.insert("t", &catalog(&locale));
+    context.insert("collection", &CollectionBody::new(&collection));
+
+    let s = tmpl
+        .render("collection.html", &context)
+        .map_err(|_| error::ErrorInternalServerError("Template error"))
+        .unwrap();
+
+    return HttpResponse::Ok().content_type("text/html").body(s);
+}
+
+/// The same collection as json, for whoever wants to build on it
+#[allow(dead_code)]
+pub async fn collection_json(data: web::Data<AppState>, req: HttpRequest) -> HttpResponse {
+    let collection = match shared_collection(&data, &req) {
+        Some(collection) => collection,
+        None => {
+            return HttpResponse::build(StatusCode::NOT_FOUND)
+                .set_header(http::header::CONTENT_TYPE, "text/json")
+                .set_header(
+                    http::header::CONTENT_ENCODING,
+                    ContentEncoding::Identity.as_str(),
+                )
+                .finish()
+        }
+    };
+
+    return HttpResponse::build(StatusCode::OK)
+        .set_header(http::header::CONTENT_TYPE, "text/json")
+        .set_header(
+            http::header::CONTENT_ENCODING,
+            ContentEncoding::Identity.as_str(),
+        )
+        .json(CollectionBody::new(&collection));
+}
@@ -1,14 +1,22 @@
 use super::super::app_state::AppState;
+use super::super::i18n::catalog;
+use super::locale::request_locale;
 use actix_web::{error, web, HttpRequest, HttpResponse};
 
 #[allow(dead_code)]
 pub async fn index(
     tmpl: web::Data<tera::Tera>,
     _: web::Data<AppState>,
-    _: HttpRequest,
+    req: HttpRequest,
 ) -> HttpResponse {
+    let locale = request_locale(&req);
+
+    let mut context = tera::Context::new();
+    context.insert("locale", &locale);
+    context.insert("t", &catalog(&locale));
+
     let s = tmpl
-        .render("under_construction.html", &tera::Context::new())
+        .render("under_construction.html", &context)
         .map_err(|_| error::ErrorInternalServerError("Template error"))
         .unwrap();
 
@@ -0,0 +1,22 @@
+use super::super::i18n::{from_accept_language, is_supported, DEFAULT_LOCALE};
+use actix_web::{http, HttpRequest};
+
+/// Locale for a web request: `?lang=en`, then `Accept-Language`, then the default
+pub fn request_locale(req: &HttpRequest) -> String {
+    let asked = req
+        .query_string()
+        .split('&')
+        .filter_map(|pair| pair.strip_prefix("lang="))
+        .find(|locale| is_supported(locale));
+    if let Some(locale) = asked {
+        return locale.to_string();
+    }
+
+    let accepted = req
+        .headers()
+        .get(http::header::ACCEPT_LANGUAGE)
+        .and_then(|header| header.to_str().ok())
+        .and_then(from_accept_language);
+
+    return accepted.unwrap_or(DEFAULT_LOCALE).to_string();
+}
@@ -1 +1,7 @@
-pub mod index;pub mod search;
+pub mod collection_page;
+pub mod index;
+pub mod locale;
+pub mod resource_page;
+pub mod search;
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,48 @@
+use super::super::app_state::AppState;
+use super::super::i18n::catalog;
+use super::super::resource_type::ResourceType;
+use super::locale::request_locale;
+use actix_web::{error, web, HttpRequest, HttpResponse};
+
+/// Page of a single resource, linked from the embeds of the bot. Like the
+/// search, servers that didn't opt into sharing look like they don't exist.
+#[allow(dead_code)]
+pub async fn resource_page(
+    tmpl: web::Data<tera::Tera>,
+    data: web::Data<AppState>,
+    req: HttpRequest,
+) -> HttpResponse {
+    let locale = request_locale(&req);
+    let guild_id = req.match_info().get("guild").unwrap_or("");
+    let id = req.match_info().get("id").unwrap_or("");
+
+    let resource = {
+        let mut db = data.bd.lock().unwrap();
+        match id.parse::<i64>() {
+            Ok(id) if db.guild_settings(guild_id).share_global => db.select_resource(guild_id, id),
+            _ => None,
+        }
+    };
+    let resource = match resource {
+        Some(resource) if resource.deleted_at.is_none() => resource,
+        _ => return HttpResponse::NotFound().finish(),
+    };
+
+    let mut context = tera::Context::new();
+    context.insert("locale", &locale);
+    context.insert("t", &catalog(&locale));
+    context.insert(
+        "type_name",
+        ResourceType::from_id(resource.type_id)
+            .unwrap_or(ResourceType::Link)
+            .name(),
+    );
+    context.insert("resource", &resource);
+
+    let s = tmpl
+        .render("resource.html", &context)
+        .map_err(|_| error::ErrorInternalServerError("Template error"))
+        .unwrap();
+
+    return HttpResponse::Ok().content_type("text/html").body(s);
+}
@@ -1,14 +1,62 @@
 use super::super::app_state::AppState;
+use super::super::resource::Resource;
+use super::super::resource_type::ResourceType;
+use super::super::search_query::SearchQuery;
+use super::locale::request_locale;
 use actix_web::http::{ContentEncoding, StatusCode};
 use actix_web::{http, web, HttpRequest, HttpResponse};
+use serde::Serialize;
+
+#[derive(Serialize)]
+struct ErrorBody {
+    error: String,
+}
+
+/// A resource plus the name of its type
+#[derive(Serialize)]
+struct ResourceBody<'a> {
+    #[serde(flatten)]
+    resource: &'a Resource,
+    type_name: &'static str,
+}
 
 #[allow(dead_code)]
 pub async fn resource_query(data: web::Data<AppState>, req: HttpRequest) -> HttpResponse {
     let query = req.match_info().get("query").unwrap_or("");
     let page = req.match_info().get("page").unwrap_or("0");
-    let page = page.parse::<u16>().unwrap();
+    let page = page.parse::<u16>().unwrap_or(0);
+
+    let mut query = match SearchQuery::parse(query, &request_locale(&req)) {
+        Ok(query) => query,
+        Err(error) => {
+            return HttpResponse::build(StatusCode::BAD_REQUEST)
+                .set_header(http::header::CONTENT_TYPE, "text/json")
+                .set_header(
+                    http::header::CONTENT_ENCODING,
+                    ContentEncoding::Identity.as_str(),
+                )
+                .json(ErrorBody { error: error })
+        }
+    };
+
+    let mut db = data.bd.lock().unwrap();
+
+    // `/guild/{guild}/...` narrows the public index to a single server,
+    // servers that didn't opt into sharing look like they don't exist
+    if let Some(guild_id) = req.match_info().get("guild") {
+        if !db.guild_settings(guild_id).share_global {
+            return HttpResponse::build(StatusCode::NOT_FOUND)
+                .set_header(http::header::CONTENT_TYPE, "text/json")
+                .set_header(
+                    http::header::CONTENT_ENCODING,
+                    ContentEncoding::Identity.as_str(),
+                )
+                .finish();
+        }
+        query = query.scoped_to(guild_id);
+    }
 
-    let resources = &data.bd.lock().unwrap().select_resources(query, 10, page);
+    let resources = &db.select_resources(&query, 10, page);
 
     if resources.len() == 0 {
         return HttpResponse::build(StatusCode::NOT_FOUND)
@@ -25,5 +73,15 @@ pub async fn resource_query(data: web::Data<AppState>, req: HttpRequest) -> Http
             http::header::CONTENT_ENCODING,
             ContentEncoding::Identity.as_str(),
         )
-        .json(&resources);
+        .json(
+            resources
+                .iter()
+                .map(|resource| ResourceBody {
+                    resource: resource,
+                    type_name: ResourceType::from_id(resource.type_id)
+                        .unwrap_or(ResourceType::Link)
+                        .name(),
+                })
+                .collect::<Vec<ResourceBody>>(),
+        );
 }
@@ -0,0 +1,167 @@
+use super::super::app_state::AppState;
+use super::super::collection::{Collection, CollectionItem};
+use super::super::guild_settings::GuildSettings;
+use super::super::memory_database::MemoryDatabase;
+use super::super::resource::Resource;
+use super::super::resource_store::ResourceStore;
+use super::collection_page::{collection_json, collection_page};
+use super::index::index;
+use super::resource_page::resource_page;
+use super::search::resource_query;
+use actix_web::http::StatusCode;
+use actix_web::rt::System;
+use actix_web::{test, web, App};
+use serde_json::Value;
+use std::sync::Mutex;
+use tera::Tera;
+
+/// Shares its resources
+const SHARED_GUILD: &str = "1";
+/// Keeps them to itself
+const PRIVATE_GUILD: &str = "2";
+
+fn resource(guild_id: &str, url: &str, title: &str) -> Resource {
+    return Resource {
+        guild_id: guild_id.to_string(),
+        user_id: "10".to_string(),
+        url: url.to_string(),
+        title: title.to_string(),
+        shash: url.to_string(),
+        type_id: 1,
+        ..Default::default()
+    };
+}
+
+/// Two resources of the shared server, the second one deleted, one of the
+/// other server and a collection holding both shared ones
+fn database() -> MemoryDatabase {
+    let mut db = MemoryDatabase::new();
+    let mut settings = GuildSettings::new(SHARED_GUILD);
+    settings.share_global = true;
+    db.save_guild_settings(&settings);
+
+    db.insert_resource(resource(SHARED_GUILD, "https://example.com/1", "Rust book"));
+    db.insert_resource(resource(
+        SHARED_GUILD,
+        "https://example.com/2",
+        "Rust deleted",
+    ));
+    db.insert_resource(resource(
+        PRIVATE_GUILD,
+        "https://example.com/3",
+        "Rust secret",
+    ));
+    db.delete_resource(SHARED_GUILD, 2, "10");
+
+    let mut collection = Collection::new(SHARED_GUILD, "10", "path");
+    collection.id = db.insert_collection(&collection).unwrap();
+    for id in &[2, 1] {
+        collection.items.push(CollectionItem {
+            resource: db.select_resource(SHARED_GUILD, *id).unwrap(),
+            note: format!("note {}", id),
+        });
+    }
+    db.update_collection(&collection);
+    return db;
+}
+
+/// Status and body of a `GET` of `uri`, served like `server.rs` does
+/// over `database()`
+fn get(uri: &str) -> (StatusCode, String) {
+    return System::new("tests").block_on(async move {
+        let db: Box<dyn ResourceStore + Send> = Box::new(database());
+        let state = web::Data::new(AppState { bd: Mutex::new(db) });
+        let tera = Tera::new(concat!(env!("CARGO_MANIFEST_DIR"), "/templates/**/*")).unwrap();
+        let mut app = test::init_service(
+            App::new()
+                .app_data(state)
+                .data(tera)
+                .service(web::resource("/guild/{guild}/resource/{id}").to(resource_page))
+                .service(web::resource("/guild/{guild}/collection/{id}/json").to(collection_json))
+                .service(web::resource("/guild/{guild}/collection/{id}").to(collection_page))
+                .service(web::resource("/guild/{guild}/{query}/{page}").to(resource_query))
+                .service(web::resource("/guild/{guild}/{query}").to(resource_query))
+                .service(web::resource("/{query}/{page}").to(resource_query))
+                .service(web::resource("/{query}").to(resource_query))
+                .service(web::resource("/").to(index)),
+        )
+        .await;
+
+        let request = test::TestRequest::get().uri(uri).to_request();
+        let response = test::call_service(&mut app, request).await;
+        let status = response.status();
+        let body = test::read_body(response).await;
+        return (status, String::from_utf8(body.to_vec()).unwrap());
+    });
+}
+
+fn json(body: &str) -> Value {
+    return serde_json::from_str(body).unwrap();
+}
+
+#[test]
+fn index_is_served() {
+    let (status, _) = get("/");
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[test]
+fn search_only_finds_shared_resources() {
+    let (status, body) = get("/rust");
+    assert_eq!(status, StatusCode::OK);
+
+    let found = json(&body);
+    let found = found.as_array().unwrap();
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0]["title"], "Rust book");
+    assert_eq!(found[0]["type_name"], "video");
+}
+
+#[test]
+fn search_pages_and_scopes_to_a_server() {
+    assert_eq!(get("/rust/1").0, StatusCode::NOT_FOUND);
+    assert_eq!(get("/guild/1/rust").0, StatusCode::OK);
+    assert_eq!(get("/guild/1/python").0, StatusCode::NOT_FOUND);
+    // Servers that don't share look like they don't exist
+    assert_eq!(get("/guild/2/rust").0, StatusCode::NOT_FOUND);
+}
+
+#[test]
+fn search_explains_bad_queries() {
+    let (status, body) = get("/type:nope");
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(json(&body)["error"].as_str().unwrap().contains("nope"));
+}
+
+#[test]
+fn resource_pages_are_for_shared_resources() {
+    let (status, body) = get("/guild/1/resource/1");
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.contains("Rust book"));
+
+    // Deleted, of a server that doesn't share, missing
+    assert_eq!(get("/guild/1/resource/2").0, StatusCode::NOT_FOUND);
+    assert_eq!(get("/guild/2/resource/3").0, StatusCode::NOT_FOUND);
+    assert_eq!(get("/guild/1/resource/9").0, StatusCode::NOT_FOUND);
+}
+
+#[test]
+fn collections_leave_deleted_resources_out() {
+    let (status, body) = get("/guild/1/collection/1");
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.contains("Rust book"));
+    assert!(body.contains("note 1"));
+    assert!(!body.contains("Rust deleted"));
+
+    let (status, body) = get("/guild/1/collection/1/json");
+    assert_eq!(status, StatusCode::OK);
+    let collection = json(&body);
+    assert_eq!(collection["name"], "path");
+    let items = collection["items"].as_array().unwrap();
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0]["position"], 1);
+    assert_eq!(items[0]["resource"]["id"], 1);
+
+    assert_eq!(get("/guild/2/collection/1").0, StatusCode::NOT_FOUND);
+    assert_eq!(get("/guild/1/collection/9/json").0, StatusCode::NOT_FOUND);
+}
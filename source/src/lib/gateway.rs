@@ -0,0 +1,582 @@
+use discord::model::{ChannelId, Event, Message, MessageId, ReadyEvent};
+use discord::{Connection, Discord, GetMessages};
+use rand::Rng;
+use std::collections::HashMap;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use tokio::sync::mpsc::Sender;
+
+/// Close codes after which connecting again can't work: bad token,
+/// bad shard or intents the bot isn't allowed to ask for
+const FATAL_CLOSE_CODES: &[u16] = &[4004, 4010, 4011, 4012, 4013, 4014];
+/// Receive errors in a row after which the connection is considered dead
+const MAX_RECEIVE_ERRORS: u32 = 5;
+/// How often discord asks to be sent heartbeats, its `Hello` says 41.25s
+const HEARTBEAT_INTERVAL: Duration = Duration::from_millis(41_250);
+/// Longest the watchdog waits for an event before opening a new session
+const MAX_SILENCE: Duration = Duration::from_secs(30 * 60);
+/// Messages fetched per channel when replaying what we missed
+const REPLAY_LIMIT: u64 = 50;
+
+/// Where the gateway connection is, every transition is logged
+#[derive(Clone, Debug, PartialEq)]
+pub enum GatewayState {
+    Connecting,
+    Connected,
+    /// Discord resumed the session and replays the events we missed
+    Resumed,
+    Disconnected(String),
+    /// Waiting before the next attempt
+    Backoff(Duration),
+    /// Nothing else to do, e.g. the token was rejected
+    Stopped(String),
+}
+
+/// Exponential backoff with full jitter: the n-th delay is random between
+/// `base` and `base * 2^n`, never longer than `max`
+pub struct Backoff {
+    base: Duration,
+    max: Duration,
+    attempt: u32,
+}
+
+#[allow(dead_code)]
+impl Backoff {
+    pub fn new(base: Duration, max: Duration) -> Self {
+        return Self {
+            base: base,
+            max: max,
+            attempt: 0,
+        };
+    }
+
+    pub fn next_delay(&mut self) -> Duration {
+        let ceiling = self
+            .base
+            .checked_mul(2u32.saturating_pow(self.attempt))
+            .unwrap_or(self.max)
+            .min(self.max);
+        self.attempt = self.attempt.saturating_add(1);
+
+        let millis = rand::thread_rng().gen_range(self.base.as_millis()..=ceiling.as_millis());
+        return Duration::from_millis(millis as u64);
+    }
+
+    /// After a healthy connection the next failure starts from `base` again
+    pub fn reset(&mut self) {
+        self.attempt = 0;
+    }
+}
+
+/// A gateway connection, as far as `Gateway` uses it. It is read from its
+/// own thread so a connection that stopped answering can be left behind.
+pub trait GatewayConnection: Send + 'static {
+    fn recv_event(&mut self) -> Result<Event, discord::Error>;
+
+    fn shutdown(self) -> Result<(), discord::Error>;
+}
+
+impl GatewayConnection for Connection {
+    fn recv_event(&mut self) -> Result<Event, discord::Error> {
+        return Connection::recv_event(self);
+    }
+
+    fn shutdown(self) -> Result<(), discord::Error> {
+        return Connection::shutdown(self);
+    }
+}
+
+/// Where `Gateway` gets its connections and the messages it missed from:
+/// discord in the bot, a scripted fake gateway in the tests
+pub trait Upstream {
+    type Connection: GatewayConnection;
+    /// What a new connection starts with, discord's `ReadyEvent`
+    type Ready;
+
+    fn connect(&mut self) -> Result<(Self::Connection, Self::Ready), discord::Error>;
+
+    /// How the ready of a connection opened again is forwarded
+    fn ready_event(ready: Self::Ready) -> Event;
+
+    /// Messages of `channel_id` sent after `message_id`, oldest first
+    fn messages_after(&self, channel_id: ChannelId, message_id: MessageId) -> Vec<Message>;
+}
+
+/// Discord's gateway and REST API
+pub struct DiscordUpstream {
+    discord: Arc<Discord>,
+    token: String,
+    /// Gateway to use instead of discord's, e.g. behind a proxy
+    url: Option<String>,
+}
+
+impl DiscordUpstream {
+    pub fn new(discord: Arc<Discord>, token: &str, url: Option<String>) -> Self {
+        return Self {
+            discord: discord,
+            token: token.to_string(),
+            url: url,
+        };
+    }
+}
+
+impl Upstream for DiscordUpstream {
+    type Connection = Connection;
+    type Ready = ReadyEvent;
+
+    fn connect(&mut self) -> Result<(Connection, ReadyEvent), discord::Error> {
+        return match &self.url {
+            Some(url) => Connection::new(url, &self.token, None),
+            None => self.discord.connect(),
+        };
+    }
+
+    fn ready_event(ready: ReadyEvent) -> Event {
+        return Event::Ready(ready);
+    }
+
+    fn messages_after(&self, channel_id: ChannelId, message_id: MessageId) -> Vec<Message> {
+        let mut messages = self
+            .discord
+            .get_messages(
+                channel_id,
+                GetMessages::After(message_id),
+                Some(REPLAY_LIMIT),
+            )
+            .unwrap_or_default();
+        messages.sort_by_key(|message| message.id);
+        return messages;
+    }
+}
+
+/// Keeps a gateway connection alive and forwards its events. Websocket
+/// drops are resumed by discord-rs itself. Closed connections are opened
+/// again with backoff as a new session, not resumed: only the messages
+/// sent meanwhile are fetched from the REST API so their commands still
+/// get an answer. Reactions (votes, search pages) and slash commands sent
+/// while the bot was away are lost.
+///
+/// discord-rs sends the heartbeats from its own thread and swallows their
+/// acknowledgements, so a connection whose heartbeats stopped being
+/// acknowledged can only be told by its silence. A watchdog opens a new
+/// session when nothing arrives for twice the heartbeat interval. A quiet
+/// server looks the same, so every silent reconnect doubles the wait, up to
+/// `MAX_SILENCE`, until a real event comes.
+pub struct Gateway<U: Upstream> {
+    upstream: U,
+    state: GatewayState,
+    backoff: Backoff,
+    /// Silence after which the connection is considered dead
+    watchdog: Duration,
+    /// The current one, `watchdog` doubled by every silent reconnect
+    silence: Duration,
+    /// Last message seen per channel, where replays start from
+    last_messages: HashMap<ChannelId, MessageId>,
+}
+
+#[allow(dead_code)]
+impl<U: Upstream> Gateway<U> {
+    pub fn new(upstream: U) -> Self {
+        return Self {
+            upstream: upstream,
+            state: GatewayState::Connecting,
+            backoff: Backoff::new(Duration::from_secs(1), Duration::from_secs(120)),
+            watchdog: HEARTBEAT_INTERVAL * 2,
+            silence: HEARTBEAT_INTERVAL * 2,
+            last_messages: HashMap::new(),
+        };
+    }
+
+    /// Considers the connection dead after `silence` without events instead
+    /// of twice the heartbeat interval
+    pub fn with_watchdog(mut self, silence: Duration) -> Self {
+        self.watchdog = silence;
+        self.silence = silence;
+        return self;
+    }
+
+    /// Waits with `backoff` between attempts instead of 1 second to 2 minutes
+    pub fn with_backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        return self;
+    }
+
+    pub fn state(&self) -> &GatewayState {
+        return &self.state;
+    }
+
+    fn transition(&mut self, state: GatewayState) {
+        println!("Gateway: {:?} -> {:?}", self.state, state);
+        self.state = state;
+    }
+
+    /// Connects, retrying with backoff until it works or can't ever work
+    pub fn connect(&mut self) -> Option<(U::Connection, U::Ready)> {
+        loop {
+            self.transition(GatewayState::Connecting);
+            match self.upstream.connect() {
+                Ok(connected) => {
+                    self.transition(GatewayState::Connected);
+                    return Some(connected);
+                }
+                Err(discord::Error::Closed(Some(code), body))
+                    if FATAL_CLOSE_CODES.contains(&code) =>
+                {
+                    self.transition(GatewayState::Stopped(format!("{} {}", code, body)));
+                    return None;
+                }
+                Err(err) => {
+                    self.transition(GatewayState::Disconnected(format!("{:?}", err)));
+                    self.wait();
+                }
+            }
+        }
+    }
+
+    fn wait(&mut self) {
+        let delay = self.backoff.next_delay();
+        self.transition(GatewayState::Backoff(delay));
+        thread::sleep(delay);
+    }
+
+    /// Receives events until the event loop goes away or discord refuses us
+    pub fn run(&mut self, connection: U::Connection, sender: Sender<Event>) {
+        let mut events = read_events(connection);
+        let mut errors = 0;
+        // The backoff starts over once a new connection delivers something
+        let mut fresh = true;
+
+        loop {
+            match events.recv_timeout(self.silence) {
+                Ok(Ok(event)) => {
+                    errors = 0;
+                    if fresh {
+                        self.backoff.reset();
+                        fresh = false;
+                    }
+                    // Every session starts with these, they don't tell a
+                    // quiet server from a dead connection
+                    if !matches!(event, Event::Ready(_) | Event::ServerCreate(_)) {
+                        self.silence = self.watchdog;
+                    }
+                    match &event {
+                        Event::Resumed { .. } => self.transition(GatewayState::Resumed),
+                        Event::MessageCreate(message) => self.seen(message),
+                        _ => {}
+                    }
+                    if sender.blocking_send(event).is_err() {
+                        return;
+                    }
+                    continue;
+                }
+                Ok(Err(discord::Error::Closed(code, body))) => {
+                    let reason = format!("closed with code {:?}: {}", code, body);
+                    if code.map_or(false, |code| FATAL_CLOSE_CODES.contains(&code)) {
+                        self.transition(GatewayState::Stopped(reason));
+                        return;
+                    }
+                    self.transition(GatewayState::Disconnected(reason));
+                }
+                Ok(Err(err)) => {
+                    errors += 1;
+                    println!(
+                        "Gateway: receive error {}/{}: {:?}",
+                        errors, MAX_RECEIVE_ERRORS, err
+                    );
+                    if errors < MAX_RECEIVE_ERRORS {
+                        continue;
+                    }
+                    self.transition(GatewayState::Disconnected(format!(
+                        "{} receive errors in a row",
+                        errors
+                    )));
+                }
+                Err(RecvTimeoutError::Timeout) => {
+                    self.transition(GatewayState::Disconnected(format!(
+                        "nothing received in {:?}",
+                        self.silence
+                    )));
+                    self.silence = (self.silence * 2).min(MAX_SILENCE);
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    self.transition(GatewayState::Disconnected(
+                        "the connection stopped being read".to_string(),
+                    ));
+                }
+            }
+
+            // Start over with a new session, the reader shuts the old
+            // connection down as soon as it gets anything from it
+            drop(events);
+            errors = 0;
+            self.wait();
+            let (new_connection, ready) = match self.connect() {
+                Some(connected) => connected,
+                None => return,
+            };
+            events = read_events(new_connection);
+            fresh = true;
+
+            if sender.blocking_send(U::ready_event(ready)).is_err() {
+                return;
+            }
+            for message in self.missed_messages() {
+                if sender.blocking_send(Event::MessageCreate(message)).is_err() {
+                    return;
+                }
+            }
+        }
+    }
+
+    fn seen(&mut self, message: &Message) {
+        self.last_messages.insert(message.channel_id, message.id);
+    }
+
+    /// Messages sent after the last one we saw in each channel, oldest first
+    fn missed_messages(&mut self) -> Vec<Message> {
+        let mut missed: Vec<Message> = Vec::new();
+
+        for (channel_id, message_id) in self.last_messages.clone() {
+            let messages = self.upstream.messages_after(channel_id, message_id);
+            if let Some(last) = messages.last() {
+                self.seen(last);
+            }
+            missed.extend(messages);
+        }
+
+        if !missed.is_empty() {
+            println!("Gateway: replaying {} missed messages", missed.len());
+        }
+        return missed;
+    }
+}
+
+/// Reads `connection` from a thread of its own until it is closed or
+/// nobody listens anymore, then shuts it down. A connection that stopped
+/// answering keeps its thread blocked until its socket fails.
+fn read_events<C: GatewayConnection>(mut connection: C) -> Receiver<Result<Event, discord::Error>> {
+    let (sender, receiver) = mpsc::sync_channel(1);
+    thread::spawn(move || loop {
+        let received = connection.recv_event();
+        let closed = matches!(received, Err(discord::Error::Closed(..)));
+        if sender.send(received).is_err() || closed {
+            let _ = connection.shutdown();
+            return;
+        }
+    });
+    return receiver;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Map;
+    use std::collections::VecDeque;
+    use std::sync::Mutex;
+    use std::time::Instant;
+    use tokio::sync::mpsc;
+
+    /// What the fake gateway does next on a connection
+    enum Step {
+        Event(&'static str),
+        Error,
+        Close(u16),
+        /// Nothing arrives for a while, as when heartbeats stop being acknowledged
+        Silence,
+    }
+
+    struct FakeConnection {
+        steps: VecDeque<Step>,
+    }
+
+    impl GatewayConnection for FakeConnection {
+        fn recv_event(&mut self) -> Result<Event, discord::Error> {
+            return match self.steps.pop_front() {
+                Some(Step::Event(name)) => Ok(Event::Unknown(name.to_string(), Map::new())),
+                Some(Step::Error) => Err(discord::Error::Protocol("fake error")),
+                Some(Step::Close(code)) => Err(discord::Error::Closed(Some(code), String::new())),
+                Some(Step::Silence) => {
+                    thread::sleep(Duration::from_millis(300));
+                    Ok(Event::Unknown("late".to_string(), Map::new()))
+                }
+                // Out of script, the token was revoked
+                None => Err(discord::Error::Closed(Some(4004), String::new())),
+            };
+        }
+
+        fn shutdown(self) -> Result<(), discord::Error> {
+            return Ok(());
+        }
+    }
+
+    /// Scripted gateway, each attempt connects with its steps or is
+    /// refused with a close code
+    struct FakeGateway {
+        attempts: VecDeque<Result<Vec<Step>, u16>>,
+        connected_at: Arc<Mutex<Vec<Instant>>>,
+    }
+
+    impl FakeGateway {
+        fn new(attempts: Vec<Result<Vec<Step>, u16>>) -> Self {
+            return Self {
+                attempts: attempts.into_iter().collect(),
+                connected_at: Arc::new(Mutex::new(Vec::new())),
+            };
+        }
+    }
+
+    impl Upstream for FakeGateway {
+        type Connection = FakeConnection;
+        type Ready = Event;
+
+        fn connect(&mut self) -> Result<(FakeConnection, Event), discord::Error> {
+            self.connected_at.lock().unwrap().push(Instant::now());
+            return match self.attempts.pop_front() {
+                Some(Ok(steps)) => Ok((
+                    FakeConnection {
+                        steps: steps.into_iter().collect(),
+                    },
+                    Event::Unknown("READY".to_string(), Map::new()),
+                )),
+                Some(Err(code)) => Err(discord::Error::Closed(Some(code), String::new())),
+                None => Err(discord::Error::Closed(Some(4004), String::new())),
+            };
+        }
+
+        fn ready_event(ready: Event) -> Event {
+            return ready;
+        }
+
+        fn messages_after(&self, _: ChannelId, _: MessageId) -> Vec<Message> {
+            return Vec::new();
+        }
+    }
+
+    fn gateway(attempts: Vec<Result<Vec<Step>, u16>>) -> Gateway<FakeGateway> {
+        return Gateway::new(FakeGateway::new(attempts)).with_backoff(Backoff::new(
+            Duration::from_millis(20),
+            Duration::from_millis(40),
+        ));
+    }
+
+    /// Runs `gateway` until it stops, returning the names of the events it forwarded
+    fn run(gateway: &mut Gateway<FakeGateway>) -> Vec<String> {
+        let (sender, mut receiver) = mpsc::channel(64);
+        if let Some((connection, _)) = gateway.connect() {
+            gateway.run(connection, sender);
+        }
+
+        let mut names = Vec::new();
+        while let Ok(event) = receiver.try_recv() {
+            if let Event::Unknown(name, _) = event {
+                names.push(name);
+            }
+        }
+        return names;
+    }
+
+    fn is_stopped(gateway: &Gateway<FakeGateway>) -> bool {
+        return matches!(gateway.state(), GatewayState::Stopped(_));
+    }
+
+    #[test]
+    fn fatal_close_code_stops_connecting() {
+        let mut gateway = gateway(vec![Err(4004)]);
+        assert!(gateway.connect().is_none());
+        assert!(is_stopped(&gateway));
+        assert_eq!(gateway.upstream.connected_at.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn fatal_close_code_stops_a_running_connection() {
+        let mut gateway = gateway(vec![
+            Ok(vec![Step::Event("A"), Step::Close(4014)]),
+            Ok(vec![Step::Event("B")]),
+        ]);
+        assert_eq!(run(&mut gateway), vec!["A"]);
+        assert!(is_stopped(&gateway));
+        assert_eq!(gateway.upstream.connected_at.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn reconnects_with_backoff_after_a_close() {
+        let mut gateway = gateway(vec![
+            Ok(vec![Step::Event("A"), Step::Close(1006)]),
+            Err(1006),
+            Ok(vec![Step::Event("B")]),
+        ]);
+        assert_eq!(run(&mut gateway), vec!["A", "READY", "B"]);
+        assert!(is_stopped(&gateway));
+
+        let connected_at = gateway.upstream.connected_at.lock().unwrap().clone();
+        assert_eq!(connected_at.len(), 3);
+        for pair in connected_at.windows(2) {
+            assert!(pair[1] - pair[0] >= Duration::from_millis(20));
+        }
+    }
+
+    #[test]
+    fn too_many_receive_errors_reconnect() {
+        let mut gateway = gateway(vec![
+            Ok(vec![
+                Step::Error,
+                Step::Error,
+                Step::Error,
+                Step::Error,
+                Step::Event("A"),
+                Step::Error,
+                Step::Error,
+                Step::Error,
+                Step::Error,
+                Step::Error,
+                Step::Event("lost"),
+            ]),
+            Ok(vec![Step::Event("B")]),
+        ]);
+        // An event in between starts the count over, the fifth error in a row reconnects
+        assert_eq!(run(&mut gateway), vec!["A", "READY", "B"]);
+    }
+
+    #[test]
+    fn silent_connection_reconnects() {
+        let mut gateway = gateway(vec![
+            Ok(vec![Step::Event("A"), Step::Silence]),
+            Ok(vec![Step::Event("B")]),
+        ])
+        .with_watchdog(Duration::from_millis(50));
+        assert_eq!(run(&mut gateway), vec!["A", "READY", "B"]);
+        assert_eq!(gateway.upstream.connected_at.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn watchdog_waits_longer_until_an_event_comes() {
+        let watchdog = Duration::from_millis(50);
+        let mut quiet =
+            gateway(vec![Ok(vec![Step::Silence]), Ok(vec![Step::Silence])]).with_watchdog(watchdog);
+        assert_eq!(run(&mut quiet), vec!["READY"]);
+        assert_eq!(quiet.silence, watchdog * 4);
+
+        let mut active = gateway(vec![Ok(vec![Step::Silence]), Ok(vec![Step::Event("B")])])
+            .with_watchdog(watchdog);
+        assert_eq!(run(&mut active), vec!["READY", "B"]);
+        assert_eq!(active.silence, watchdog);
+    }
+
+    #[test]
+    fn backoff_stays_between_base_and_max() {
+        let base = Duration::from_millis(100);
+        let max = Duration::from_millis(1000);
+        let mut backoff = Backoff::new(base, max);
+
+        for attempt in 0..20 {
+            let delay = backoff.next_delay();
+            let ceiling = (base * 2u32.saturating_pow(attempt)).min(max);
+            assert!(delay >= base && delay <= ceiling, "{:?}", delay);
+        }
+
+        backoff.reset();
+        assert_eq!(backoff.next_delay(), base);
+    }
+}
@@ -0,0 +1,65 @@
+use serde::{Deserialize, Serialize};
+
+/// Hour (UTC) of the resource of the day when the server didn't pick one
+pub const DEFAULT_DAILY_HOUR: i32 = 12;
+/// Days a resource of the day isn't picked again
+pub const DEFAULT_DAILY_WINDOW: i32 = 30;
+/// Weekday (1 is monday) and hour (UTC) of the weekly digest by default
+pub const DEFAULT_DIGEST_DAY: i32 = 1;
+pub const DEFAULT_DIGEST_HOUR: i32 = 9;
+
+/// Per discord server (guild) configuration
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct GuildSettings {
+    pub guild_id: String,
+    /// Resources of this guild show up in the public global index
+    pub share_global: bool,
+    /// Default reply language, empty for the bot default
+    pub locale: String,
+    /// Command prefix, empty for the bot default
+    pub prefix: String,
+    /// Channels where the bot answers, empty for every channel
+    pub allowed_channels: Vec<String>,
+    pub roles: Vec<GuildRole>,
+    /// Channel for the resource of the day, empty when it's off
+    pub daily_channel: String,
+    /// Hour (UTC) from which the resource of the day is posted
+    pub daily_hour: i32,
+    /// Days a resource of the day isn't picked again
+    pub daily_window: i32,
+    /// Channel for the weekly digest, empty when it's off
+    pub digest_channel: String,
+    /// Weekday of the digest, 1 is monday and 7 sunday
+    pub digest_day: i32,
+    /// Hour (UTC) from which the digest is posted
+    pub digest_hour: i32,
+}
+
+/// Permission level granted by a discord role, e.g. `curator`
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct GuildRole {
+    pub role_id: String,
+    pub permission: String,
+}
+
+#[allow(dead_code)]
+impl GuildSettings {
+    pub fn new(guild_id: &str) -> Self {
+        return Self {
+            guild_id: guild_id.to_string(),
+            daily_hour: DEFAULT_DAILY_HOUR,
+            daily_window: DEFAULT_DAILY_WINDOW,
+            digest_day: DEFAULT_DIGEST_DAY,
+            digest_hour: DEFAULT_DIGEST_HOUR,
+            ..Default::default()
+        };
+    }
+
+    pub fn allows_channel(&self, channel_id: &str) -> bool {
+        return self.allowed_channels.is_empty()
+            || self
+                .allowed_channels
+                .iter()
+                .any(|allowed| allowed == channel_id);
+    }
+}
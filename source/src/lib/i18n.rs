@@ -0,0 +1,149 @@
+use lazy_static::lazy_static;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Catalogs shipped with the bot, `locales/<code>.json`
+const CATALOGS: &[(&str, &str)] = &[
+    ("es", include_str!("../../locales/es.json")),
+    ("en", include_str!("../../locales/en.json")),
+];
+
+pub const DEFAULT_LOCALE: &str = "es";
+
+lazy_static! {
+    static ref MESSAGES: HashMap<&'static str, HashMap<String, String>> = CATALOGS
+        .iter()
+        .map(|(locale, source)| {
+            let mut messages = HashMap::new();
+            flatten("", &catalog_value(source), &mut messages);
+            (*locale, messages)
+        })
+        .collect();
+}
+
+fn catalog_value(source: &str) -> Value {
+    return serde_json::from_str(source).expect("Invalid locale catalog");
+}
+
+/// `{"save": {"saved": "..."}}` becomes `save.saved`
+fn flatten(prefix: &str, value: &Value, messages: &mut HashMap<String, String>) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                let key = match prefix {
+                    "" => key.clone(),
+                    _ => format!("{}.{}", prefix, key),
+                };
+                flatten(&key, value, messages);
+            }
+        }
+        Value::String(text) => {
+            messages.insert(prefix.to_string(), text.clone());
+        }
+        _ => {}
+    }
+}
+
+/// Message `key` in `locale`, falls back to the default locale and then to the key
+pub fn tr(locale: &str, key: &str) -> String {
+    let message = MESSAGES
+        .get(locale)
+        .and_then(|messages| messages.get(key))
+        .or_else(|| {
+            MESSAGES
+                .get(DEFAULT_LOCALE)
+                .and_then(|messages| messages.get(key))
+        });
+
+    return match message {
+        Some(message) => message.clone(),
+        None => key.to_string(),
+    };
+}
+
+/// Like `tr` replacing every `{name}` with its value
+pub fn tr_args(locale: &str, key: &str, args: &[(&str, &str)]) -> String {
+    let mut message = tr(locale, key);
+    for (name, value) in args {
+        message = message.replace(&format!("{{{}}}", name), value);
+    }
+    return message;
+}
+
+pub fn is_supported(locale: &str) -> bool {
+    return MESSAGES.contains_key(locale);
+}
+
+pub fn locales() -> Vec<&'static str> {
+    return CATALOGS.iter().map(|(locale, _)| *locale).collect();
+}
+
+/// The whole catalog of `locale`, as nested JSON for the web templates
+pub fn catalog(locale: &str) -> Value {
+    let source = CATALOGS
+        .iter()
+        .find(|(code, _)| *code == locale)
+        .or_else(|| CATALOGS.iter().find(|(code, _)| *code == DEFAULT_LOCALE))
+        .map(|(_, source)| *source)
+        .unwrap();
+    return catalog_value(source);
+}
+
+/// First supported locale of an `Accept-Language` header, e.g. `en-US,en;q=0.9`
+pub fn from_accept_language(header: &str) -> Option<&'static str> {
+    return header
+        .split(',')
+        .filter_map(|part| part.split(';').next())
+        .map(|tag| tag.trim().to_lowercase())
+        .filter_map(|tag| {
+            let language = tag.split('-').next().unwrap_or("").to_string();
+            locales().into_iter().find(|locale| *locale == language)
+        })
+        .next();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_catalog_has_every_message() {
+        let default = &MESSAGES[DEFAULT_LOCALE];
+        for locale in locales() {
+            let mut missing: Vec<&String> = default
+                .keys()
+                .filter(|key| !MESSAGES[locale].contains_key(*key))
+                .collect();
+            missing.extend(
+                MESSAGES[locale]
+                    .keys()
+                    .filter(|key| !default.contains_key(*key)),
+            );
+            assert!(missing.is_empty(), "{} differs at {:?}", locale, missing);
+        }
+    }
+
+    #[test]
+    fn falls_back_to_the_default_locale_and_the_key() {
+        assert_eq!(tr("fr", "query.empty"), tr(DEFAULT_LOCALE, "query.empty"));
+        assert_ne!(tr("en", "query.empty"), tr("es", "query.empty"));
+        assert_eq!(tr("en", "no.such.key"), "no.such.key");
+    }
+
+    #[test]
+    fn replaces_arguments() {
+        assert_eq!(
+            tr_args("en", "query.bad_domain", &[("value", "x"), ("unused", "y")]),
+            "domain: expects a domain like youtube.com but got \"x\""
+        );
+    }
+
+    #[test]
+    fn reads_accept_language() {
+        assert_eq!(from_accept_language("en-US,en;q=0.9"), Some("en"));
+        assert_eq!(from_accept_language("fr-FR, ES;q=0.5"), Some("es"));
+        assert_eq!(from_accept_language("fr"), None);
+        assert_eq!(from_accept_language(""), None);
+        assert!(is_supported("en") && !is_supported("fr"));
+    }
+}
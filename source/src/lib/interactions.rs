@@ -0,0 +1,421 @@
+use super::bot_state::BotState;
+use super::commands::command::{ArgKind, Command, Permission};
+use super::commands::response::Response;
+use super::i18n::{is_supported, tr, DEFAULT_LOCALE};
+use super::tags::normalize;
+use chrono::Utc;
+use discord::model::{ChannelId, Message, MessageId};
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+
+const API_URL: &str = "https://discord.com/api/v10";
+/// Prefix shown in usages and errors of slash commands
+pub const SLASH_PREFIX: &str = "/";
+
+/// Interaction types discord sends
+const APPLICATION_COMMAND: u64 = 2;
+const AUTOCOMPLETE: u64 = 4;
+/// Ways to answer an interaction
+const CHANNEL_MESSAGE: u64 = 4;
+const DEFERRED_CHANNEL_MESSAGE: u64 = 5;
+const AUTOCOMPLETE_RESULT: u64 = 8;
+/// Message flag of replies only the caller sees
+const EPHEMERAL: u64 = 1 << 6;
+/// Message type of replies, their `message_reference` is the replied message
+const REPLY: u64 = 19;
+/// Option types
+const STRING_OPTION: u64 = 3;
+const INTEGER_OPTION: u64 = 4;
+
+/// Discord limits for descriptions and autocomplete choices
+const MAX_DESCRIPTION: usize = 100;
+const MAX_CHOICES: usize = 25;
+/// `MANAGE_GUILD`, members without it don't see admin commands in the picker
+const MANAGE_GUILD: &str = "32";
+/// Arguments completed with the tags of the server
+const TAG_ARGS: &[&str] = &["consulta", "cambios"];
+/// Discord locales our catalogs are shown in
+const DISCORD_LOCALES: &[(&str, &[&str])] =
+    &[("es", &["es-ES", "es-419"]), ("en", &["en-US", "en-GB"])];
+
+/// A slash command or an autocomplete request from `INTERACTION_CREATE`
+pub struct Interaction {
+    pub id: String,
+    pub token: String,
+    kind: u64,
+    pub command: String,
+    /// Option values by option name
+    pub options: HashMap<String, Value>,
+    /// Option being typed when discord asks for autocomplete choices
+    pub focused: Option<String>,
+    /// Stand-in for the message of a prefix command, so the same handlers
+    /// run for both. Its id is the interaction id.
+    pub message: Message,
+}
+
+#[allow(dead_code)]
+impl Interaction {
+    pub fn from_event(data: &Map<String, Value>) -> Option<Self> {
+        let kind = data.get("type")?.as_u64()?;
+        if kind != APPLICATION_COMMAND && kind != AUTOCOMPLETE {
+            return None;
+        }
+        let command = data.get("data")?;
+        let name = command.get("name")?.as_str()?.to_string();
+        // Guild interactions carry a member, private ones a user
+        let user = data
+            .get("member")
+            .and_then(|member| member.get("user"))
+            .or_else(|| data.get("user"))?;
+
+        // The content is what a prefix command would have looked like
+        let mut content = format!("/{}", name);
+        let mut options = HashMap::new();
+        let mut focused = None;
+        for option in command
+            .get("options")
+            .and_then(Value::as_array)
+            .cloned()
+            .unwrap_or_default()
+        {
+            let option_name = match option.get("name").and_then(Value::as_str) {
+                Some(option_name) => option_name.to_string(),
+                None => continue,
+            };
+            if option.get("focused").and_then(Value::as_bool) == Some(true) {
+                focused = Some(option_name.clone());
+            }
+            let value = option.get("value").cloned().unwrap_or(Value::Null);
+            match &value {
+                Value::String(text) => content = format!("{} {}", content, text),
+                Value::Number(number) => content = format!("{} {}", content, number),
+                _ => {}
+            }
+            options.insert(option_name, value);
+        }
+        let message = serde_json::from_value(json!({
+            "id": data.get("id")?,
+            "channel_id": data.get("channel_id")?,
+            "content": content,
+            "nonce": null,
+            "tts": false,
+            "timestamp": Utc::now().to_rfc3339(),
+            "edited_timestamp": null,
+            "pinned": false,
+            "type": 0,
+            "author": user,
+            "mention_everyone": false,
+            "mentions": [],
+            "mention_roles": [],
+            "reactions": [],
+            "attachments": [],
+            "embeds": [],
+        }))
+        .ok()?;
+
+        return Some(Self {
+            id: data.get("id")?.as_str()?.to_string(),
+            token: data.get("token")?.as_str()?.to_string(),
+            kind: kind,
+            command: name,
+            options: options,
+            focused: focused,
+            message: message,
+        });
+    }
+
+    pub fn is_autocomplete(&self) -> bool {
+        return self.kind == AUTOCOMPLETE;
+    }
+}
+
+/// Description of `key` in every catalog, cut to what discord accepts
+fn description(locale: &str, key: &str) -> String {
+    let text = tr(locale, key);
+    if text.chars().count() <= MAX_DESCRIPTION {
+        return text;
+    }
+    let cut: String = text.chars().take(MAX_DESCRIPTION - 1).collect();
+    return format!("{}…", cut);
+}
+
+fn localizations(key: &str) -> Value {
+    let mut localized = Map::new();
+    for (locale, discord_locales) in DISCORD_LOCALES {
+        if !is_supported(locale) {
+            continue;
+        }
+        for discord_locale in discord_locales.iter() {
+            localized.insert(
+                discord_locale.to_string(),
+                Value::String(description(locale, key)),
+            );
+        }
+    }
+    return Value::Object(localized);
+}
+
+/// Application command definitions of `commands`, for a bulk overwrite
+pub fn application_commands(commands: &[Command]) -> Value {
+    let definitions: Vec<Value> = commands
+        .iter()
+        .map(|command| {
+            let key = format!("slash.commands.{}", command.name);
+            let options: Vec<Value> = command
+                .args
+                .iter()
+                .map(|arg| {
+                    let option_name = arg.option_name();
+                    let key = format!("slash.options.{}", option_name);
+                    json!({
+                        "name": option_name,
+                        "description": description(DEFAULT_LOCALE, &key),
+                        "description_localizations": localizations(&key),
+                        "type": match arg.kind {
+                            ArgKind::Integer => INTEGER_OPTION,
+                            _ => STRING_OPTION,
+                        },
+                        "required": arg.required,
+                        "autocomplete": TAG_ARGS.contains(&arg.name),
+                    })
+                })
+                .collect();
+            let mut definition = json!({
+                "name": command.name,
+                "description": description(DEFAULT_LOCALE, &key),
+                "description_localizations": localizations(&key),
+                "options": options,
+                "dm_permission": !command.guild_only,
+            });
+            // Curators are a role of the bot discord knows nothing about,
+            // only admin commands can be hidden
+            if command.permission >= Permission::Admin {
+                definition["default_member_permissions"] = json!(MANAGE_GUILD);
+            }
+            definition
+        })
+        .collect();
+    return Value::Array(definitions);
+}
+
+/// Tags of the server completing the last word of `typed`, keeping its
+/// `#`, `+`, `-` or `tag:` marker and everything typed before it
+pub fn tag_choices(state: &mut BotState, guild_id: &str, typed: &str) -> Vec<String> {
+    let (before, word) = match typed.rfind(char::is_whitespace) {
+        Some(index) => (&typed[..index + 1], &typed[index + 1..]),
+        None => ("", typed),
+    };
+    let mut marker = "";
+    for prefix in &["#", "+", "-", "tag:", "etiqueta:"] {
+        if word.to_lowercase().starts_with(prefix) {
+            marker = &word[..prefix.len()];
+            break;
+        }
+    }
+    let partial = normalize(&word[marker.len()..]).unwrap_or_default();
+
+    return state
+        .db
+        .popular_tags(guild_id, 100)
+        .into_iter()
+        .filter(|(tag, _)| tag.starts_with(&partial))
+        .map(|(tag, _)| format!("{}{}{}", before, marker, tag))
+        .filter(|choice| choice.chars().count() <= MAX_DESCRIPTION)
+        .take(MAX_CHOICES)
+        .collect();
+}
+
+/// The REST calls discord-rs doesn't have: registering application
+/// commands, answering interactions and reading what a message replies to
+pub struct InteractionClient {
+    http: reqwest::blocking::Client,
+    token: String,
+    application_id: u64,
+}
+
+#[allow(dead_code)]
+impl InteractionClient {
+    pub fn new(token: &str, application_id: u64) -> Self {
+        return Self {
+            http: reqwest::blocking::Client::new(),
+            token: token.to_string(),
+            application_id: application_id,
+        };
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str, body: Value) -> bool {
+        return self.request_json(method, path, body).is_some();
+    }
+
+    /// What discord answered, `Value::Null` when it answered without a body.
+    /// A `Value::Null` body isn't sent.
+    fn request_json(&self, method: reqwest::Method, path: &str, body: Value) -> Option<Value> {
+        let mut request = self
+            .http
+            .request(method, &format!("{}{}", API_URL, path))
+            .header("Authorization", format!("Bot {}", self.token));
+        if !body.is_null() {
+            request = request.json(&body);
+        }
+        let result = request.send();
+
+        match result {
+            Ok(response) if response.status().is_success() => {
+                return Some(response.json().unwrap_or(Value::Null));
+            }
+            Ok(response) => {
+                println!("Discord API: {} answered {}", path, response.status());
+                return None;
+            }
+            Err(err) => {
+                println!("Discord API: {} failed: {:?}", path, err);
+                return None;
+            }
+        }
+    }
+
+    /// Replaces every global application command with `commands`
+    pub fn register(&self, commands: &[Command]) -> bool {
+        let path = format!("/applications/{}/commands", self.application_id);
+        return self.request(reqwest::Method::PUT, &path, application_commands(commands));
+    }
+
+    fn callback(&self, interaction: &Interaction, body: Value) -> bool {
+        let path = format!(
+            "/interactions/{}/{}/callback",
+            interaction.id, interaction.token
+        );
+        return self.request(reqwest::Method::POST, &path, body);
+    }
+
+    fn flags(ephemeral: bool) -> u64 {
+        return if ephemeral { EPHEMERAL } else { 0 };
+    }
+
+    /// Answers right away, for replies that don't run a command
+    pub fn respond(&self, interaction: &Interaction, content: &str, ephemeral: bool) -> bool {
+        return self.callback(
+            interaction,
+            json!({
+                "type": CHANNEL_MESSAGE,
+                "data": {"content": content, "flags": InteractionClient::flags(ephemeral)},
+            }),
+        );
+    }
+
+    /// Shows "thinking..." so the command has more than 3 seconds to run
+    pub fn defer(&self, interaction: &Interaction, ephemeral: bool) -> bool {
+        return self.callback(
+            interaction,
+            json!({
+                "type": DEFERRED_CHANNEL_MESSAGE,
+                "data": {"flags": InteractionClient::flags(ephemeral)},
+            }),
+        );
+    }
+
+    /// Replaces the "thinking..." of `defer` with the reply, returns the id
+    /// of the `@original` message it is shown in
+    pub fn edit_reply(&self, interaction: &Interaction, response: &Response) -> Option<MessageId> {
+        let path = format!(
+            "/webhooks/{}/{}/messages/@original",
+            self.application_id, interaction.token
+        );
+        let body = match response {
+            Response::Embed(embed) => json!({ "embeds": [embed.to_json()] }),
+            response => json!({ "content": response.text() }),
+        };
+        let message = self.request_json(reqwest::Method::PATCH, &path, body)?;
+        let id = message.get("id")?.as_str()?.parse::<u64>().ok()?;
+        return Some(MessageId(id));
+    }
+
+    /// Message `message_id` replies to, discord-rs drops `message_reference`
+    pub fn replied_to(
+        &self,
+        channel_id: ChannelId,
+        message_id: MessageId,
+    ) -> Option<(ChannelId, MessageId)> {
+        let path = format!("/channels/{}/messages/{}", channel_id.0, message_id.0);
+        let message = self.request_json(reqwest::Method::GET, &path, Value::Null)?;
+        return replied_message(&message);
+    }
+
+    pub fn autocomplete(&self, interaction: &Interaction, choices: &[String]) -> bool {
+        let choices: Vec<Value> = choices
+            .iter()
+            .map(|choice| json!({"name": choice, "value": choice}))
+            .collect();
+        return self.callback(
+            interaction,
+            json!({
+                "type": AUTOCOMPLETE_RESULT,
+                "data": {"choices": choices},
+            }),
+        );
+    }
+}
+
+/// Channel and id of the message a raw reply message answers
+fn replied_message(message: &Value) -> Option<(ChannelId, MessageId)> {
+    if message.get("type")?.as_u64()? != REPLY {
+        return None;
+    }
+    let reference = message.get("message_reference")?;
+    let channel_id = reference.get("channel_id")?.as_str()?.parse::<u64>().ok()?;
+    let message_id = reference.get("message_id")?.as_str()?.parse::<u64>().ok()?;
+    return Some((ChannelId(channel_id), MessageId(message_id)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::commands::registry;
+    use super::*;
+
+    #[test]
+    fn hides_admin_and_server_commands_where_they_cant_run() {
+        let definitions = application_commands(&registry());
+        let definition = |name: &str| {
+            definitions
+                .as_array()
+                .unwrap()
+                .iter()
+                .find(|definition| definition["name"] == name)
+                .cloned()
+                .unwrap()
+        };
+
+        let config = definition("config");
+        assert_eq!(config["default_member_permissions"], MANAGE_GUILD);
+        assert_eq!(config["dm_permission"], false);
+        assert_eq!(
+            definition("restore")["default_member_permissions"],
+            MANAGE_GUILD
+        );
+
+        let search = definition("search");
+        assert!(search.get("default_member_permissions").is_none());
+        assert_eq!(search["dm_permission"], true);
+        assert_eq!(definition("save")["dm_permission"], false);
+    }
+
+    #[test]
+    fn reads_the_message_a_reply_answers() {
+        let reply = json!({
+            "type": 19,
+            "message_reference": {"channel_id": "2000", "message_id": "42", "guild_id": "1000"},
+        });
+        assert_eq!(
+            replied_message(&reply),
+            Some((ChannelId(2000), MessageId(42)))
+        );
+
+        // Crossposts and forwards have references too, they aren't replies
+        let crosspost = json!({
+            "type": 0,
+            "message_reference": {"channel_id": "2000", "message_id": "42"},
+        });
+        assert_eq!(replied_message(&crosspost), None);
+        assert_eq!(replied_message(&json!({"type": 19})), None);
+    }
+}
@@ -0,0 +1,473 @@
+use chrono::{DateTime, Utc};
+use rand::seq::SliceRandom;
+use std::collections::HashMap;
+
+use super::collection::Collection;
+use super::guild_settings::GuildSettings;
+use super::migrations::Migration;
+use super::resource::Resource;
+use super::resource_store::ResourceStore;
+use super::search_query::{rank, Scope, SearchQuery};
+
+/// Keeps resources in a vector, useful for tests and local runs without Postgres
+#[derive(Default)]
+pub struct MemoryDatabase {
+    resources: Vec<Resource>,
+    guilds: HashMap<String, GuildSettings>,
+    user_locales: HashMap<String, String>,
+    migrations: Vec<i64>,
+    /// Guild, resource and when it was the resource of the day
+    daily_picks: Vec<(String, i64, DateTime<Utc>)>,
+    /// Guild and when it got a digest
+    digests: Vec<(String, DateTime<Utc>)>,
+    /// Votes by resource and user
+    ratings: HashMap<(i64, String), i32>,
+    /// User and resource, in the order they were bookmarked
+    bookmarks: Vec<(String, i64)>,
+    collections: Vec<Collection>,
+}
+
+#[allow(dead_code)]
+impl MemoryDatabase {
+    pub fn new() -> Self {
+        return Self::default();
+    }
+
+    fn in_scope(&self, scope: &Scope, resource: &Resource) -> bool {
+        return match scope {
+            Scope::Guild(guild_id) => &resource.guild_id == guild_id,
+            Scope::Public => self
+                .guilds
+                .get(&resource.guild_id)
+                .map_or(false, |settings| settings.share_global),
+        };
+    }
+
+    /// Best matches first, newest first among equally good ones
+    fn matching(&self, query: &SearchQuery) -> Vec<&Resource> {
+        let found: Vec<&Resource> = self
+            .resources
+            .iter()
+            .filter(|resource| resource.deleted_at.is_none())
+            .filter(|resource| self.in_scope(&query.scope, resource))
+            .filter(|resource| query.matches_filters(resource))
+            .collect();
+
+        return rank(&query.terms, found);
+    }
+
+    /// `collection` with the current version of its resources, they may have
+    /// been edited since they were added
+    fn refreshed(&self, collection: &Collection) -> Collection {
+        let mut collection = collection.clone();
+        for item in collection.items.iter_mut() {
+            if let Some(resource) = self
+                .resources
+                .iter()
+                .find(|resource| resource.id == item.resource.id)
+            {
+                item.resource = resource.clone();
+            }
+        }
+        return collection;
+    }
+
+    /// Resources are handed out as clones, they carry their votes
+    fn count_votes(&mut self, resource_id: i64) {
+        let scores: Vec<i32> = self
+            .ratings
+            .iter()
+            .filter(|((rated, _), _)| *rated == resource_id)
+            .map(|(_, score)| *score)
+            .collect();
+        for resource in self
+            .resources
+            .iter_mut()
+            .filter(|resource| resource.id == resource_id)
+        {
+            resource.votes = scores.len() as i64;
+            resource.rating = match scores.len() {
+                0 => 0.0,
+                votes => scores.iter().sum::<i32>() as f64 / votes as f64,
+            };
+        }
+    }
+
+    fn find_mut(&mut self, guild_id: &str, id: i64) -> Option<&mut Resource> {
+        return self
+            .resources
+            .iter_mut()
+            .find(|resource| resource.guild_id == guild_id && resource.id == id);
+    }
+}
+
+impl ResourceStore for MemoryDatabase {
+    fn insert_resource(&mut self, resource: Resource) -> bool {
+        if resource.url.is_empty() {
+            return false;
+        }
+        if !resource.shash.is_empty()
+            && self
+                .select_resource_by_hash(&resource.guild_id, &resource.shash)
+                .is_some()
+        {
+            return false;
+        }
+
+        let mut resource = resource;
+        resource.id = self.resources.len() as i64 + 1;
+        resource.created_at = Some(Utc::now());
+        resource.tags.sort();
+
+        self.resources.push(resource);
+        return true;
+    }
+
+    fn select_resources(&mut self, query: &SearchQuery, limit: u16, page: u16) -> Vec<Resource> {
+        let offset = page as usize * limit as usize;
+
+        return self
+            .matching(query)
+            .into_iter()
+            .skip(offset)
+            .take(limit as usize)
+            .cloned()
+            .collect();
+    }
+
+    fn select_random_resource(&mut self, query: &SearchQuery, excluded: &[i64]) -> Vec<Resource> {
+        let matching: Vec<&Resource> = self
+            .matching(query)
+            .into_iter()
+            .filter(|resource| !excluded.contains(&resource.id))
+            .collect();
+
+        return match matching.choose(&mut rand::thread_rng()) {
+            Some(resource) => vec![(*resource).clone()],
+            None => Vec::new(),
+        };
+    }
+
+    fn select_resource_by_hash(&mut self, guild_id: &str, shash: &str) -> Option<Resource> {
+        return self
+            .resources
+            .iter()
+            .find(|resource| {
+                resource.guild_id == guild_id
+                    && resource.shash == shash
+                    && resource.deleted_at.is_none()
+            })
+            .cloned();
+    }
+
+    fn select_resource(&mut self, guild_id: &str, id: i64) -> Option<Resource> {
+        return self
+            .resources
+            .iter()
+            .find(|resource| resource.guild_id == guild_id && resource.id == id)
+            .cloned();
+    }
+
+    fn update_resource(&mut self, resource: &Resource) -> bool {
+        return match self.find_mut(&resource.guild_id, resource.id) {
+            Some(saved) => {
+                saved.title = resource.title.clone();
+                saved.description = resource.description.clone();
+                saved.type_id = resource.type_id;
+                true
+            }
+            None => false,
+        };
+    }
+
+    fn delete_resource(&mut self, guild_id: &str, id: i64, deleted_by: &str) -> bool {
+        return match self.find_mut(guild_id, id) {
+            Some(saved) if saved.deleted_at.is_none() => {
+                saved.deleted_at = Some(Utc::now());
+                saved.deleted_by = deleted_by.to_string();
+                true
+            }
+            _ => false,
+        };
+    }
+
+    fn restore_resource(&mut self, guild_id: &str, id: i64) -> bool {
+        let shash = match self.select_resource(guild_id, id) {
+            Some(resource) if resource.deleted_at.is_some() => resource.shash,
+            _ => return false,
+        };
+        if !shash.is_empty() && self.select_resource_by_hash(guild_id, &shash).is_some() {
+            return false;
+        }
+
+        if let Some(saved) = self.find_mut(guild_id, id) {
+            saved.deleted_at = None;
+            saved.deleted_by = "".to_string();
+        }
+        return true;
+    }
+
+    fn select_deleted_resources(&mut self, guild_id: &str, limit: u16) -> Vec<Resource> {
+        let mut deleted: Vec<Resource> = self
+            .resources
+            .iter()
+            .filter(|resource| resource.guild_id == guild_id && resource.deleted_at.is_some())
+            .cloned()
+            .collect();
+        deleted.sort_by(|a, b| b.deleted_at.cmp(&a.deleted_at));
+        deleted.truncate(limit as usize);
+
+        return deleted;
+    }
+
+    fn set_resource_tags(&mut self, resource_id: i64, tags: &[String]) -> bool {
+        return match self
+            .resources
+            .iter_mut()
+            .find(|resource| resource.id == resource_id)
+        {
+            Some(saved) => {
+                saved.tags = tags.to_vec();
+                saved.tags.sort();
+                true
+            }
+            None => false,
+        };
+    }
+
+    fn popular_tags(&mut self, guild_id: &str, limit: u16) -> Vec<(String, i64)> {
+        let mut uses: HashMap<String, i64> = HashMap::new();
+        for resource in &self.resources {
+            if resource.guild_id != guild_id || resource.deleted_at.is_some() {
+                continue;
+            }
+            for tag in &resource.tags {
+                *uses.entry(tag.clone()).or_insert(0) += 1;
+            }
+        }
+
+        let mut popular: Vec<(String, i64)> = uses.into_iter().collect();
+        popular.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        popular.truncate(limit as usize);
+
+        return popular;
+    }
+
+    fn daily_picks(&mut self, guild_id: &str, since: DateTime<Utc>) -> Vec<(i64, DateTime<Utc>)> {
+        return self
+            .daily_picks
+            .iter()
+            .rev()
+            .filter(|(guild, _, picked_at)| guild == guild_id && *picked_at >= since)
+            .map(|(_, resource_id, picked_at)| (*resource_id, *picked_at))
+            .collect();
+    }
+
+    fn save_daily_pick(&mut self, guild_id: &str, resource_id: i64) -> bool {
+        self.daily_picks
+            .push((guild_id.to_string(), resource_id, Utc::now()));
+        return true;
+    }
+
+    fn add_bookmark(&mut self, user_id: &str, resource_id: i64) -> bool {
+        let bookmark = (user_id.to_string(), resource_id);
+        if self.bookmarks.contains(&bookmark) {
+            return false;
+        }
+        self.bookmarks.push(bookmark);
+        return true;
+    }
+
+    fn remove_bookmark(&mut self, user_id: &str, resource_id: i64) -> bool {
+        let before = self.bookmarks.len();
+        self.bookmarks
+            .retain(|(user, resource)| user != user_id || *resource != resource_id);
+        return self.bookmarks.len() < before;
+    }
+
+    fn select_bookmarks(&mut self, guild_id: &str, user_id: &str) -> Vec<Resource> {
+        return self
+            .bookmarks
+            .iter()
+            .rev()
+            .filter(|(user, _)| user == user_id)
+            .filter_map(|(_, resource_id)| {
+                self.resources.iter().find(|resource| {
+                    resource.id == *resource_id
+                        && resource.guild_id == guild_id
+                        && resource.deleted_at.is_none()
+                })
+            })
+            .cloned()
+            .collect();
+    }
+
+    fn insert_collection(&mut self, collection: &Collection) -> Option<i64> {
+        if self.collections.iter().any(|saved| {
+            saved.guild_id == collection.guild_id
+                && saved.user_id == collection.user_id
+                && saved.name == collection.name
+        }) {
+            return None;
+        }
+
+        let mut collection = collection.clone();
+        collection.id = self
+            .collections
+            .iter()
+            .map(|saved| saved.id)
+            .max()
+            .unwrap_or(0)
+            + 1;
+        collection.created_at = Some(Utc::now());
+        collection.items.clear();
+        self.collections.push(collection.clone());
+        return Some(collection.id);
+    }
+
+    fn select_collection(&mut self, guild_id: &str, id: i64) -> Option<Collection> {
+        return self
+            .collections
+            .iter()
+            .find(|collection| collection.guild_id == guild_id && collection.id == id)
+            .map(|collection| self.refreshed(collection));
+    }
+
+    fn select_collections(&mut self, guild_id: &str, user_id: &str) -> Vec<Collection> {
+        let mut collections: Vec<Collection> = self
+            .collections
+            .iter()
+            .filter(|collection| collection.guild_id == guild_id && collection.user_id == user_id)
+            .map(|collection| self.refreshed(collection))
+            .collect();
+        collections.sort_by(|a, b| a.name.cmp(&b.name));
+        return collections;
+    }
+
+    fn update_collection(&mut self, collection: &Collection) -> bool {
+        return match self
+            .collections
+            .iter_mut()
+            .find(|saved| saved.id == collection.id)
+        {
+            Some(saved) => {
+                saved.description = collection.description.clone();
+                saved.items = collection.items.clone();
+                true
+            }
+            None => false,
+        };
+    }
+
+    fn delete_collection(&mut self, guild_id: &str, id: i64) -> bool {
+        let before = self.collections.len();
+        self.collections
+            .retain(|collection| collection.guild_id != guild_id || collection.id != id);
+        return self.collections.len() < before;
+    }
+
+    fn select_resources_since(
+        &mut self,
+        guild_id: &str,
+        since: DateTime<Utc>,
+        limit: u16,
+    ) -> Vec<Resource> {
+        return self
+            .resources
+            .iter()
+            .rev()
+            .filter(|resource| resource.guild_id == guild_id && resource.deleted_at.is_none())
+            .filter(|resource| {
+                resource
+                    .created_at
+                    .map_or(false, |created| created >= since)
+            })
+            .take(limit as usize)
+            .cloned()
+            .collect();
+    }
+
+    fn rate_resource(&mut self, resource_id: i64, user_id: &str, score: i32) -> bool {
+        if !self
+            .resources
+            .iter()
+            .any(|resource| resource.id == resource_id)
+        {
+            return false;
+        }
+        self.ratings
+            .insert((resource_id, user_id.to_string()), score);
+        self.count_votes(resource_id);
+        return true;
+    }
+
+    fn unrate_resource(&mut self, resource_id: i64, user_id: &str, score: i32) -> bool {
+        let key = (resource_id, user_id.to_string());
+        if self.ratings.get(&key) != Some(&score) {
+            return false;
+        }
+        self.ratings.remove(&key);
+        self.count_votes(resource_id);
+        return true;
+    }
+
+    fn last_digest(&mut self, guild_id: &str) -> Option<DateTime<Utc>> {
+        return self
+            .digests
+            .iter()
+            .rev()
+            .find(|(guild, _)| guild == guild_id)
+            .map(|(_, posted_at)| *posted_at);
+    }
+
+    fn save_digest(&mut self, guild_id: &str) -> bool {
+        self.digests.push((guild_id.to_string(), Utc::now()));
+        return true;
+    }
+
+    fn guild_settings(&mut self, guild_id: &str) -> GuildSettings {
+        return match self.guilds.get(guild_id) {
+            Some(settings) => settings.clone(),
+            None => GuildSettings::new(guild_id),
+        };
+    }
+
+    fn save_guild_settings(&mut self, settings: &GuildSettings) -> bool {
+        self.guilds
+            .insert(settings.guild_id.clone(), settings.clone());
+        return true;
+    }
+
+    fn user_locale(&mut self, user_id: &str) -> Option<String> {
+        return self.user_locales.get(user_id).cloned();
+    }
+
+    fn save_user_locale(&mut self, user_id: &str, locale: &str) -> bool {
+        self.user_locales
+            .insert(user_id.to_string(), locale.to_string());
+        return true;
+    }
+
+    fn applied_migrations(&mut self) -> Result<Vec<i64>, String> {
+        return Ok(self.migrations.clone());
+    }
+
+    fn apply_migration(&mut self, migration: &Migration) -> Result<(), String> {
+        // Nothing to create, only remember the version
+        self.migrations.push(migration.version);
+        return Ok(());
+    }
+
+    fn assign_legacy_resources(&mut self, guild_id: &str) -> Result<u64, String> {
+        let mut assigned = 0;
+        for resource in self
+            .resources
+            .iter_mut()
+            .filter(|resource| resource.guild_id.is_empty())
+        {
+            resource.guild_id = guild_id.to_string();
+            assigned += 1;
+        }
+        return Ok(assigned);
+    }
+}
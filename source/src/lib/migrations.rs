@@ -0,0 +1,179 @@
+use super::resource_store::ResourceStore;
+
+/// A schema change, each backend gets its own flavour of SQL
+pub struct Migration {
+    pub version: i64,
+    pub name: &'static str,
+    pub postgres: &'static str,
+    pub sqlite: &'static str,
+}
+
+/// Every migration, in the order they must be applied.
+/// Never edit an applied migration, add a new one instead.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "initial",
+        postgres: include_str!("../../migrations/postgres/0001_initial.sql"),
+        sqlite: include_str!("../../migrations/sqlite/0001_initial.sql"),
+    },
+    Migration {
+        version: 2,
+        name: "full_text_search",
+        postgres: include_str!("../../migrations/postgres/0002_full_text_search.sql"),
+        sqlite: include_str!("../../migrations/sqlite/0002_full_text_search.sql"),
+    },
+    Migration {
+        version: 3,
+        name: "created_at",
+        postgres: include_str!("../../migrations/postgres/0003_created_at.sql"),
+        sqlite: include_str!("../../migrations/sqlite/0003_created_at.sql"),
+    },
+    Migration {
+        version: 4,
+        name: "guilds",
+        postgres: include_str!("../../migrations/postgres/0004_guilds.sql"),
+        sqlite: include_str!("../../migrations/sqlite/0004_guilds.sql"),
+    },
+    Migration {
+        version: 5,
+        name: "embed_fields",
+        postgres: include_str!("../../migrations/postgres/0005_embed_fields.sql"),
+        sqlite: include_str!("../../migrations/sqlite/0005_embed_fields.sql"),
+    },
+    Migration {
+        version: 6,
+        name: "unique_hash",
+        postgres: include_str!("../../migrations/postgres/0006_unique_hash.sql"),
+        sqlite: include_str!("../../migrations/sqlite/0006_unique_hash.sql"),
+    },
+    Migration {
+        version: 7,
+        name: "locales",
+        postgres: include_str!("../../migrations/postgres/0007_locales.sql"),
+        sqlite: include_str!("../../migrations/sqlite/0007_locales.sql"),
+    },
+    Migration {
+        version: 8,
+        name: "guild_config",
+        postgres: include_str!("../../migrations/postgres/0008_guild_config.sql"),
+        sqlite: include_str!("../../migrations/sqlite/0008_guild_config.sql"),
+    },
+    Migration {
+        version: 9,
+        name: "soft_delete",
+        postgres: include_str!("../../migrations/postgres/0009_soft_delete.sql"),
+        sqlite: include_str!("../../migrations/sqlite/0009_soft_delete.sql"),
+    },
+    Migration {
+        version: 10,
+        name: "tags",
+        postgres: include_str!("../../migrations/postgres/0010_tags.sql"),
+        sqlite: include_str!("../../migrations/sqlite/0010_tags.sql"),
+    },
+    Migration {
+        version: 11,
+        name: "resource_types",
+        postgres: include_str!("../../migrations/postgres/0011_resource_types.sql"),
+        sqlite: include_str!("../../migrations/sqlite/0011_resource_types.sql"),
+    },
+    Migration {
+        version: 12,
+        name: "daily_picks",
+        postgres: include_str!("../../migrations/postgres/0012_daily_picks.sql"),
+        sqlite: include_str!("../../migrations/sqlite/0012_daily_picks.sql"),
+    },
+    Migration {
+        version: 13,
+        name: "digests",
+        postgres: include_str!("../../migrations/postgres/0013_digests.sql"),
+        sqlite: include_str!("../../migrations/sqlite/0013_digests.sql"),
+    },
+    Migration {
+        version: 14,
+        name: "ratings",
+        postgres: include_str!("../../migrations/postgres/0014_ratings.sql"),
+        sqlite: include_str!("../../migrations/sqlite/0014_ratings.sql"),
+    },
+    Migration {
+        version: 15,
+        name: "bookmarks_collections",
+        postgres: include_str!("../../migrations/postgres/0015_bookmarks_collections.sql"),
+        sqlite: include_str!("../../migrations/sqlite/0015_bookmarks_collections.sql"),
+    },
+];
+
+/// Applies every pending migration, returns the versions applied now.
+pub fn migrate(store: &mut dyn ResourceStore) -> Result<Vec<i64>, String> {
+    let applied = store.applied_migrations()?;
+    let mut versions = Vec::new();
+
+    for migration in MIGRATIONS {
+        if applied.contains(&migration.version) {
+            continue;
+        }
+
+        store.apply_migration(migration).map_err(|err| {
+            format!(
+                "Migration {} ({}) failed: {}",
+                migration.version, migration.name, err
+            )
+        })?;
+        versions.push(migration.version);
+    }
+    return Ok(versions);
+}
+
+/// Every known migration together with whether it has been applied.
+pub fn status(store: &mut dyn ResourceStore) -> Result<Vec<(&'static Migration, bool)>, String> {
+    let applied = store.applied_migrations()?;
+
+    return Ok(MIGRATIONS
+        .iter()
+        .map(|migration| (migration, applied.contains(&migration.version)))
+        .collect());
+}
+
+/// Resources saved before migration 4 have an empty guild id, no search,
+/// page or API finds them until they are given the server they came from.
+/// Returns how many were given to `guild_id`.
+pub fn assign_legacy(store: &mut dyn ResourceStore, guild_id: &str) -> Result<u64, String> {
+    if guild_id.is_empty() || !guild_id.chars().all(|character| character.is_ascii_digit()) {
+        return Err(format!("Not a guild id: {}", guild_id));
+    }
+    return store.assign_legacy_resources(guild_id);
+}
+
+/// `migrate` subcommand: `migrate` applies pending migrations, `migrate status` lists them
+/// and `migrate legacy <guild_id>` gives the resources saved before servers existed to a server.
+pub fn run_command(
+    store: &mut dyn ResourceStore,
+    action: Option<&str>,
+    argument: Option<&str>,
+) -> Result<(), String> {
+    match action.unwrap_or("up") {
+        "up" => {
+            let versions = migrate(store)?;
+            if versions.is_empty() {
+                println!("Database is up to date.");
+            }
+            for version in versions {
+                println!("Applied migration {}", version);
+            }
+        }
+        "status" => {
+            for (migration, applied) in status(store)? {
+                let state = if applied { "applied" } else { "pending" };
+                println!("{:>4}  {:<30} {}", migration.version, migration.name, state);
+            }
+        }
+        "legacy" => {
+            let guild_id = argument.ok_or("Usage: migrate legacy <guild_id>")?;
+            migrate(store)?;
+            let assigned = assign_legacy(store, guild_id)?;
+            println!("Assigned {} legacy resources to {}", assigned, guild_id);
+        }
+        other => return Err(format!("Unknown migrate action: {}", other)),
+    }
+    return Ok(());
+}
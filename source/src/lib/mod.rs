@@ -1,5 +1,23 @@
 pub mod app_state;
 pub mod bot_state;
+pub mod canonical_url;
+pub mod collection;
 pub mod commands;
 pub mod custom_database;
+pub mod embed;
 pub mod endpoints;
+pub mod gateway;
+pub mod guild_settings;
+pub mod i18n;
+pub mod interactions;
+pub mod memory_database;
+pub mod migrations;
+pub mod resource;
+pub mod resource_store;
+pub mod resource_type;
+pub mod scheduler;
+pub mod search_query;
+pub mod sqlite_database;
+pub mod tags;
+#[cfg(test)]
+pub mod testing;
@@ -0,0 +1,137 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::canonical_url::{canonicalize, content_hash};
+use super::embed::{find_urls, Embed};
+use super::resource_type::ResourceType;
+use super::search_query::rating_weight;
+use discord::model;
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct Resource {
+    pub id: i64,
+    pub guild_id: String,
+    pub user_id: String,
+    pub channel_id: String,
+    /// Canonical form, see `canonical_url::canonicalize`
+    pub url: String,
+    pub title: String,
+    pub description: String,
+    /// Site that published it, e.g. YouTube
+    pub provider: String,
+    pub author: String,
+    pub thumbnail_url: String,
+    pub video_url: String,
+    /// SHA-256 of the canonical url, unique per guild
+    pub shash: String,
+    /// See `resource_type::ResourceType`
+    pub type_id: i32,
+    /// Normalized tag names, see `tags::normalize`
+    pub tags: Vec<String>,
+    pub created_at: Option<DateTime<Utc>>,
+    /// Set by `!delete`, deleted resources are hidden until an admin restores them
+    pub deleted_at: Option<DateTime<Utc>>,
+    pub deleted_by: String,
+    /// Average of the votes, 0 until someone votes
+    pub rating: f64,
+    pub votes: i64,
+}
+
+#[allow(dead_code)]
+impl Resource {
+    /// How much the votes push the resource up in rankings, see `rating_weight`
+    pub fn weight(&self) -> f64 {
+        return rating_weight(self.rating, self.votes);
+    }
+
+    /// Every resource found in `message`: embeds, links discord didn't
+    /// embed (yet) and file attachments. The author of the message is credited.
+    pub fn from_message(message: &model::Message, guild_id: &model::ServerId) -> Vec<Self> {
+        let mut resources: Vec<Self> = Vec::new();
+
+        for embed in message.embeds.iter().filter_map(Embed::parse) {
+            let mut resource = Resource::new(message, guild_id);
+            resource.fill_from_embed(&embed);
+            resource.type_id = ResourceType::classify(
+                &resource.url,
+                embed.kind.as_deref(),
+                !resource.video_url.is_empty(),
+            )
+            .id();
+            resources.push(resource);
+        }
+
+        // Discord adds embeds a moment after the message is sent
+        for url in find_urls(&message.content) {
+            let mut resource = Resource::new(message, guild_id);
+            resource.type_id = ResourceType::classify(&url, None, false).id();
+            resource.url = url;
+            resources.push(resource);
+        }
+
+        for attachment in &message.attachments {
+            let mut resource = Resource::new(message, guild_id);
+            resource.fill_from_attachment(attachment);
+            resource.type_id = attachment_type(attachment).id();
+            resources.push(resource);
+        }
+
+        for resource in resources.iter_mut() {
+            resource.url = canonicalize(&resource.url);
+            resource.shash = content_hash(&resource.url);
+        }
+
+        let mut seen: Vec<String> = Vec::new();
+        resources.retain(|resource| {
+            if resource.url.is_empty() || seen.contains(&resource.shash) {
+                return false;
+            }
+            seen.push(resource.shash.clone());
+            return true;
+        });
+
+        return resources;
+    }
+
+    /// An empty resource credited to the author of `message`
+    pub fn new(message: &model::Message, guild_id: &model::ServerId) -> Self {
+        return Self {
+            guild_id: guild_id.0.to_string(),
+            user_id: message.author.id.to_string(),
+            channel_id: message.channel_id.0.to_string(),
+            type_id: ResourceType::Link.id(),
+            ..Default::default()
+        };
+    }
+
+    pub fn fill_from_embed(&mut self, embed: &Embed) {
+        self.url = embed.url();
+        self.title = embed.title();
+        self.description = embed.description();
+        self.provider = embed.provider();
+        self.author = embed.author();
+        self.thumbnail_url = embed.thumbnail_url();
+        self.video_url = embed.video_url();
+    }
+
+    pub fn fill_from_attachment(&mut self, attachment: &model::Attachment) {
+        self.url = attachment.url.trim().to_string();
+        self.title = attachment.filename.clone();
+        self.description = format!("{} KB", attachment.size / 1024);
+        self.provider = "Discord".to_string();
+
+        // Images are their own thumbnail
+        if attachment_type(attachment) == ResourceType::Image {
+            self.thumbnail_url = self.url.clone();
+        }
+    }
+}
+
+/// Type of an uploaded file by its extension. Discord gives dimensions to
+/// videos too, they only tell images apart when the extension is unknown.
+fn attachment_type(attachment: &model::Attachment) -> ResourceType {
+    return match ResourceType::classify(&attachment.filename, None, false) {
+        ResourceType::Link if attachment.dimensions.is_some() => ResourceType::Image,
+        kind => kind,
+    };
+}
@@ -0,0 +1,127 @@
+use chrono::{DateTime, Utc};
+
+use super::collection::Collection;
+use super::custom_database::DiscordDatabase;
+use super::guild_settings::GuildSettings;
+use super::memory_database::MemoryDatabase;
+use super::migrations::Migration;
+use super::resource::Resource;
+use super::search_query::SearchQuery;
+use super::sqlite_database::SqliteDatabase;
+
+/// Operations the bot and the web server need from a resources backend.
+pub trait ResourceStore {
+    /// `false` when it can't be saved, including duplicates of a saved resource
+    fn insert_resource(&mut self, resource: Resource) -> bool;
+
+    /// Best matches first, `page` starts at 0
+    fn select_resources(&mut self, query: &SearchQuery, limit: u16, page: u16) -> Vec<Resource>;
+
+    /// A random match of `query`, an empty query matches anything in its scope
+    fn select_random_resource(&mut self, query: &SearchQuery, excluded: &[i64]) -> Vec<Resource>;
+
+    /// The resource of `guild_id` with the content hash `shash`, if saved before
+    fn select_resource_by_hash(&mut self, guild_id: &str, shash: &str) -> Option<Resource>;
+
+    /// The resource `id` of `guild_id`, deleted or not
+    fn select_resource(&mut self, guild_id: &str, id: i64) -> Option<Resource>;
+
+    /// Saves the title, description and type of `resource`
+    fn update_resource(&mut self, resource: &Resource) -> bool;
+
+    /// Soft delete, the resource stays in the database for `restore_resource`
+    fn delete_resource(&mut self, guild_id: &str, id: i64, deleted_by: &str) -> bool;
+
+    /// `false` when it isn't deleted or the same url was saved again meanwhile
+    fn restore_resource(&mut self, guild_id: &str, id: i64) -> bool;
+
+    /// Replaces the tags of `resource_id`, the names are already normalized
+    fn set_resource_tags(&mut self, resource_id: i64, tags: &[String]) -> bool;
+
+    /// Most used tags of `guild_id` and how many resources carry each one
+    fn popular_tags(&mut self, guild_id: &str, limit: u16) -> Vec<(String, i64)>;
+
+    /// Most recently deleted resources of `guild_id`
+    fn select_deleted_resources(&mut self, guild_id: &str, limit: u16) -> Vec<Resource>;
+
+    /// Resources of the day of `guild_id` picked since `since`, newest first
+    fn daily_picks(&mut self, guild_id: &str, since: DateTime<Utc>) -> Vec<(i64, DateTime<Utc>)>;
+
+    fn save_daily_pick(&mut self, guild_id: &str, resource_id: i64) -> bool;
+
+    /// Vote of `user_id`, from 1 to 5, replacing the one they gave before
+    fn rate_resource(&mut self, resource_id: i64, user_id: &str, score: i32) -> bool;
+
+    /// Takes back the vote of `user_id` if it still is `score`, a vote
+    /// replaced since then stays. `false` when there was no such vote.
+    fn unrate_resource(&mut self, resource_id: i64, user_id: &str, score: i32) -> bool;
+
+    /// `false` when `user_id` had already bookmarked it
+    fn add_bookmark(&mut self, user_id: &str, resource_id: i64) -> bool;
+
+    /// `false` when `user_id` hadn't bookmarked it
+    fn remove_bookmark(&mut self, user_id: &str, resource_id: i64) -> bool;
+
+    /// Resources of `guild_id` bookmarked by `user_id`, last bookmarked first
+    fn select_bookmarks(&mut self, guild_id: &str, user_id: &str) -> Vec<Resource>;
+
+    /// Id of the new collection, `None` when its owner has one with that name
+    fn insert_collection(&mut self, collection: &Collection) -> Option<i64>;
+
+    /// A collection with its items, deleted resources included
+    fn select_collection(&mut self, guild_id: &str, id: i64) -> Option<Collection>;
+
+    /// Collections of `user_id` in `guild_id` by name, with their items
+    fn select_collections(&mut self, guild_id: &str, user_id: &str) -> Vec<Collection>;
+
+    /// Saves the description and the items, in their order
+    fn update_collection(&mut self, collection: &Collection) -> bool;
+
+    fn delete_collection(&mut self, guild_id: &str, id: i64) -> bool;
+
+    /// Resources of `guild_id` saved since `since`, newest first
+    fn select_resources_since(
+        &mut self,
+        guild_id: &str,
+        since: DateTime<Utc>,
+        limit: u16,
+    ) -> Vec<Resource>;
+
+    /// When `guild_id` got its last digest
+    fn last_digest(&mut self, guild_id: &str) -> Option<DateTime<Utc>>;
+
+    fn save_digest(&mut self, guild_id: &str) -> bool;
+
+    /// Settings of `guild_id`, the defaults when the guild never changed them
+    fn guild_settings(&mut self, guild_id: &str) -> GuildSettings;
+
+    fn save_guild_settings(&mut self, settings: &GuildSettings) -> bool;
+
+    /// Reply language chosen by `user_id`, if any
+    fn user_locale(&mut self, user_id: &str) -> Option<String>;
+
+    fn save_user_locale(&mut self, user_id: &str, locale: &str) -> bool;
+
+    /// Versions recorded in the `schema_migrations` table, creating it if needed
+    fn applied_migrations(&mut self) -> Result<Vec<i64>, String>;
+
+    /// Runs `migration` and records its version, all or nothing
+    fn apply_migration(&mut self, migration: &Migration) -> Result<(), String>;
+
+    /// Gives the resources saved before they belonged to a server, the ones
+    /// with an empty guild id, to `guild_id`. Returns how many there were.
+    fn assign_legacy_resources(&mut self, guild_id: &str) -> Result<u64, String>;
+}
+
+/// Opens the backend matching the scheme of `database_uri`: `sqlite://file.db`,
+/// `memory://` to keep everything in process and anything else is Postgres.
+#[allow(dead_code)]
+pub fn connect(database_uri: String) -> Box<dyn ResourceStore + Send> {
+    if database_uri.starts_with("sqlite://") {
+        return Box::new(SqliteDatabase::new(database_uri));
+    }
+    if database_uri.starts_with("memory://") {
+        return Box::new(MemoryDatabase::new());
+    }
+    return Box::new(DiscordDatabase::new(database_uri));
+}
@@ -0,0 +1,285 @@
+use super::search_query::url_host;
+
+/// Kinds of resources, the ids match the rows seeded in the `types` table
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ResourceType {
+    Video = 1,
+    Article = 2,
+    Repository = 3,
+    Documentation = 4,
+    Course = 5,
+    Podcast = 6,
+    Image = 7,
+    Pdf = 8,
+    /// Anything we couldn't classify, the default of old resources
+    Link = 10,
+}
+
+pub const RESOURCE_TYPES: &[ResourceType] = &[
+    ResourceType::Video,
+    ResourceType::Article,
+    ResourceType::Repository,
+    ResourceType::Documentation,
+    ResourceType::Course,
+    ResourceType::Podcast,
+    ResourceType::Image,
+    ResourceType::Pdf,
+    ResourceType::Link,
+];
+
+const VIDEO_HOSTS: &[&str] = &[
+    "youtube.com",
+    "youtu.be",
+    "vimeo.com",
+    "twitch.tv",
+    "dailymotion.com",
+];
+const REPOSITORY_HOSTS: &[&str] = &[
+    "github.com",
+    "gitlab.com",
+    "bitbucket.org",
+    "codeberg.org",
+    "sr.ht",
+];
+const DOCUMENTATION_HOSTS: &[&str] = &[
+    "docs.rs",
+    "doc.rust-lang.org",
+    "developer.mozilla.org",
+    "readthedocs.io",
+    "devdocs.io",
+];
+const COURSE_HOSTS: &[&str] = &[
+    "udemy.com",
+    "coursera.org",
+    "edx.org",
+    "platzi.com",
+    "freecodecamp.org",
+    "domestika.org",
+    "khanacademy.org",
+    "egghead.io",
+];
+const PODCAST_HOSTS: &[&str] = &[
+    "podcasts.apple.com",
+    "anchor.fm",
+    "podbean.com",
+    "ivoox.com",
+];
+const ARTICLE_HOSTS: &[&str] = &[
+    "medium.com",
+    "dev.to",
+    "hashnode.dev",
+    "substack.com",
+    "wikipedia.org",
+];
+const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "gif", "webp", "svg", "bmp"];
+const VIDEO_EXTENSIONS: &[&str] = &["mp4", "webm", "mov", "mkv"];
+
+#[allow(dead_code)]
+impl ResourceType {
+    pub fn id(&self) -> i32 {
+        return *self as i32;
+    }
+
+    /// Name used in `type:` filters and in the `types` table
+    pub fn name(&self) -> &'static str {
+        return match self {
+            ResourceType::Video => "video",
+            ResourceType::Article => "article",
+            ResourceType::Repository => "repository",
+            ResourceType::Documentation => "documentation",
+            ResourceType::Course => "course",
+            ResourceType::Podcast => "podcast",
+            ResourceType::Image => "image",
+            ResourceType::Pdf => "pdf",
+            ResourceType::Link => "link",
+        };
+    }
+
+    pub fn from_id(id: i32) -> Option<Self> {
+        return RESOURCE_TYPES.iter().find(|kind| kind.id() == id).cloned();
+    }
+
+    /// Accepts the english and spanish names or the id
+    pub fn from_name(name: &str) -> Option<Self> {
+        if let Ok(id) = name.parse::<i32>() {
+            return ResourceType::from_id(id);
+        }
+        return match name.to_lowercase().as_str() {
+            "video" => Some(ResourceType::Video),
+            "article" | "articulo" | "blog" => Some(ResourceType::Article),
+            "repository" | "repositorio" | "repo" => Some(ResourceType::Repository),
+            "documentation" | "documentacion" | "docs" => Some(ResourceType::Documentation),
+            "course" | "curso" => Some(ResourceType::Course),
+            "podcast" => Some(ResourceType::Podcast),
+            "image" | "imagen" => Some(ResourceType::Image),
+            "pdf" => Some(ResourceType::Pdf),
+            "link" | "enlace" => Some(ResourceType::Link),
+            _ => None,
+        };
+    }
+
+    /// Guesses the type of `url` from its site and extension, plus the
+    /// `type` discord gave its embed (`video`, `image`, `article`...)
+    pub fn classify(url: &str, embed_kind: Option<&str>, has_video: bool) -> Self {
+        let host = url_host(url).unwrap_or_default();
+        let path = url
+            .split(|c| c == '?' || c == '#')
+            .next()
+            .unwrap_or("")
+            .to_lowercase();
+        let file = path.trim_end_matches('/').rsplit('/').next().unwrap_or("");
+        let extension = match file.rfind('.') {
+            Some(index) if !path.ends_with('/') => &file[index + 1..],
+            _ => "",
+        };
+        let on = |hosts: &[&str]| {
+            hosts
+                .iter()
+                .any(|site| host == *site || host.ends_with(&format!(".{}", site)))
+        };
+
+        if extension == "pdf" {
+            return ResourceType::Pdf;
+        }
+        if IMAGE_EXTENSIONS.contains(&extension) || embed_kind == Some("image") {
+            return ResourceType::Image;
+        }
+        if on(REPOSITORY_HOSTS) {
+            return ResourceType::Repository;
+        }
+        if on(COURSE_HOSTS) {
+            return ResourceType::Course;
+        }
+        if on(PODCAST_HOSTS)
+            || (host == "open.spotify.com"
+                && (path.contains("/episode/") || path.contains("/show/")))
+        {
+            return ResourceType::Podcast;
+        }
+        if on(VIDEO_HOSTS)
+            || VIDEO_EXTENSIONS.contains(&extension)
+            || has_video
+            || embed_kind == Some("video")
+            || embed_kind == Some("gifv")
+        {
+            return ResourceType::Video;
+        }
+        if on(DOCUMENTATION_HOSTS) || host.starts_with("docs.") || path.contains("/docs/") {
+            return ResourceType::Documentation;
+        }
+        if on(ARTICLE_HOSTS) || host.starts_with("blog.") || path.contains("/blog/") {
+            return ResourceType::Article;
+        }
+        if embed_kind == Some("article") {
+            return ResourceType::Article;
+        }
+        return ResourceType::Link;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn classify(url: &str) -> ResourceType {
+        return ResourceType::classify(url, None, false);
+    }
+
+    #[test]
+    fn reads_names_in_both_languages_and_ids() {
+        assert_eq!(
+            ResourceType::from_name("Repo"),
+            Some(ResourceType::Repository)
+        );
+        assert_eq!(ResourceType::from_name("curso"), Some(ResourceType::Course));
+        assert_eq!(ResourceType::from_name("8"), Some(ResourceType::Pdf));
+        assert_eq!(ResourceType::from_name("9"), None);
+        assert_eq!(ResourceType::from_name("book"), None);
+
+        for kind in RESOURCE_TYPES {
+            assert_eq!(ResourceType::from_name(kind.name()), Some(*kind));
+            assert_eq!(ResourceType::from_id(kind.id()), Some(*kind));
+        }
+    }
+
+    #[test]
+    fn classifies_by_site() {
+        assert_eq!(
+            classify("https://www.youtube.com/watch?v=1"),
+            ResourceType::Video
+        );
+        assert_eq!(
+            classify("https://github.com/rust-lang/rust"),
+            ResourceType::Repository
+        );
+        assert_eq!(
+            classify("https://docs.rs/serde"),
+            ResourceType::Documentation
+        );
+        assert_eq!(
+            classify("https://tokio.readthedocs.io/"),
+            ResourceType::Documentation
+        );
+        assert_eq!(
+            classify("https://www.udemy.com/course/rust"),
+            ResourceType::Course
+        );
+        assert_eq!(
+            classify("https://open.spotify.com/episode/1"),
+            ResourceType::Podcast
+        );
+        assert_eq!(
+            classify("https://open.spotify.com/track/1"),
+            ResourceType::Link
+        );
+        assert_eq!(
+            classify("https://blog.example.com/post"),
+            ResourceType::Article
+        );
+        assert_eq!(classify("https://example.com/"), ResourceType::Link);
+        // Only the site itself and its subdomains
+        assert_eq!(classify("https://notgithub.com/a"), ResourceType::Link);
+    }
+
+    #[test]
+    fn extensions_come_before_the_site() {
+        assert_eq!(
+            classify("https://github.com/a/b/raw/main/book.pdf"),
+            ResourceType::Pdf
+        );
+        assert_eq!(
+            classify("https://example.com/logo.PNG?size=2"),
+            ResourceType::Image
+        );
+        assert_eq!(
+            classify("https://example.com/clip.mp4"),
+            ResourceType::Video
+        );
+        assert_eq!(classify("https://example.com/v1.2/"), ResourceType::Link);
+    }
+
+    #[test]
+    fn falls_back_to_the_embed() {
+        let url = "https://example.com/page";
+        assert_eq!(
+            ResourceType::classify(url, Some("image"), false),
+            ResourceType::Image
+        );
+        assert_eq!(
+            ResourceType::classify(url, Some("gifv"), false),
+            ResourceType::Video
+        );
+        assert_eq!(
+            ResourceType::classify(url, Some("link"), true),
+            ResourceType::Video
+        );
+        assert_eq!(
+            ResourceType::classify(url, Some("article"), false),
+            ResourceType::Article
+        );
+        assert_eq!(
+            ResourceType::classify("https://docs.rs/a", Some("article"), false),
+            ResourceType::Documentation
+        );
+    }
+}
@@ -0,0 +1,286 @@
+use super::bot_state::BotState;
+use super::commands::cmd_rate::RATING_EMOJIS;
+use super::commands::cmd_search_resource::{resource_embed, resource_link};
+use super::commands::response::ResponseEmbed;
+use super::guild_settings::GuildSettings;
+use super::i18n::{tr, tr_args, DEFAULT_LOCALE};
+use super::resource::Resource;
+use super::resource_type::ResourceType;
+use super::search_query::SearchQuery;
+use chrono::{DateTime, Datelike, Timelike, Utc};
+use discord::model::{ChannelId, ReactionEmoji};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// How often the scheduled posts are checked
+pub const TICK: Duration = Duration::from_secs(60);
+/// Days a digest covers at most
+pub const DIGEST_DAYS: i64 = 7;
+/// Resources a digest looks at
+const DIGEST_LIMIT: u16 = 200;
+/// Tags, resources per tag and contributors listed in a digest
+const DIGEST_TAGS: usize = 5;
+const DIGEST_TAG_RESOURCES: usize = 3;
+const DIGEST_CONTRIBUTORS: usize = 3;
+
+/// Posts whatever is due in every server the bot is in
+pub fn tick(state: &mut BotState) {
+    let guild_ids: Vec<String> = state
+        .cache
+        .read()
+        .unwrap()
+        .servers()
+        .iter()
+        .map(|server| server.id.0.to_string())
+        .collect();
+    let now = Utc::now();
+
+    for guild_id in guild_ids {
+        post_daily_pick(state, &guild_id, now);
+        post_digest(state, &guild_id, now);
+    }
+}
+
+/// The resource of the day, once a day from the configured hour. Resources
+/// picked within the window aren't picked again unless there's nothing else.
+fn post_daily_pick(state: &mut BotState, guild_id: &str, now: DateTime<Utc>) {
+    let settings = state.settings_of(guild_id);
+    let channel_id = match guild_channel(state, guild_id, &settings.daily_channel) {
+        Some(channel_id) => channel_id,
+        None => return,
+    };
+    if (now.hour() as i32) < settings.daily_hour {
+        return;
+    }
+
+    let since = now - chrono::Duration::days(settings.daily_window.max(1) as i64);
+    let picks = state.db.daily_picks(guild_id, since);
+    if let Some((_, picked_at)) = picks.first() {
+        if picked_at.date_naive() == now.date_naive() {
+            return;
+        }
+    }
+
+    let query = SearchQuery::default().scoped_to(guild_id);
+    let excluded: Vec<i64> = picks.iter().map(|(resource_id, _)| *resource_id).collect();
+    let mut resources = state.db.select_random_resource(&query, &excluded);
+    if resources.is_empty() {
+        resources = state.db.select_random_resource(&query, &[]);
+    }
+    let resource = match resources.first() {
+        Some(resource) => resource.clone(),
+        None => return,
+    };
+
+    // Saved first, a failing channel shouldn't be retried every minute
+    if !state.db.save_daily_pick(guild_id, resource.id) {
+        println!("Scheduler: daily pick of {} could not be saved", guild_id);
+        return;
+    }
+
+    let locale = guild_locale(&settings);
+    let embed = resource_embed(state, &resource, &locale);
+    let content = tr(&locale, "daily.title");
+    let sent = match state
+        .discord
+        .send_embed(channel_id, &content, |builder| embed.build(builder))
+    {
+        Ok(sent) => sent,
+        Err(err) => {
+            println!("Scheduler: daily pick of {} not sent: {:?}", guild_id, err);
+            return;
+        }
+    };
+
+    // Members vote by pressing one
+    for emoji in RATING_EMOJIS {
+        let _ = state.discord.add_reaction(
+            sent.channel_id,
+            sent.id,
+            ReactionEmoji::Unicode(emoji.to_string()),
+        );
+    }
+}
+
+/// The weekly digest, once on the configured weekday from the configured hour
+fn post_digest(state: &mut BotState, guild_id: &str, now: DateTime<Utc>) {
+    let settings = state.settings_of(guild_id);
+    let channel_id = match guild_channel(state, guild_id, &settings.digest_channel) {
+        Some(channel_id) => channel_id,
+        None => return,
+    };
+    if now.weekday().number_from_monday() as i32 != settings.digest_day
+        || (now.hour() as i32) < settings.digest_hour
+    {
+        return;
+    }
+    if let Some(last) = state.db.last_digest(guild_id) {
+        if last.date_naive() == now.date_naive() {
+            return;
+        }
+    }
+
+    let since = digest_start(state, guild_id, now);
+    if !state.db.save_digest(guild_id) {
+        println!("Scheduler: digest of {} could not be saved", guild_id);
+        return;
+    }
+
+    // A quiet week gets no digest
+    let locale = guild_locale(&settings);
+    let embed = match digest_embed(state, guild_id, since, &locale) {
+        Some(embed) => embed,
+        None => return,
+    };
+    if let Err(err) = state
+        .discord
+        .send_embed(channel_id, "", |builder| embed.build(builder))
+    {
+        println!("Scheduler: digest of {} not sent: {:?}", guild_id, err);
+    }
+}
+
+/// `channel_id` when it is a channel of `guild_id`, so settings saved
+/// with a channel of another server never post there
+fn guild_channel(state: &BotState, guild_id: &str, channel_id: &str) -> Option<ChannelId> {
+    let channel_id = ChannelId(channel_id.parse::<u64>().ok()?);
+    return match state.guild_of(channel_id) {
+        Some(owner) if owner.0.to_string() == guild_id => Some(channel_id),
+        _ => None,
+    };
+}
+
+/// Where the next digest of `guild_id` starts: the last one, or a week ago
+/// if it was longer ago
+pub fn digest_start(state: &mut BotState, guild_id: &str, now: DateTime<Utc>) -> DateTime<Utc> {
+    let week_ago = now - chrono::Duration::days(DIGEST_DAYS);
+    return match state.db.last_digest(guild_id) {
+        Some(last) if last > week_ago => last,
+        _ => week_ago,
+    };
+}
+
+/// Resources of `guild_id` saved since `since`, grouped by tag and by type,
+/// and who saved the most. `None` when nothing was saved.
+pub fn digest_embed(
+    state: &mut BotState,
+    guild_id: &str,
+    since: DateTime<Utc>,
+    locale: &str,
+) -> Option<ResponseEmbed> {
+    let resources = state
+        .db
+        .select_resources_since(guild_id, since, DIGEST_LIMIT);
+    if resources.is_empty() {
+        return None;
+    }
+
+    let mut embed = ResponseEmbed::new(&tr(locale, "digest.title"));
+    embed.description = tr_args(
+        locale,
+        "digest.summary",
+        &[
+            ("count", &resources.len().to_string()),
+            ("since", &since.format("%Y-%m-%d").to_string()),
+        ],
+    );
+    embed.timestamp = Some(Utc::now());
+
+    // Most used tags first, each with its best voted resources
+    let tags = grouped(&resources, |resource| resource.tags.clone());
+    for (tag, tagged) in tags.iter().take(DIGEST_TAGS) {
+        let mut tagged = tagged.clone();
+        tagged.sort_by(|a, b| {
+            b.weight()
+                .partial_cmp(&a.weight())
+                .unwrap_or(Ordering::Equal)
+        });
+        let mut lines: Vec<String> = tagged
+            .iter()
+            .take(DIGEST_TAG_RESOURCES)
+            .map(|resource| resource_link(resource))
+            .collect();
+        if tagged.len() > DIGEST_TAG_RESOURCES {
+            let more = (tagged.len() - DIGEST_TAG_RESOURCES).to_string();
+            lines.push(tr_args(locale, "digest.more", &[("count", &more)]));
+        }
+        embed.field(
+            &format!("#{} ({})", tag, tagged.len()),
+            &lines.join("\n"),
+            false,
+        );
+    }
+
+    let types: Vec<String> = grouped(&resources, |resource| {
+        let kind = ResourceType::from_id(resource.type_id).unwrap_or(ResourceType::Link);
+        vec![kind.name().to_string()]
+    })
+    .iter()
+    .map(|(kind, of_kind)| format!("{} {}", kind, of_kind.len()))
+    .collect();
+    embed.field(&tr(locale, "digest.types"), &types.join(" · "), false);
+
+    let contributors: Vec<String> = grouped(&resources, |resource| vec![resource.user_id.clone()])
+        .iter()
+        .take(DIGEST_CONTRIBUTORS)
+        .map(|(user_id, saved)| format!("<@{}> {}", user_id, saved.len()))
+        .collect();
+    embed.field(
+        &tr(locale, "digest.contributors"),
+        &contributors.join("\n"),
+        false,
+    );
+
+    return Some(embed);
+}
+
+/// `resources` by the keys `keys_of` gives each one, biggest groups first
+fn grouped<'a, F>(resources: &'a [Resource], keys_of: F) -> Vec<(String, Vec<&'a Resource>)>
+where
+    F: Fn(&Resource) -> Vec<String>,
+{
+    let mut groups: HashMap<String, Vec<&Resource>> = HashMap::new();
+    for resource in resources {
+        for key in keys_of(resource) {
+            groups.entry(key).or_insert_with(Vec::new).push(resource);
+        }
+    }
+
+    let mut grouped: Vec<(String, Vec<&Resource>)> = groups.into_iter().collect();
+    grouped.sort_by(|a, b| b.1.len().cmp(&a.1.len()).then(a.0.cmp(&b.0)));
+    return grouped;
+}
+
+/// Locale scheduled posts are written in, nobody asked for them
+fn guild_locale(settings: &GuildSettings) -> String {
+    return match settings.locale.as_str() {
+        "" => DEFAULT_LOCALE.to_string(),
+        locale => locale.to_string(),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::testing::*;
+    use super::*;
+
+    #[test]
+    fn only_posts_in_channels_of_the_server() {
+        let state = bot();
+        let guild_id = GUILD_ID.to_string();
+        assert_eq!(
+            guild_channel(&state, &guild_id, &CHANNEL_ID.to_string()),
+            Some(ChannelId(CHANNEL_ID))
+        );
+        assert_eq!(
+            guild_channel(&state, &guild_id, &FOREIGN_CHANNEL_ID.to_string()),
+            None
+        );
+        assert_eq!(
+            guild_channel(&state, &guild_id, &PRIVATE_CHANNEL_ID.to_string()),
+            None
+        );
+        assert_eq!(guild_channel(&state, &guild_id, ""), None);
+    }
+}
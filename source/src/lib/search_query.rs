@@ -0,0 +1,524 @@
+use chrono::NaiveDate;
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use url::Url;
+
+use super::i18n::{tr, tr_args};
+use super::resource::Resource;
+use super::resource_type::ResourceType;
+use super::tags::normalize;
+
+/// Which resources a query can see
+#[derive(Debug, Clone, PartialEq)]
+pub enum Scope {
+    /// Only guilds that opted into the global index
+    Public,
+    /// Only the given guild
+    Guild(String),
+}
+
+impl Default for Scope {
+    fn default() -> Self {
+        return Scope::Public;
+    }
+}
+
+/// A parsed `!search` query: free text plus optional field filters, e.g.
+/// `rust async #wasm author:@ana domain:youtube.com after:2024-01-01 type:video`
+#[derive(Debug, Default, Clone)]
+pub struct SearchQuery {
+    /// Free text in `websearch_to_tsquery` syntax
+    pub text: String,
+    pub terms: Vec<Term>,
+    pub scope: Scope,
+    /// `in:global` was asked for, the query ignores the current guild
+    pub global: bool,
+    pub user_id: Option<String>,
+    pub channel_id: Option<String>,
+    pub domain: Option<String>,
+    pub type_id: Option<i32>,
+    /// Every one of them must be on the resource, `tag:rust` or `#rust`
+    pub tags: Vec<String>,
+    /// Saved on or after this day
+    pub after: Option<NaiveDate>,
+    /// Saved before this day
+    pub before: Option<NaiveDate>,
+}
+
+#[allow(dead_code)]
+impl SearchQuery {
+    /// A query made only of free text, no filters are read
+    pub fn text(text: &str) -> Self {
+        return Self {
+            text: text.trim().to_string(),
+            terms: split_terms(text),
+            ..Default::default()
+        };
+    }
+
+    /// Restricts the query to `guild_id` unless `in:global` was asked for
+    pub fn scoped_to(mut self, guild_id: &str) -> Self {
+        if !self.global {
+            self.scope = Scope::Guild(guild_id.to_string());
+        }
+        return self;
+    }
+
+    /// Error messages are written in `locale`
+    pub fn parse(input: &str, locale: &str) -> Result<Self, String> {
+        let mut query = SearchQuery::default();
+        let mut text: Vec<String> = Vec::new();
+
+        for token in tokenize(input, locale)? {
+            let (key, value) = match token.find(':') {
+                Some(index) if !token.starts_with('"') => (&token[..index], &token[index + 1..]),
+                _ => ("", token.as_str()),
+            };
+            let key = key.to_lowercase();
+
+            match key.as_str() {
+                "author" | "autor" | "user" => {
+                    query.user_id = Some(parse_mention(value, "<@", "author", locale)?);
+                }
+                "channel" | "canal" => {
+                    query.channel_id = Some(parse_mention(value, "<#", "channel", locale)?);
+                }
+                "domain" | "dominio" | "site" => {
+                    query.domain = Some(parse_domain(value, locale)?);
+                }
+                "type" | "tipo" => {
+                    let kind = ResourceType::from_name(value)
+                        .ok_or_else(|| tr_args(locale, "query.bad_type", &[("value", value)]))?;
+                    query.type_id = Some(kind.id());
+                }
+                "tag" | "etiqueta" => {
+                    let tag = normalize(value)
+                        .ok_or_else(|| tr_args(locale, "query.bad_tag", &[("value", value)]))?;
+                    query.tags.push(tag);
+                }
+                "" if token.starts_with('#') => {
+                    if let Some(tag) = normalize(&token) {
+                        query.tags.push(tag);
+                    }
+                }
+                "in" | "en" => {
+                    if value.to_lowercase() != "global" {
+                        return Err(tr_args(locale, "query.bad_scope", &[("value", value)]));
+                    }
+                    query.global = true;
+                }
+                "after" | "desde" => query.after = Some(parse_date(value, "after", locale)?),
+                "before" | "hasta" => query.before = Some(parse_date(value, "before", locale)?),
+                // Not a filter, `https://...` and friends are plain text
+                _ => text.push(token.clone()),
+            }
+        }
+
+        if let (Some(after), Some(before)) = (query.after, query.before) {
+            if after >= before {
+                return Err(tr_args(
+                    locale,
+                    "query.bad_range",
+                    &[
+                        ("after", &after.to_string()),
+                        ("before", &before.to_string()),
+                    ],
+                ));
+            }
+        }
+
+        query.text = text.join(" ");
+        query.terms = split_terms(&query.text);
+
+        if query.is_empty() {
+            return Err(tr(locale, "query.empty"));
+        }
+        return Ok(query);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.terms.is_empty()
+            && !self.global
+            && self.user_id.is_none()
+            && self.channel_id.is_none()
+            && self.domain.is_none()
+            && self.type_id.is_none()
+            && self.tags.is_empty()
+            && self.after.is_none()
+            && self.before.is_none();
+    }
+
+    /// Filters check for backends that can't express them in SQL,
+    /// the scope is left to the caller
+    pub fn matches_filters(&self, resource: &Resource) -> bool {
+        if let Some(user_id) = &self.user_id {
+            if &resource.user_id != user_id {
+                return false;
+            }
+        }
+        if let Some(channel_id) = &self.channel_id {
+            if &resource.channel_id != channel_id {
+                return false;
+            }
+        }
+        if let Some(domain) = &self.domain {
+            match url_host(&resource.url) {
+                Some(host) if host == *domain || host.ends_with(&format!(".{}", domain)) => {}
+                _ => return false,
+            }
+        }
+        if let Some(type_id) = self.type_id {
+            if resource.type_id != type_id {
+                return false;
+            }
+        }
+        if !self.tags.iter().all(|tag| resource.tags.contains(tag)) {
+            return false;
+        }
+        let saved = resource.created_at.map(|date| date.naive_utc().date());
+        if let Some(after) = self.after {
+            if saved.map_or(true, |saved| saved < after) {
+                return false;
+            }
+        }
+        if let Some(before) = self.before {
+            if saved.map_or(true, |saved| saved >= before) {
+                return false;
+            }
+        }
+        return true;
+    }
+}
+
+/// Lowercased host of `url` without the `www.`
+pub fn url_host(url: &str) -> Option<String> {
+    let url = Url::parse(url).ok()?;
+    let host = url.host_str()?.to_lowercase();
+    return Some(host.trim_start_matches("www.").to_string());
+}
+
+/// Splits on whitespace keeping `"quoted text"` (and `key:"quoted text"`) together
+fn tokenize(input: &str, locale: &str) -> Result<Vec<String>, String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+
+    for character in input.chars() {
+        if character == '"' {
+            quoted = !quoted;
+            current.push(character);
+        } else if character.is_whitespace() && !quoted {
+            if !current.is_empty() {
+                tokens.push(current.clone());
+                current.clear();
+            }
+        } else {
+            current.push(character);
+        }
+    }
+
+    if quoted {
+        return Err(tr(locale, "query.unclosed_quotes"));
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+    return Ok(tokens);
+}
+
+/// Accepts discord mentions (`<@123>`, `<@!123>`, `<#123>`) or raw ids
+fn parse_mention(value: &str, mention: &str, filter: &str, locale: &str) -> Result<String, String> {
+    let id = value
+        .trim_start_matches(mention)
+        .trim_start_matches('!')
+        .trim_end_matches('>');
+
+    if id.is_empty() || !id.chars().all(|character| character.is_ascii_digit()) {
+        return Err(tr_args(
+            locale,
+            "query.bad_mention",
+            &[("filter", filter), ("value", value)],
+        ));
+    }
+    return Ok(id.to_string());
+}
+
+fn parse_domain(value: &str, locale: &str) -> Result<String, String> {
+    let value = value.trim_matches('"').to_lowercase();
+    let domain = value
+        .trim_start_matches("https://")
+        .trim_start_matches("http://")
+        .trim_start_matches("www.")
+        .trim_end_matches('/');
+
+    if domain.is_empty() || !domain.contains('.') || domain.contains('/') {
+        return Err(tr_args(locale, "query.bad_domain", &[("value", &value)]));
+    }
+    return Ok(domain.to_string());
+}
+
+fn parse_date(value: &str, filter: &str, locale: &str) -> Result<NaiveDate, String> {
+    return NaiveDate::parse_from_str(value, "%Y-%m-%d").map_err(|_| {
+        tr_args(
+            locale,
+            "query.bad_date",
+            &[("filter", filter), ("value", value)],
+        )
+    });
+}
+
+/// A single search term, quoted phrases are kept together
+#[derive(Debug, Clone, PartialEq)]
+pub struct Term {
+    pub text: String,
+    pub negated: bool,
+}
+
+/// Splits a query the same way Postgres `websearch_to_tsquery` reads it:
+/// words are AND'ed, `"quoted text"` is a phrase and `-word` excludes.
+pub fn split_terms(query: &str) -> Vec<Term> {
+    let mut terms = Vec::new();
+    let mut current = String::new();
+    let mut negated = false;
+    let mut quoted = false;
+
+    for character in query.to_lowercase().chars() {
+        if character == '"' {
+            if quoted || !current.is_empty() {
+                push_term(&mut terms, &mut current, &mut negated);
+            }
+            quoted = !quoted;
+        } else if character.is_whitespace() && !quoted {
+            push_term(&mut terms, &mut current, &mut negated);
+        } else if character == '-' && current.is_empty() && !quoted && !negated {
+            negated = true;
+        } else {
+            current.push(character);
+        }
+    }
+    push_term(&mut terms, &mut current, &mut negated);
+
+    return terms;
+}
+
+fn push_term(terms: &mut Vec<Term>, current: &mut String, negated: &mut bool) {
+    let text = current.trim();
+    if !text.is_empty() {
+        terms.push(Term {
+            text: text.to_string(),
+            negated: *negated,
+        });
+    }
+    current.clear();
+    *negated = false;
+}
+
+/// Every resource ranks as if it had `PRIOR_VOTES` votes of `PRIOR_RATING`
+/// stars besides its own, so a single vote doesn't outrank many
+pub const PRIOR_VOTES: f64 = 2.0;
+pub const PRIOR_RATING: f64 = 3.0;
+
+/// Average of the votes and the prior, from 1 to 5 and 3 without votes.
+/// Relevance is multiplied by it, backends without relevance sort by it.
+pub fn rating_weight(rating: f64, votes: i64) -> f64 {
+    let votes = votes.max(0) as f64;
+    return (rating * votes + PRIOR_RATING * PRIOR_VOTES) / (votes + PRIOR_VOTES);
+}
+
+/// How many times the positive terms appear in `haystack`, `None` when
+/// a term is missing or an excluded one is present.
+pub fn score(terms: &[Term], haystack: &str) -> Option<usize> {
+    let haystack = haystack.to_lowercase();
+    let mut score = 0;
+
+    for term in terms {
+        let found = haystack.matches(term.text.as_str()).count();
+        if term.negated && found > 0 {
+            return None;
+        }
+        if !term.negated && found == 0 {
+            return None;
+        }
+        score += found;
+    }
+    return Some(score);
+}
+
+/// Orders `resources` like Postgres does: relevance times the votes, then
+/// the votes alone and then the newest. Backends without full-text search
+/// use it, resources missing a term or having an excluded one are left out.
+pub fn rank<R: Borrow<Resource>>(terms: &[Term], resources: Vec<R>) -> Vec<R> {
+    let mut scored: Vec<(f64, R)> = resources
+        .into_iter()
+        .filter_map(|resource| {
+            let item: &Resource = resource.borrow();
+            let found = score(terms, &haystack(item))?;
+            let weight = item.weight();
+            Some((found as f64 * weight, resource))
+        })
+        .collect();
+    scored.sort_by(|a, b| {
+        let (first, second): (&Resource, &Resource) = (a.1.borrow(), b.1.borrow());
+        b.0.partial_cmp(&a.0)
+            .unwrap_or(Ordering::Equal)
+            .then(
+                second
+                    .weight()
+                    .partial_cmp(&first.weight())
+                    .unwrap_or(Ordering::Equal),
+            )
+            .then(second.id.cmp(&first.id))
+    });
+
+    return scored.into_iter().map(|(_, resource)| resource).collect();
+}
+
+/// Text the terms of a query are looked for in
+fn haystack(resource: &Resource) -> String {
+    return format!(
+        "{} {} {} {} {}",
+        resource.title, resource.description, resource.provider, resource.author, resource.url
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{DateTime, Utc};
+
+    const LOCALE: &str = "en";
+
+    fn term(text: &str, negated: bool) -> Term {
+        return Term {
+            text: text.to_string(),
+            negated,
+        };
+    }
+
+    fn resource(id: i64, title: &str, rating: f64, votes: i64) -> Resource {
+        return Resource {
+            id,
+            title: title.to_string(),
+            url: format!("https://www.example.com/{}", id),
+            rating,
+            votes,
+            ..Default::default()
+        };
+    }
+
+    fn date(date: &str) -> DateTime<Utc> {
+        return date.parse().unwrap();
+    }
+
+    #[test]
+    fn reads_every_filter() {
+        let query = SearchQuery::parse(
+            "Rust async #WASM tag:web author:<@!42> channel:<#7> domain:https://www.YouTube.com/ \
+             type:repo after:2024-01-01 before:2024-02-01 in:global",
+            LOCALE,
+        )
+        .unwrap();
+
+        assert_eq!(query.text, "Rust async");
+        assert_eq!(query.terms, vec![term("rust", false), term("async", false)]);
+        assert_eq!(query.tags, vec!["wasm", "web"]);
+        assert_eq!(query.user_id.as_deref(), Some("42"));
+        assert_eq!(query.channel_id.as_deref(), Some("7"));
+        assert_eq!(query.domain.as_deref(), Some("youtube.com"));
+        assert_eq!(query.type_id, Some(ResourceType::Repository.id()));
+        assert_eq!(query.after, Some("2024-01-01".parse().unwrap()));
+        assert_eq!(query.before, Some("2024-02-01".parse().unwrap()));
+        assert!(query.global);
+        assert_eq!(query.clone().scoped_to("1").scope, Scope::Public);
+    }
+
+    #[test]
+    fn keeps_links_and_quotes_as_text() {
+        let query =
+            SearchQuery::parse("https://example.com/a \"async rust\" -java", LOCALE).unwrap();
+        assert_eq!(query.text, "https://example.com/a \"async rust\" -java");
+        assert_eq!(
+            query.terms,
+            vec![
+                term("https://example.com/a", false),
+                term("async rust", false),
+                term("java", true),
+            ]
+        );
+        assert_eq!(query.scoped_to("1").scope, Scope::Guild("1".to_string()));
+    }
+
+    #[test]
+    fn explains_bad_queries() {
+        let error = |input: &str| SearchQuery::parse(input, LOCALE).unwrap_err();
+
+        assert_eq!(error("  "), tr(LOCALE, "query.empty"));
+        assert_eq!(error("\"rust"), tr(LOCALE, "query.unclosed_quotes"));
+        assert!(error("type:nope").contains("nope"));
+        assert!(error("author:ana").contains("ana"));
+        assert!(error("domain:localhost").contains("localhost"));
+        assert!(error("in:guild").contains("guild"));
+        assert!(error("after:yesterday").contains("yesterday"));
+        assert!(error("after:2024-02-01 before:2024-01-01").contains("2024-02-01"));
+    }
+
+    #[test]
+    fn filters_what_sql_cant() {
+        let query = SearchQuery::parse(
+            "domain:youtube.com #rust after:2024-01-01 before:2024-02-01",
+            LOCALE,
+        )
+        .unwrap();
+        let mut found = Resource {
+            url: "https://m.youtube.com/watch?v=1".to_string(),
+            tags: vec!["rust".to_string(), "async".to_string()],
+            created_at: Some(date("2024-01-31T23:00:00Z")),
+            ..Default::default()
+        };
+        assert!(query.matches_filters(&found));
+
+        found.created_at = Some(date("2024-02-01T00:00:00Z"));
+        assert!(!query.matches_filters(&found));
+        found.created_at = None;
+        assert!(!query.matches_filters(&found));
+
+        let other_site = Resource {
+            url: "https://notyoutube.com/watch".to_string(),
+            ..found.clone()
+        };
+        assert!(!SearchQuery::parse("domain:youtube.com", LOCALE)
+            .unwrap()
+            .matches_filters(&other_site));
+    }
+
+    #[test]
+    fn scores_every_term() {
+        let terms = split_terms("rust -java");
+        assert_eq!(score(&terms, "Rust and more rust"), Some(2));
+        assert_eq!(score(&terms, "Rust and Java"), None);
+        assert_eq!(score(&terms, "Python"), None);
+    }
+
+    #[test]
+    fn ranks_by_relevance_times_votes() {
+        let ranked = rank(
+            &split_terms("rust"),
+            vec![
+                resource(1, "rust", 0.0, 0),
+                resource(2, "rust", 5.0, 10),
+                resource(3, "rust rust", 1.0, 10),
+                resource(4, "python", 5.0, 10),
+                resource(5, "rust", 0.0, 0),
+            ],
+        );
+        let ids: Vec<i64> = ranked.iter().map(|resource| resource.id).collect();
+        // Ties go to the newest, missing the term leaves it out
+        assert_eq!(ids, vec![2, 5, 1, 3]);
+    }
+
+    #[test]
+    fn weighs_votes_against_the_prior() {
+        assert_eq!(rating_weight(0.0, 0), PRIOR_RATING);
+        assert!(rating_weight(5.0, 1) < rating_weight(4.5, 20));
+    }
+}
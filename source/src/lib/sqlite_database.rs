@@ -0,0 +1,978 @@
+use chrono::{DateTime, Utc};
+use rusqlite::functions::FunctionFlags;
+use rusqlite::types::ToSql;
+use rusqlite::{params, params_from_iter, Connection, Params, Row};
+use std::time::Duration;
+
+use super::collection::{Collection, CollectionItem};
+use super::guild_settings::{GuildRole, GuildSettings};
+use super::migrations::Migration;
+use super::resource::Resource;
+use super::resource_store::ResourceStore;
+use super::search_query::{url_host, Scope, SearchQuery, PRIOR_RATING, PRIOR_VOTES};
+
+/// Every resource column plus its tag names separated by commas
+const RESOURCE_COLUMNS: &str = "*, (SELECT group_concat(t.name) FROM resource_tags rt \
+    JOIN tags t ON t.id = rt.tag_id WHERE rt.resource_id = resources.id) AS tags, \
+    COALESCE((SELECT avg(r.score) FROM ratings r WHERE r.resource_id = resources.id), 0.0) \
+    AS rating, (SELECT count(*) FROM ratings r WHERE r.resource_id = resources.id) AS votes";
+
+/// Text the terms of a search are looked for in, like `search_query::rank` does
+const SEARCH_TEXT: &str = "lower(title || ' ' || description || ' ' || provider || ' ' || \
+    author || ' ' || url)";
+
+/// SQLite backend for small deployments that don't want to run Postgres
+pub struct SqliteDatabase {
+    db: Connection,
+}
+
+#[allow(dead_code)]
+impl SqliteDatabase {
+    /// `database_uri` looks like `sqlite://pequenin.db`
+    pub fn new(database_uri: String) -> Self {
+        let path = database_uri.trim_start_matches("sqlite://");
+        let db = Connection::open(path).unwrap();
+
+        // Bot workers write through their own connections
+        db.busy_timeout(Duration::from_secs(5))
+            .expect("Connection error at busy_timeout");
+
+        // Used by the domain: search filter
+        db.create_scalar_function(
+            "url_host",
+            1,
+            FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+            |context| {
+                let url: String = context.get(0)?;
+                return Ok(url_host(&url));
+            },
+        )
+        .expect("Connection error at url_host");
+
+        return Self { db: db };
+    }
+
+    fn row_to_resource(row: &Row) -> rusqlite::Result<Resource> {
+        return Ok(Resource {
+            id: row.get("id")?,
+            guild_id: row.get("guild_id")?,
+            user_id: row.get("user_id")?,
+            channel_id: row.get("channel_id")?,
+            url: row.get("url")?,
+            title: row.get("title")?,
+            description: row.get("description")?,
+            provider: row.get("provider")?,
+            author: row.get("author")?,
+            thumbnail_url: row.get("thumbnail_url")?,
+            video_url: row.get("video_url")?,
+            shash: row.get("shash")?,
+            type_id: row.get("type_id")?,
+            created_at: row.get::<_, Option<DateTime<Utc>>>("created_at")?,
+            deleted_at: row.get::<_, Option<DateTime<Utc>>>("deleted_at")?,
+            deleted_by: row.get("deleted_by")?,
+            tags: split_tags(row.get::<_, Option<String>>("tags")?),
+            rating: row.get("rating")?,
+            votes: row.get("votes")?,
+        });
+    }
+
+    fn row_to_collection(row: &Row) -> rusqlite::Result<Collection> {
+        return Ok(Collection {
+            id: row.get("id")?,
+            guild_id: row.get("guild_id")?,
+            user_id: row.get("user_id")?,
+            name: row.get("name")?,
+            description: row.get("description")?,
+            created_at: row.get::<_, Option<DateTime<Utc>>>("created_at")?,
+            items: Vec::new(),
+        });
+    }
+
+    /// Collections of `query` with their items
+    fn query_collections<P: Params>(&mut self, query: &str, params: P) -> Vec<Collection> {
+        let mut collections: Vec<Collection> = match self.db.prepare(query) {
+            Ok(mut statement) => {
+                match statement.query_map(params, SqliteDatabase::row_to_collection) {
+                    Ok(rows) => rows.filter_map(|row| row.ok()).collect(),
+                    Err(_) => return Vec::new(),
+                }
+            }
+            Err(_) => return Vec::new(),
+        };
+        for collection in collections.iter_mut() {
+            collection.items = self.collection_items(collection.id);
+        }
+        return collections;
+    }
+
+    fn collection_items(&mut self, collection_id: i64) -> Vec<CollectionItem> {
+        let query = format!(
+            "SELECT {} FROM collection_items ci JOIN resources ON resources.id = ci.resource_id \
+            WHERE ci.collection_id = ?1 ORDER BY ci.position",
+            RESOURCE_COLUMNS
+        );
+
+        let mut statement = match self.db.prepare(query.as_str()) {
+            Ok(statement) => statement,
+            Err(_) => return Vec::new(),
+        };
+        let rows = match statement.query_map(params![collection_id], |row| {
+            Ok(CollectionItem {
+                resource: SqliteDatabase::row_to_resource(row)?,
+                note: row.get("note")?,
+            })
+        }) {
+            Ok(rows) => rows,
+            Err(_) => return Vec::new(),
+        };
+
+        return rows.filter_map(|row| row.ok()).collect();
+    }
+
+    /// `search_query::rating_weight` of each row, over the `rating` and
+    /// `votes` of `RESOURCE_COLUMNS`
+    fn rating_weight() -> String {
+        return format!(
+            "((rating * votes + {1:.1} * {0:.1}) / (votes + {1:.1}))",
+            PRIOR_RATING, PRIOR_VOTES
+        );
+    }
+
+    /// WHERE clause for `query`, every term must appear in the text of the
+    /// resource and `-term` must not. Terms are matched literally. Also the
+    /// expression ranking the matches: how many times the terms appear, like
+    /// `search_query::score`.
+    fn search_conditions(query: &SearchQuery) -> (String, String, Vec<Box<dyn ToSql>>) {
+        let mut conditions: Vec<String> = vec!["deleted_at IS NULL".to_string()];
+        let mut params: Vec<Box<dyn ToSql>> = Vec::new();
+        let mut found: Vec<String> = Vec::new();
+
+        match &query.scope {
+            Scope::Guild(guild_id) => {
+                params.push(Box::new(guild_id.clone()));
+                conditions.push(format!("guild_id = ?{}", params.len()));
+            }
+            Scope::Public => conditions.push(
+                "guild_id IN (SELECT guild_id FROM guild_settings WHERE share_global = 1)"
+                    .to_string(),
+            ),
+        }
+        for term in &query.terms {
+            params.push(Box::new(term.text.clone()));
+            let operator = if term.negated { "=" } else { ">" };
+            conditions.push(format!(
+                "instr({}, ?{}) {} 0",
+                SEARCH_TEXT,
+                params.len(),
+                operator
+            ));
+            if !term.negated {
+                found.push(format!(
+                    "(length({0}) - length(replace({0}, ?{1}, ''))) / length(?{1})",
+                    SEARCH_TEXT,
+                    params.len()
+                ));
+            }
+        }
+        if let Some(user_id) = &query.user_id {
+            params.push(Box::new(user_id.clone()));
+            conditions.push(format!("user_id = ?{}", params.len()));
+        }
+        if let Some(channel_id) = &query.channel_id {
+            params.push(Box::new(channel_id.clone()));
+            conditions.push(format!("channel_id = ?{}", params.len()));
+        }
+        if let Some(domain) = &query.domain {
+            params.push(Box::new(domain.clone()));
+            params.push(Box::new(format!("%.{}", escape_like(domain))));
+            conditions.push(format!(
+                "(url_host(url) = ?{} OR url_host(url) LIKE ?{} ESCAPE '\\')",
+                params.len() - 1,
+                params.len()
+            ));
+        }
+        for tag in &query.tags {
+            params.push(Box::new(tag.clone()));
+            conditions.push(format!(
+                "id IN (SELECT rt.resource_id FROM resource_tags rt \
+                JOIN tags t ON t.id = rt.tag_id WHERE t.name = ?{})",
+                params.len()
+            ));
+        }
+        if let Some(type_id) = query.type_id {
+            params.push(Box::new(type_id));
+            conditions.push(format!("type_id = ?{}", params.len()));
+        }
+        if let Some(after) = query.after {
+            params.push(Box::new(after.format("%Y-%m-%d").to_string()));
+            conditions.push(format!("created_at >= ?{}", params.len()));
+        }
+        if let Some(before) = query.before {
+            params.push(Box::new(before.format("%Y-%m-%d").to_string()));
+            conditions.push(format!("created_at < ?{}", params.len()));
+        }
+
+        let rank = match found.is_empty() {
+            true => "0".to_string(),
+            false => found.join(" + "),
+        };
+        return (conditions.join(" AND "), rank, params);
+    }
+
+    /// Resources matching `query`, `order` decides which and in what order.
+    /// It can use every resource column plus the rank as `relevance`.
+    fn query_resources(&mut self, query: &SearchQuery, order: &str) -> Vec<Resource> {
+        let (conditions, rank, params) = SqliteDatabase::search_conditions(query);
+        let query = format!(
+            "SELECT * FROM (SELECT {}, {} AS relevance FROM resources WHERE {}) {}",
+            RESOURCE_COLUMNS, rank, conditions, order
+        );
+
+        let mut statement = match self.db.prepare(query.as_str()) {
+            Ok(statement) => statement,
+            Err(err) => {
+                println!("Search error: {}", err);
+                return Vec::new();
+            }
+        };
+        let rows = match statement.query_map(
+            params_from_iter(params.iter()),
+            SqliteDatabase::row_to_resource,
+        ) {
+            Ok(rows) => rows,
+            Err(err) => {
+                println!("Search error: {}", err);
+                return Vec::new();
+            }
+        };
+
+        return rows.filter_map(|row| row.ok()).collect();
+    }
+}
+
+impl ResourceStore for SqliteDatabase {
+    fn insert_resource(&mut self, resource: Resource) -> bool {
+        if resource.url.is_empty() {
+            return false;
+        }
+
+        let query = "INSERT INTO resources(\
+            guild_id, user_id, channel_id, url, title, description, provider, \
+            author, thumbnail_url, video_url, type_id, shash, created_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, datetime('now'));";
+
+        let result = self.db.execute(
+            query,
+            params![
+                resource.guild_id,
+                resource.user_id,
+                resource.channel_id,
+                resource.url,
+                resource.title,
+                resource.description,
+                resource.provider,
+                resource.author,
+                resource.thumbnail_url,
+                resource.video_url,
+                resource.type_id,
+                resource.shash,
+            ],
+        );
+        match result {
+            Ok(_) => {
+                if !resource.tags.is_empty() {
+                    let id = self.db.last_insert_rowid();
+                    self.set_resource_tags(id, &resource.tags);
+                }
+                return true;
+            }
+            Err(_) => return false,
+        }
+    }
+
+    fn select_resources(&mut self, query: &SearchQuery, limit: u16, page: u16) -> Vec<Resource> {
+        // Relevance times the votes, the votes alone when there's no text,
+        // in the same order `search_query::rank` and Postgres give
+        let order = format!(
+            "ORDER BY relevance * {0} DESC, {0} DESC, id DESC LIMIT {1} OFFSET {2}",
+            SqliteDatabase::rating_weight(),
+            limit,
+            page as u32 * limit as u32
+        );
+
+        return self.query_resources(query, &order);
+    }
+
+    fn select_random_resource(&mut self, query: &SearchQuery, excluded: &[i64]) -> Vec<Resource> {
+        let order = format!("WHERE {} ORDER BY random() LIMIT 1", excluded_ids(excluded));
+
+        return self.query_resources(query, order.as_str());
+    }
+
+    fn select_resource_by_hash(&mut self, guild_id: &str, shash: &str) -> Option<Resource> {
+        let query = format!(
+            "SELECT {} FROM resources \
+            WHERE guild_id = ?1 AND shash = ?2 AND deleted_at IS NULL LIMIT 1",
+            RESOURCE_COLUMNS
+        );
+
+        return self
+            .db
+            .query_row(
+                query.as_str(),
+                params![guild_id, shash],
+                SqliteDatabase::row_to_resource,
+            )
+            .ok();
+    }
+
+    fn select_resource(&mut self, guild_id: &str, id: i64) -> Option<Resource> {
+        let query = format!(
+            "SELECT {} FROM resources WHERE guild_id = ?1 AND id = ?2",
+            RESOURCE_COLUMNS
+        );
+
+        return self
+            .db
+            .query_row(
+                query.as_str(),
+                params![guild_id, id],
+                SqliteDatabase::row_to_resource,
+            )
+            .ok();
+    }
+
+    fn update_resource(&mut self, resource: &Resource) -> bool {
+        let query = "UPDATE resources SET title = ?1, description = ?2, type_id = ?3 \
+            WHERE guild_id = ?4 AND id = ?5";
+
+        let result = self.db.execute(
+            query,
+            params![
+                resource.title,
+                resource.description,
+                resource.type_id,
+                resource.guild_id,
+                resource.id
+            ],
+        );
+        match result {
+            Ok(updated) => return updated == 1,
+            Err(_) => return false,
+        }
+    }
+
+    fn delete_resource(&mut self, guild_id: &str, id: i64, deleted_by: &str) -> bool {
+        let query = "UPDATE resources SET deleted_at = datetime('now'), deleted_by = ?1 \
+            WHERE guild_id = ?2 AND id = ?3 AND deleted_at IS NULL";
+
+        match self.db.execute(query, params![deleted_by, guild_id, id]) {
+            Ok(updated) => return updated == 1,
+            Err(_) => return false,
+        }
+    }
+
+    fn restore_resource(&mut self, guild_id: &str, id: i64) -> bool {
+        let query = "UPDATE resources SET deleted_at = NULL, deleted_by = '' \
+            WHERE guild_id = ?1 AND id = ?2 AND deleted_at IS NOT NULL";
+
+        match self.db.execute(query, params![guild_id, id]) {
+            Ok(updated) => return updated == 1,
+            Err(_) => return false,
+        }
+    }
+
+    fn select_deleted_resources(&mut self, guild_id: &str, limit: u16) -> Vec<Resource> {
+        let query = format!(
+            "SELECT {} FROM resources WHERE guild_id = ?1 AND deleted_at IS NOT NULL \
+            ORDER BY deleted_at DESC LIMIT ?2",
+            RESOURCE_COLUMNS
+        );
+
+        let mut statement = match self.db.prepare(query.as_str()) {
+            Ok(statement) => statement,
+            Err(_) => return Vec::new(),
+        };
+        let rows =
+            match statement.query_map(params![guild_id, limit], SqliteDatabase::row_to_resource) {
+                Ok(rows) => rows,
+                Err(_) => return Vec::new(),
+            };
+
+        return rows.filter_map(|row| row.ok()).collect();
+    }
+
+    fn set_resource_tags(&mut self, resource_id: i64, tags: &[String]) -> bool {
+        let transaction = match self.db.transaction() {
+            Ok(transaction) => transaction,
+            Err(_) => return false,
+        };
+
+        let mut result = transaction.execute(
+            "DELETE FROM resource_tags WHERE resource_id = ?1",
+            params![resource_id],
+        );
+        for tag in tags {
+            result = result.and_then(|_| {
+                transaction.execute(
+                    "INSERT OR IGNORE INTO tags (name) VALUES (?1)",
+                    params![tag],
+                )
+            });
+            result = result.and_then(|_| {
+                transaction.execute(
+                    "INSERT OR IGNORE INTO resource_tags (resource_id, tag_id) \
+                    SELECT ?1, id FROM tags WHERE name = ?2",
+                    params![resource_id, tag],
+                )
+            });
+        }
+
+        match result.and_then(|_| transaction.commit()) {
+            Ok(_) => return true,
+            Err(_) => return false,
+        }
+    }
+
+    fn popular_tags(&mut self, guild_id: &str, limit: u16) -> Vec<(String, i64)> {
+        let query = "SELECT t.name, count(*) AS uses FROM tags t \
+            JOIN resource_tags rt ON rt.tag_id = t.id \
+            JOIN resources r ON r.id = rt.resource_id \
+            WHERE r.guild_id = ?1 AND r.deleted_at IS NULL \
+            GROUP BY t.name ORDER BY uses DESC, t.name LIMIT ?2";
+
+        let mut statement = match self.db.prepare(query) {
+            Ok(statement) => statement,
+            Err(_) => return Vec::new(),
+        };
+        let rows = match statement.query_map(params![guild_id, limit], |row| {
+            Ok((row.get("name")?, row.get("uses")?))
+        }) {
+            Ok(rows) => rows,
+            Err(_) => return Vec::new(),
+        };
+
+        return rows.filter_map(|row| row.ok()).collect();
+    }
+
+    fn guild_settings(&mut self, guild_id: &str) -> GuildSettings {
+        let query = "SELECT * FROM guild_settings WHERE guild_id = ?1";
+
+        let settings = self.db.query_row(query, params![guild_id], |row| {
+            Ok(GuildSettings {
+                guild_id: row.get("guild_id")?,
+                share_global: row.get("share_global")?,
+                locale: row.get("locale")?,
+                prefix: row.get("prefix")?,
+                daily_channel: row.get("daily_channel")?,
+                daily_hour: row.get("daily_hour")?,
+                daily_window: row.get("daily_window")?,
+                digest_channel: row.get("digest_channel")?,
+                digest_day: row.get("digest_day")?,
+                digest_hour: row.get("digest_hour")?,
+                ..Default::default()
+            })
+        });
+        let mut settings = match settings {
+            Ok(settings) => settings,
+            Err(_) => return GuildSettings::new(guild_id),
+        };
+
+        let query = "SELECT channel_id FROM guild_channels WHERE guild_id = ?1";
+        if let Ok(mut statement) = self.db.prepare(query) {
+            if let Ok(rows) = statement.query_map(params![guild_id], |row| row.get(0)) {
+                settings.allowed_channels = rows.filter_map(|row| row.ok()).collect();
+            }
+        }
+
+        let query = "SELECT role_id, permission FROM guild_roles WHERE guild_id = ?1";
+        if let Ok(mut statement) = self.db.prepare(query) {
+            let rows = statement.query_map(params![guild_id], |row| {
+                Ok(GuildRole {
+                    role_id: row.get("role_id")?,
+                    permission: row.get("permission")?,
+                })
+            });
+            if let Ok(rows) = rows {
+                settings.roles = rows.filter_map(|row| row.ok()).collect();
+            }
+        }
+        return settings;
+    }
+
+    fn save_guild_settings(&mut self, settings: &GuildSettings) -> bool {
+        let transaction = match self.db.transaction() {
+            Ok(transaction) => transaction,
+            Err(_) => return false,
+        };
+
+        let query = "INSERT INTO guild_settings (guild_id, share_global, locale, prefix, \
+            daily_channel, daily_hour, daily_window, digest_channel, digest_day, digest_hour) \
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10) \
+            ON CONFLICT (guild_id) DO UPDATE SET \
+            share_global = excluded.share_global, locale = excluded.locale, \
+            prefix = excluded.prefix, daily_channel = excluded.daily_channel, \
+            daily_hour = excluded.daily_hour, daily_window = excluded.daily_window, \
+            digest_channel = excluded.digest_channel, digest_day = excluded.digest_day, \
+            digest_hour = excluded.digest_hour";
+        let mut result = transaction.execute(
+            query,
+            params![
+                settings.guild_id,
+                settings.share_global,
+                settings.locale,
+                settings.prefix,
+                settings.daily_channel,
+                settings.daily_hour,
+                settings.daily_window,
+                settings.digest_channel,
+                settings.digest_day,
+                settings.digest_hour
+            ],
+        );
+
+        // Lists are rewritten as a whole
+        result = result.and_then(|_| {
+            transaction.execute(
+                "DELETE FROM guild_channels WHERE guild_id = ?1",
+                params![settings.guild_id],
+            )
+        });
+        for channel_id in &settings.allowed_channels {
+            result = result.and_then(|_| {
+                transaction.execute(
+                    "INSERT INTO guild_channels (guild_id, channel_id) VALUES (?1, ?2)",
+                    params![settings.guild_id, channel_id],
+                )
+            });
+        }
+        result = result.and_then(|_| {
+            transaction.execute(
+                "DELETE FROM guild_roles WHERE guild_id = ?1",
+                params![settings.guild_id],
+            )
+        });
+        for role in &settings.roles {
+            result = result.and_then(|_| {
+                transaction.execute(
+                    "INSERT INTO guild_roles (guild_id, role_id, permission) VALUES (?1, ?2, ?3)",
+                    params![settings.guild_id, role.role_id, role.permission],
+                )
+            });
+        }
+
+        match result.and_then(|_| transaction.commit()) {
+            Ok(_) => return true,
+            Err(_) => return false,
+        }
+    }
+
+    fn daily_picks(&mut self, guild_id: &str, since: DateTime<Utc>) -> Vec<(i64, DateTime<Utc>)> {
+        let query = "SELECT resource_id, picked_at FROM daily_picks \
+            WHERE guild_id = ?1 AND picked_at >= ?2 ORDER BY picked_at DESC";
+        let since = since.format("%Y-%m-%d %H:%M:%S").to_string();
+
+        let mut statement = match self.db.prepare(query) {
+            Ok(statement) => statement,
+            Err(_) => return Vec::new(),
+        };
+        let rows = match statement.query_map(params![guild_id, since], |row| {
+            Ok((row.get("resource_id")?, row.get("picked_at")?))
+        }) {
+            Ok(rows) => rows,
+            Err(_) => return Vec::new(),
+        };
+
+        return rows.filter_map(|row| row.ok()).collect();
+    }
+
+    fn save_daily_pick(&mut self, guild_id: &str, resource_id: i64) -> bool {
+        let query = "INSERT INTO daily_picks (guild_id, resource_id) VALUES (?1, ?2)";
+
+        match self.db.execute(query, params![guild_id, resource_id]) {
+            Ok(_) => return true,
+            Err(_) => return false,
+        }
+    }
+
+    fn add_bookmark(&mut self, user_id: &str, resource_id: i64) -> bool {
+        let query = "INSERT OR IGNORE INTO bookmarks (user_id, resource_id) VALUES (?1, ?2)";
+
+        match self.db.execute(query, params![user_id, resource_id]) {
+            Ok(inserted) => return inserted == 1,
+            Err(_) => return false,
+        }
+    }
+
+    fn remove_bookmark(&mut self, user_id: &str, resource_id: i64) -> bool {
+        let query = "DELETE FROM bookmarks WHERE user_id = ?1 AND resource_id = ?2";
+
+        match self.db.execute(query, params![user_id, resource_id]) {
+            Ok(deleted) => return deleted == 1,
+            Err(_) => return false,
+        }
+    }
+
+    fn select_bookmarks(&mut self, guild_id: &str, user_id: &str) -> Vec<Resource> {
+        let query = format!(
+            "SELECT {} FROM resources WHERE guild_id = ?1 AND deleted_at IS NULL \
+            AND id IN (SELECT resource_id FROM bookmarks WHERE user_id = ?2) \
+            ORDER BY (SELECT b.created_at FROM bookmarks b \
+            WHERE b.resource_id = resources.id AND b.user_id = ?2) DESC",
+            RESOURCE_COLUMNS
+        );
+
+        let mut statement = match self.db.prepare(query.as_str()) {
+            Ok(statement) => statement,
+            Err(_) => return Vec::new(),
+        };
+        let rows = match statement
+            .query_map(params![guild_id, user_id], SqliteDatabase::row_to_resource)
+        {
+            Ok(rows) => rows,
+            Err(_) => return Vec::new(),
+        };
+
+        return rows.filter_map(|row| row.ok()).collect();
+    }
+
+    fn insert_collection(&mut self, collection: &Collection) -> Option<i64> {
+        let query = "INSERT OR IGNORE INTO collections (guild_id, user_id, name, description) \
+            VALUES (?1, ?2, ?3, ?4)";
+
+        return match self.db.execute(
+            query,
+            params![
+                collection.guild_id,
+                collection.user_id,
+                collection.name,
+                collection.description
+            ],
+        ) {
+            Ok(1) => Some(self.db.last_insert_rowid()),
+            _ => None,
+        };
+    }
+
+    fn select_collection(&mut self, guild_id: &str, id: i64) -> Option<Collection> {
+        return self
+            .query_collections(
+                "SELECT * FROM collections WHERE guild_id = ?1 AND id = ?2",
+                params![guild_id, id],
+            )
+            .pop();
+    }
+
+    fn select_collections(&mut self, guild_id: &str, user_id: &str) -> Vec<Collection> {
+        return self.query_collections(
+            "SELECT * FROM collections WHERE guild_id = ?1 AND user_id = ?2 ORDER BY name",
+            params![guild_id, user_id],
+        );
+    }
+
+    fn update_collection(&mut self, collection: &Collection) -> bool {
+        let transaction = match self.db.transaction() {
+            Ok(transaction) => transaction,
+            Err(_) => return false,
+        };
+
+        let mut result = transaction.execute(
+            "UPDATE collections SET description = ?1 WHERE id = ?2",
+            params![collection.description, collection.id],
+        );
+        result = result.and_then(|_| {
+            transaction.execute(
+                "DELETE FROM collection_items WHERE collection_id = ?1",
+                params![collection.id],
+            )
+        });
+        for (position, item) in collection.items.iter().enumerate() {
+            result = result.and_then(|_| {
+                transaction.execute(
+                    "INSERT INTO collection_items (collection_id, resource_id, position, note) \
+                    VALUES (?1, ?2, ?3, ?4)",
+                    params![collection.id, item.resource.id, position as i64, item.note],
+                )
+            });
+        }
+
+        match result.and_then(|_| transaction.commit()) {
+            Ok(_) => return true,
+            Err(_) => return false,
+        }
+    }
+
+    fn delete_collection(&mut self, guild_id: &str, id: i64) -> bool {
+        let transaction = match self.db.transaction() {
+            Ok(transaction) => transaction,
+            Err(_) => return false,
+        };
+
+        // Foreign keys aren't enforced, the items go by hand
+        let mut result = transaction.execute(
+            "DELETE FROM collection_items WHERE collection_id IN \
+            (SELECT id FROM collections WHERE guild_id = ?1 AND id = ?2)",
+            params![guild_id, id],
+        );
+        result = result.and_then(|_| {
+            transaction.execute(
+                "DELETE FROM collections WHERE guild_id = ?1 AND id = ?2",
+                params![guild_id, id],
+            )
+        });
+
+        match result.and_then(|deleted| transaction.commit().map(|_| deleted)) {
+            Ok(deleted) => return deleted == 1,
+            Err(_) => return false,
+        }
+    }
+
+    fn select_resources_since(
+        &mut self,
+        guild_id: &str,
+        since: DateTime<Utc>,
+        limit: u16,
+    ) -> Vec<Resource> {
+        let query = format!(
+            "SELECT {} FROM resources WHERE guild_id = ?1 AND deleted_at IS NULL \
+            AND created_at >= ?2 ORDER BY created_at DESC, id DESC LIMIT ?3",
+            RESOURCE_COLUMNS
+        );
+        let since = since.format("%Y-%m-%d %H:%M:%S").to_string();
+
+        let mut statement = match self.db.prepare(query.as_str()) {
+            Ok(statement) => statement,
+            Err(_) => return Vec::new(),
+        };
+        let rows = match statement.query_map(
+            params![guild_id, since, limit],
+            SqliteDatabase::row_to_resource,
+        ) {
+            Ok(rows) => rows,
+            Err(_) => return Vec::new(),
+        };
+
+        return rows.filter_map(|row| row.ok()).collect();
+    }
+
+    fn rate_resource(&mut self, resource_id: i64, user_id: &str, score: i32) -> bool {
+        let query = "INSERT INTO ratings (resource_id, user_id, score) VALUES (?1, ?2, ?3) \
+            ON CONFLICT (resource_id, user_id) DO UPDATE SET \
+            score = excluded.score, rated_at = datetime('now')";
+
+        match self.db.execute(query, params![resource_id, user_id, score]) {
+            Ok(_) => return true,
+            Err(_) => return false,
+        }
+    }
+
+    fn unrate_resource(&mut self, resource_id: i64, user_id: &str, score: i32) -> bool {
+        let query = "DELETE FROM ratings WHERE resource_id = ?1 AND user_id = ?2 AND score = ?3";
+
+        match self.db.execute(query, params![resource_id, user_id, score]) {
+            Ok(deleted) => return deleted == 1,
+            Err(_) => return false,
+        }
+    }
+
+    fn last_digest(&mut self, guild_id: &str) -> Option<DateTime<Utc>> {
+        let query = "SELECT max(posted_at) FROM digests WHERE guild_id = ?1";
+
+        return self
+            .db
+            .query_row(query, params![guild_id], |row| row.get(0))
+            .unwrap_or(None);
+    }
+
+    fn save_digest(&mut self, guild_id: &str) -> bool {
+        let query = "INSERT INTO digests (guild_id) VALUES (?1)";
+
+        match self.db.execute(query, params![guild_id]) {
+            Ok(_) => return true,
+            Err(_) => return false,
+        }
+    }
+
+    fn user_locale(&mut self, user_id: &str) -> Option<String> {
+        let query = "SELECT locale FROM user_settings WHERE user_id = ?1 AND locale <> ''";
+
+        return self
+            .db
+            .query_row(query, params![user_id], |row| row.get(0))
+            .ok();
+    }
+
+    fn save_user_locale(&mut self, user_id: &str, locale: &str) -> bool {
+        let query = "INSERT INTO user_settings (user_id, locale) VALUES (?1, ?2) \
+            ON CONFLICT (user_id) DO UPDATE SET locale = excluded.locale";
+
+        match self.db.execute(query, params![user_id, locale]) {
+            Ok(_) => return true,
+            Err(_) => return false,
+        }
+    }
+
+    fn applied_migrations(&mut self) -> Result<Vec<i64>, String> {
+        self.db
+            .execute_batch(
+                "CREATE TABLE IF NOT EXISTS schema_migrations
+                (
+                  version INTEGER PRIMARY KEY,
+                  name TEXT NOT NULL,
+                  applied_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+                );",
+            )
+            .map_err(|err| err.to_string())?;
+
+        let mut statement = self
+            .db
+            .prepare("SELECT version FROM schema_migrations ORDER BY version")
+            .map_err(|err| err.to_string())?;
+        let rows = statement
+            .query_map(params![], |row| row.get(0))
+            .map_err(|err| err.to_string())?;
+
+        return rows
+            .collect::<rusqlite::Result<Vec<i64>>>()
+            .map_err(|err| err.to_string());
+    }
+
+    fn apply_migration(&mut self, migration: &Migration) -> Result<(), String> {
+        let transaction = self.db.transaction().map_err(|err| err.to_string())?;
+
+        transaction
+            .execute_batch(migration.sqlite)
+            .map_err(|err| err.to_string())?;
+        transaction
+            .execute(
+                "INSERT INTO schema_migrations (version, name) VALUES (?1, ?2)",
+                params![migration.version, migration.name],
+            )
+            .map_err(|err| err.to_string())?;
+
+        return transaction.commit().map_err(|err| err.to_string());
+    }
+
+    fn assign_legacy_resources(&mut self, guild_id: &str) -> Result<u64, String> {
+        return self
+            .db
+            .execute(
+                "UPDATE resources SET guild_id = ?1 WHERE guild_id = ''",
+                params![guild_id],
+            )
+            .map(|assigned| assigned as u64)
+            .map_err(|err| err.to_string());
+    }
+}
+
+/// `text` matched literally by `LIKE ... ESCAPE '\'`
+fn escape_like(text: &str) -> String {
+    return text
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+}
+
+/// `id NOT IN (...)` for `excluded`, ids are numbers so they are safe
+/// to write in the query
+fn excluded_ids(excluded: &[i64]) -> String {
+    let ids: Vec<String> = excluded.iter().map(|id| id.to_string()).collect();
+    return format!("id NOT IN ({})", ids.join(", "));
+}
+
+/// Tag names of a `group_concat`, sorted like the Postgres backend does
+fn split_tags(tags: Option<String>) -> Vec<String> {
+    let mut tags: Vec<String> = match tags {
+        Some(tags) => tags.split(',').map(|tag| tag.to_string()).collect(),
+        None => Vec::new(),
+    };
+    tags.sort();
+    return tags;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::migrations;
+    use super::*;
+
+    fn database() -> SqliteDatabase {
+        let mut db = SqliteDatabase::new("sqlite://:memory:".to_string());
+        migrations::migrate(&mut db).unwrap();
+        return db;
+    }
+
+    /// Titles found by the search `input` in the server `1`, best first
+    fn titles(db: &mut SqliteDatabase, input: &str) -> Vec<String> {
+        let query = SearchQuery::parse(input, "en").unwrap().scoped_to("1");
+        return db
+            .select_resources(&query, 10, 0)
+            .into_iter()
+            .map(|resource| resource.title)
+            .collect();
+    }
+
+    /// Saves `title` at `https://example.com/{path}`, returns its id
+    fn save(db: &mut SqliteDatabase, path: &str, title: &str) -> i64 {
+        let resource = Resource {
+            guild_id: "1".to_string(),
+            user_id: "10".to_string(),
+            url: format!("https://example.com/{}", path),
+            title: title.to_string(),
+            shash: title.to_string(),
+            type_id: 10,
+            ..Default::default()
+        };
+        assert!(db.insert_resource(resource));
+        return db.db.last_insert_rowid();
+    }
+
+    #[test]
+    fn escapes_like_wildcards() {
+        assert_eq!(escape_like("100%_a\\b"), "100\\%\\_a\\\\b");
+    }
+
+    #[test]
+    fn searches_terms_literally() {
+        let mut db = database();
+        save(&mut db, "a", "100% rust");
+        save(&mut db, "b", "1000 rust");
+        save(&mut db, "c", "snake_case");
+        save(&mut db, "d", "snakescase");
+
+        assert_eq!(titles(&mut db, "100%"), vec!["100% rust"]);
+        assert_eq!(titles(&mut db, "snake_case"), vec!["snake_case"]);
+        assert_eq!(titles(&mut db, "rust -100%"), vec!["1000 rust"]);
+    }
+
+    #[test]
+    fn ranks_by_relevance_and_votes() {
+        let mut db = database();
+        save(&mut db, "a", "rust");
+        let twice = save(&mut db, "b", "rust and more rust");
+        let voted = save(&mut db, "c", "rust voted");
+        for user in &["1", "2", "3", "4"] {
+            assert!(db.rate_resource(voted, user, 5));
+        }
+        // Twice as relevant but badly rated
+        for user in &["1", "2"] {
+            assert!(db.rate_resource(twice, user, 1));
+        }
+
+        assert_eq!(
+            titles(&mut db, "rust"),
+            vec!["rust voted", "rust and more rust", "rust"]
+        );
+    }
+
+    #[test]
+    fn searches_with_only_filters() {
+        let mut db = database();
+        save(&mut db, "a", "rust");
+        let voted = save(&mut db, "b", "python");
+        assert!(db.rate_resource(voted, "1", 5));
+
+        assert_eq!(titles(&mut db, "type:link"), vec!["python", "rust"]);
+        // No server shares its resources
+        assert!(titles(&mut db, "in:global").is_empty());
+    }
+}
@@ -0,0 +1,64 @@
+/// Longest tag we keep, longer ones are cut
+const MAX_TAG_LENGTH: usize = 32;
+
+/// Normal form of a tag: lowercase, without the leading `#`, only letters,
+/// digits and `-_+.`. `None` when nothing is left.
+pub fn normalize(tag: &str) -> Option<String> {
+    let tag: String = tag
+        .trim()
+        .trim_start_matches('#')
+        .to_lowercase()
+        .chars()
+        .filter(|character| character.is_alphanumeric() || "-_+.".contains(*character))
+        .take(MAX_TAG_LENGTH)
+        .collect();
+    let tag = tag.trim_matches('.').to_string();
+
+    if tag.is_empty() {
+        return None;
+    }
+    return Some(tag);
+}
+
+/// Normalized `#hashtags` written in `text`, without repetitions. Channel
+/// mentions (`<#123>`) and url fragments are not hashtags.
+pub fn find_hashtags(text: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+
+    for word in text.split_whitespace() {
+        if !word.starts_with('#') {
+            continue;
+        }
+        if let Some(tag) = normalize(word) {
+            if !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+    }
+    return tags;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_tags() {
+        assert_eq!(normalize(" #Rust "), Some("rust".to_string()));
+        assert_eq!(normalize("C++"), Some("c++".to_string()));
+        assert_eq!(normalize("node.js."), Some("node.js".to_string()));
+        assert_eq!(normalize("Año!"), Some("año".to_string()));
+        assert_eq!(normalize("#"), None);
+        assert_eq!(normalize("..."), None);
+        assert_eq!(normalize(&"a".repeat(40)).unwrap().len(), MAX_TAG_LENGTH);
+    }
+
+    #[test]
+    fn finds_hashtags_once() {
+        assert_eq!(
+            find_hashtags("#Rust async #rust #web, <#123> https://example.com/#top # #"),
+            vec!["rust", "web"]
+        );
+        assert!(find_hashtags("no tags here").is_empty());
+    }
+}
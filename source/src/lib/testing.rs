@@ -0,0 +1,247 @@
+use super::bot_state::BotState;
+use super::canonical_url::content_hash;
+use super::commands::response::{Response, ERROR_COLOUR};
+use super::commands::{self, registry};
+use super::memory_database::MemoryDatabase;
+use super::resource::Resource;
+use super::resource_store::ResourceStore;
+use chrono::Utc;
+use discord::model::{Message, ReadyEvent, ServerId};
+use discord::{Discord, State};
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// The server the tests run in, only its cache knows about it
+pub const GUILD_ID: u64 = 1000;
+pub const CHANNEL_ID: u64 = 2000;
+/// A channel of the same server, named `links`
+pub const OTHER_CHANNEL_ID: u64 = 2001;
+/// A channel the cache doesn't know, like private messages
+pub const PRIVATE_CHANNEL_ID: u64 = 2999;
+/// Another server the bot is in, with a single channel
+pub const FOREIGN_GUILD_ID: u64 = 5000;
+pub const FOREIGN_CHANNEL_ID: u64 = 5001;
+/// Owner of the server, an admin
+pub const OWNER_ID: u64 = 3000;
+/// Member with the `curators` role
+pub const CURATOR_ID: u64 = 3001;
+pub const MEMBER_ID: u64 = 3002;
+pub const BOT_ID: u64 = 3999;
+pub const CURATORS_ROLE_ID: u64 = 4000;
+
+/// Id of the next message built, every message gets its own
+static NEXT_MESSAGE_ID: AtomicU64 = AtomicU64::new(1);
+
+fn user(id: u64, name: &str) -> Value {
+    return json!({
+        "id": id.to_string(),
+        "username": name,
+        "discriminator": "0001",
+        "avatar": null,
+        "bot": false,
+    });
+}
+
+fn member(id: u64, name: &str, roles: &[u64]) -> Value {
+    return json!({
+        "user": user(id, name),
+        "roles": roles.iter().map(|role| role.to_string()).collect::<Vec<String>>(),
+        "nick": null,
+        "joined_at": "2021-01-01T00:00:00+00:00",
+        "mute": false,
+        "deaf": false,
+    });
+}
+
+fn channel(guild_id: u64, id: u64, name: &str) -> Value {
+    return json!({
+        "id": id.to_string(),
+        "guild_id": guild_id.to_string(),
+        "name": name,
+        "type": 0,
+        "position": 0,
+        "permission_overwrites": [],
+        "topic": null,
+        "last_message_id": null,
+        "last_pin_timestamp": null,
+        "nsfw": false,
+    });
+}
+
+/// A server owned by `OWNER_ID` as the ready event describes it
+fn server(
+    id: u64,
+    name: &str,
+    roles: Vec<Value>,
+    members: Vec<Value>,
+    channels: Vec<Value>,
+) -> Value {
+    return json!({
+        "id": id.to_string(),
+        "name": name,
+        "owner_id": OWNER_ID.to_string(),
+        "region": "us-east",
+        "icon": null,
+        "splash": null,
+        "afk_timeout": 300,
+        "afk_channel_id": null,
+        "verification_level": 0,
+        "default_message_notifications": 0,
+        "mfa_level": 0,
+        "large": false,
+        "member_count": members.len(),
+        "joined_at": "2021-01-01T00:00:00+00:00",
+        "features": [],
+        "emojis": [],
+        "voice_states": [],
+        "presences": [],
+        "roles": roles,
+        "members": members,
+        "channels": channels,
+    });
+}
+
+fn role(id: u64, name: &str, position: u64) -> Value {
+    return json!({
+        "id": id.to_string(),
+        "name": name,
+        "color": 0,
+        "hoist": false,
+        "managed": false,
+        "position": position,
+        "mentionable": position > 0,
+        "permissions": 0,
+    });
+}
+
+/// What discord sends when the bot connects, with the test server and
+/// the foreign one in it
+fn ready_event() -> ReadyEvent {
+    let tests = server(
+        GUILD_ID,
+        "Tests",
+        vec![
+            role(GUILD_ID, "@everyone", 0),
+            role(CURATORS_ROLE_ID, "curators", 1),
+        ],
+        vec![
+            member(OWNER_ID, "owner", &[]),
+            member(CURATOR_ID, "curator", &[CURATORS_ROLE_ID]),
+            member(MEMBER_ID, "member", &[]),
+            member(BOT_ID, "bot", &[]),
+        ],
+        vec![
+            channel(GUILD_ID, CHANNEL_ID, "general"),
+            channel(GUILD_ID, OTHER_CHANNEL_ID, "links"),
+        ],
+    );
+    let foreign = server(
+        FOREIGN_GUILD_ID,
+        "Foreign",
+        vec![role(FOREIGN_GUILD_ID, "@everyone", 0)],
+        vec![member(OWNER_ID, "owner", &[]), member(BOT_ID, "bot", &[])],
+        vec![channel(FOREIGN_GUILD_ID, FOREIGN_CHANNEL_ID, "general")],
+    );
+
+    return serde_json::from_value(json!({
+        "v": 9,
+        "user": {
+            "id": BOT_ID.to_string(),
+            "username": "bot",
+            "discriminator": "0001",
+            "avatar": null,
+            "email": null,
+            "verified": true,
+            "bot": true,
+            "mfa_enabled": false,
+        },
+        "session_id": "tests",
+        "private_channels": [],
+        "presences": [],
+        "relationships": [],
+        "guilds": [tests, foreign],
+        "_trace": [],
+    }))
+    .expect("A valid ready event");
+}
+
+/// A bot over `MemoryDatabase` that knows the test server. It never
+/// reaches discord, the commands tested don't need to.
+pub fn bot() -> BotState {
+    let discord = Discord::from_bot_token("tests").expect("A client without connecting");
+    return BotState::new(
+        Arc::new(discord),
+        State::new(ready_event()),
+        Box::new(MemoryDatabase::new()),
+        None,
+        Some("https://example.com".to_string()),
+    );
+}
+
+/// A message of `author_id` in `channel_id`, with the attachments and
+/// embeds given as discord sends them
+pub fn message_with(
+    channel_id: u64,
+    author_id: u64,
+    content: &str,
+    attachments: Value,
+    embeds: Value,
+) -> Message {
+    return serde_json::from_value(json!({
+        "id": NEXT_MESSAGE_ID.fetch_add(1, Ordering::Relaxed).to_string(),
+        "channel_id": channel_id.to_string(),
+        "content": content,
+        "nonce": null,
+        "tts": false,
+        "timestamp": Utc::now().to_rfc3339(),
+        "edited_timestamp": null,
+        "pinned": false,
+        "type": 0,
+        "author": user(author_id, &format!("user{}", author_id)),
+        "mention_everyone": false,
+        "mentions": [],
+        "mention_roles": [],
+        "reactions": [],
+        "attachments": attachments,
+        "embeds": embeds,
+    }))
+    .expect("A valid message");
+}
+
+/// A plain text message of `author_id` in the test channel
+pub fn message(author_id: u64, content: &str) -> Message {
+    return message_with(CHANNEL_ID, author_id, content, json!([]), json!([]));
+}
+
+/// Reply of the bot to `message`, dispatched like `bot.rs` does
+pub fn run_message(state: &mut BotState, message: &Message) -> Response {
+    return commands::run_message(&registry(), state, message).unwrap_or(Response::Nothing);
+}
+
+/// Runs the command `content` of `author_id` in the test channel
+pub fn run(state: &mut BotState, author_id: u64, content: &str) -> Response {
+    return run_message(state, &message(author_id, content));
+}
+
+/// Saves `url` in the test server as `user_id` would, returns its id
+pub fn saved(state: &mut BotState, user_id: u64, url: &str, title: &str) -> i64 {
+    let mut resource = Resource::new(&message(user_id, url), &ServerId(GUILD_ID));
+    resource.url = url.to_string();
+    resource.title = title.to_string();
+    resource.shash = content_hash(url);
+    assert!(state.db.insert_resource(resource.clone()));
+    return state
+        .db
+        .select_resource_by_hash(&GUILD_ID.to_string(), &resource.shash)
+        .expect("The resource just saved")
+        .id;
+}
+
+/// Replies made with `Response::error`
+pub fn is_error(response: &Response) -> bool {
+    return match response {
+        Response::Embed(embed) => embed.colour == ERROR_COLOUR,
+        _ => false,
+    };
+}
@@ -3,9 +3,12 @@ mod lib;
 use actix_files as fs;
 use actix_web::{web, App, HttpServer};
 use lib::app_state;
-use lib::custom_database::DiscordDatabase;
+use lib::endpoints::collection_page::{collection_json, collection_page};
 use lib::endpoints::index::index;
+use lib::endpoints::resource_page::resource_page;
 use lib::endpoints::search::resource_query;
+use lib::migrations;
+use lib::resource_store;
 use std::env;
 use std::sync::Mutex;
 use tera::Tera;
@@ -20,9 +23,12 @@ async fn main() -> std::io::Result<()> {
     let port = port.parse::<u16>().unwrap();
     let database_uri = env::var("DATABASE_URI").expect("Expected a token in the environment");
 
+    let mut db = resource_store::connect(database_uri);
+    migrations::migrate(db.as_mut()).expect("Failed to apply migrations");
+
     let state = web::Data::new(app_state::AppState {
         // Setting database
-        bd: Mutex::new(DiscordDatabase::new(database_uri)),
+        bd: Mutex::new(db),
     });
 
     println!("Server start at http://{}:{}", host, port);
@@ -34,6 +40,11 @@ async fn main() -> std::io::Result<()> {
             .app_data(state.clone())
             .data(tera)
             .service(fs::Files::new("/static", "./static"))
+            .service(web::resource("/guild/{guild}/resource/{id}").to(resource_page))
+            .service(web::resource("/guild/{guild}/collection/{id}/json").to(collection_json))
+            .service(web::resource("/guild/{guild}/collection/{id}").to(collection_page))
+            .service(web::resource("/guild/{guild}/{query}/{page}").to(resource_query))
+            .service(web::resource("/guild/{guild}/{query}").to(resource_query))
             .service(web::resource("/{query}/{page}").to(resource_query))
             .service(web::resource("/{query}").to(resource_query))
             .service(web::resource("/").to(index))